@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS update_slug_redirects_updated_at ON slug_redirects;
+DROP TABLE IF EXISTS slug_redirects;
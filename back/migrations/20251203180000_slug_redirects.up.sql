@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS slug_redirects (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR NOT NULL UNIQUE,
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS slug_redirects_post_id_idx ON slug_redirects (post_id);
+
+CREATE TRIGGER update_slug_redirects_updated_at
+BEFORE UPDATE ON slug_redirects
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
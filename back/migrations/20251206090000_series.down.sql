@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS update_series_posts_updated_at ON series_posts;
+DROP TABLE IF EXISTS series_posts;
+DROP TRIGGER IF EXISTS update_series_updated_at ON series;
+DROP TABLE IF EXISTS series;
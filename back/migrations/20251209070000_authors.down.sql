@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS update_posts_authors_updated_at ON posts_authors;
+DROP TABLE IF EXISTS posts_authors;
+ALTER TABLE users DROP COLUMN IF EXISTS social_links;
+ALTER TABLE users DROP COLUMN IF EXISTS avatar_url;
+ALTER TABLE users DROP COLUMN IF EXISTS bio;
+ALTER TABLE users DROP COLUMN IF EXISTS slug;
@@ -0,0 +1,32 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS slug VARCHAR UNIQUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS bio TEXT DEFAULT '';
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_url VARCHAR;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS social_links JSONB NOT NULL DEFAULT '{}'::jsonb;
+
+UPDATE users u
+SET slug = s.base || CASE WHEN s.n > 1 THEN '-' || s.n ELSE '' END
+FROM (
+    SELECT id, base, ROW_NUMBER() OVER (PARTITION BY base ORDER BY id) n
+    FROM (
+        SELECT id, TRIM(BOTH '-' FROM LOWER(REGEXP_REPLACE(username, '[^a-zA-Z0-9]+', '-', 'g'))) base
+        FROM users
+    ) b
+) s
+WHERE u.id = s.id AND u.slug IS NULL;
+
+CREATE TABLE IF NOT EXISTS posts_authors (
+    id SERIAL PRIMARY KEY,
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (post_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS posts_authors_user_id_idx ON posts_authors (user_id);
+
+CREATE TRIGGER update_posts_authors_updated_at
+BEFORE UPDATE ON posts_authors
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
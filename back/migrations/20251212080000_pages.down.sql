@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS update_pages_updated_at ON pages;
+DROP TABLE IF EXISTS pages;
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS pages (
+    id SERIAL PRIMARY KEY,
+    parent_id INTEGER REFERENCES pages(id) ON DELETE RESTRICT,
+    title VARCHAR NOT NULL,
+    slug VARCHAR NOT NULL,
+    path VARCHAR NOT NULL UNIQUE,
+    markdown TEXT DEFAULT '',
+    meta VARCHAR DEFAULT '',
+    private boolean DEFAULT TRUE,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS pages_parent_id_idx ON pages (parent_id, position);
+
+CREATE TRIGGER update_pages_updated_at
+BEFORE UPDATE ON pages
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
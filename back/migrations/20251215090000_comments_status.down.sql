@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS comments_post_id_idx;
+DROP INDEX IF EXISTS comments_status_idx;
+ALTER TABLE comments DROP CONSTRAINT IF EXISTS comments_status_check;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS approved boolean;
+UPDATE comments SET approved = CASE
+    WHEN status = 'approved' THEN TRUE
+    WHEN status = 'pending' THEN NULL
+    ELSE FALSE
+END;
+ALTER TABLE comments DROP COLUMN IF EXISTS status;
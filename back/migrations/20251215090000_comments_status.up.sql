@@ -0,0 +1,14 @@
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS status VARCHAR NOT NULL DEFAULT 'pending';
+
+UPDATE comments SET status = CASE
+    WHEN approved IS TRUE THEN 'approved'
+    WHEN approved IS FALSE THEN 'rejected'
+    ELSE 'pending'
+END;
+
+ALTER TABLE comments DROP COLUMN IF EXISTS approved;
+ALTER TABLE comments ADD CONSTRAINT comments_status_check
+    CHECK (status IN ('pending', 'approved', 'rejected', 'spam'));
+
+CREATE INDEX IF NOT EXISTS comments_status_idx ON comments (status, created_at);
+CREATE INDEX IF NOT EXISTS comments_post_id_idx ON comments (post_id);
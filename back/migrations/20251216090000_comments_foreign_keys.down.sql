@@ -0,0 +1,2 @@
+ALTER TABLE comments DROP CONSTRAINT IF EXISTS comments_parent_id_fkey;
+ALTER TABLE comments DROP CONSTRAINT IF EXISTS comments_post_id_fkey;
@@ -0,0 +1,8 @@
+DELETE FROM comments WHERE post_id NOT IN (SELECT id FROM posts);
+UPDATE comments SET parent_id = NULL
+WHERE parent_id IS NOT NULL AND parent_id NOT IN (SELECT id FROM comments);
+
+ALTER TABLE comments ADD CONSTRAINT comments_post_id_fkey
+    FOREIGN KEY (post_id) REFERENCES posts(id) ON DELETE CASCADE;
+ALTER TABLE comments ADD CONSTRAINT comments_parent_id_fkey
+    FOREIGN KEY (parent_id) REFERENCES comments(id) ON DELETE CASCADE;
@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS spam_documents;
+DROP TABLE IF EXISTS spam_tokens;
+
+ALTER TABLE comments DROP COLUMN IF EXISTS trained_as;
+ALTER TABLE comments DROP COLUMN IF EXISTS spam_score;
+ALTER TABLE comments DROP COLUMN IF EXISTS user_agent;
+ALTER TABLE comments DROP COLUMN IF EXISTS author_ip;
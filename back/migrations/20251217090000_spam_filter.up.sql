@@ -0,0 +1,18 @@
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS author_ip VARCHAR;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS user_agent VARCHAR;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS spam_score REAL;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS trained_as VARCHAR;
+
+CREATE TABLE IF NOT EXISTS spam_tokens (
+    token VARCHAR PRIMARY KEY,
+    spam INTEGER NOT NULL DEFAULT 0,
+    ham INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS spam_documents (
+    category VARCHAR PRIMARY KEY,
+    total INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO spam_documents (category, total) VALUES ('spam', 0), ('ham', 0)
+ON CONFLICT (category) DO NOTHING;
@@ -0,0 +1,2 @@
+-- Se rellena al arrancar para los comentarios existentes
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS html_content TEXT;
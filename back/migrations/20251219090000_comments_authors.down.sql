@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS comments_email_idx;
+
+ALTER TABLE comments DROP COLUMN IF EXISTS user_id;
+ALTER TABLE comments DROP COLUMN IF EXISTS avatar_url;
+ALTER TABLE comments DROP COLUMN IF EXISTS website;
+ALTER TABLE comments DROP COLUMN IF EXISTS email;
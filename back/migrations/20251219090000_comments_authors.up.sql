@@ -0,0 +1,6 @@
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS email VARCHAR;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS website VARCHAR;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS avatar_url VARCHAR;
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS user_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS comments_email_idx ON comments (LOWER(email));
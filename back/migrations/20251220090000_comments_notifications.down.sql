@@ -0,0 +1,3 @@
+ALTER TABLE comments DROP COLUMN IF EXISTS unsubscribe_token;
+ALTER TABLE comments DROP COLUMN IF EXISTS reply_notified;
+ALTER TABLE comments DROP COLUMN IF EXISTS notify_replies;
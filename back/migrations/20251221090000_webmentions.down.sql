@@ -0,0 +1,7 @@
+DROP TRIGGER IF EXISTS update_webmentions_updated_at ON webmentions;
+DROP TABLE IF EXISTS webmentions;
+
+DROP INDEX IF EXISTS comments_webmention_idx;
+ALTER TABLE comments DROP CONSTRAINT IF EXISTS comments_kind_check;
+ALTER TABLE comments DROP COLUMN IF EXISTS source_url;
+ALTER TABLE comments DROP COLUMN IF EXISTS kind;
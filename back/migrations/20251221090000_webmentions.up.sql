@@ -0,0 +1,27 @@
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS kind VARCHAR NOT NULL DEFAULT 'comment';
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS source_url VARCHAR;
+ALTER TABLE comments ADD CONSTRAINT comments_kind_check
+    CHECK (kind IN ('comment', 'webmention'));
+CREATE UNIQUE INDEX IF NOT EXISTS comments_webmention_idx
+    ON comments (post_id, source_url) WHERE kind = 'webmention';
+
+CREATE TABLE IF NOT EXISTS webmentions (
+    id SERIAL PRIMARY KEY,
+    direction VARCHAR NOT NULL CHECK (direction IN ('incoming', 'outgoing')),
+    source VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'done', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (direction, source, target)
+);
+
+CREATE INDEX IF NOT EXISTS webmentions_due_idx ON webmentions (status, next_attempt_at);
+
+CREATE TRIGGER update_webmentions_updated_at
+BEFORE UPDATE ON webmentions
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
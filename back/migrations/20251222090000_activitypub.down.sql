@@ -0,0 +1,12 @@
+DROP TRIGGER IF EXISTS update_activitypub_followers_updated_at ON activitypub_followers;
+DROP TABLE IF EXISTS activitypub_followers;
+DROP TABLE IF EXISTS activitypub_keys;
+ALTER TABLE posts DROP COLUMN IF EXISTS federated_at;
+
+DELETE FROM comments WHERE kind = 'activitypub';
+DROP INDEX IF EXISTS comments_source_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS comments_webmention_idx
+    ON comments (post_id, source_url) WHERE kind = 'webmention';
+ALTER TABLE comments DROP CONSTRAINT IF EXISTS comments_kind_check;
+ALTER TABLE comments ADD CONSTRAINT comments_kind_check
+    CHECK (kind IN ('comment', 'webmention'));
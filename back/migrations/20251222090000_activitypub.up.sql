@@ -0,0 +1,29 @@
+ALTER TABLE comments DROP CONSTRAINT IF EXISTS comments_kind_check;
+ALTER TABLE comments ADD CONSTRAINT comments_kind_check
+    CHECK (kind IN ('comment', 'webmention', 'activitypub'));
+DROP INDEX IF EXISTS comments_webmention_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS comments_source_idx
+    ON comments (kind, post_id, source_url) WHERE source_url IS NOT NULL;
+
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS federated_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE IF NOT EXISTS activitypub_keys (
+    id SERIAL PRIMARY KEY,
+    public_key_pem TEXT NOT NULL,
+    private_key_pem TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS activitypub_followers (
+    id SERIAL PRIMARY KEY,
+    actor VARCHAR NOT NULL UNIQUE,
+    inbox VARCHAR NOT NULL,
+    shared_inbox VARCHAR,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER update_activitypub_followers_updated_at
+BEFORE UPDATE ON activitypub_followers
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS post_reactions_daily;
+DROP TABLE IF EXISTS post_views_daily;
+DROP TABLE IF EXISTS post_visitors;
@@ -0,0 +1,29 @@
+-- Visitantes del día (hash de IP + user agent con sal diaria) para no
+-- contar dos veces la misma visita o reacción. Se purga a diario.
+CREATE TABLE IF NOT EXISTS post_visitors (
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    day DATE NOT NULL DEFAULT CURRENT_DATE,
+    action VARCHAR NOT NULL,
+    visitor_hash VARCHAR(64) NOT NULL,
+    PRIMARY KEY (post_id, day, action, visitor_hash)
+);
+
+CREATE INDEX IF NOT EXISTS post_visitors_day_idx ON post_visitors (day);
+
+CREATE TABLE IF NOT EXISTS post_views_daily (
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    views INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (post_id, day)
+);
+
+CREATE TABLE IF NOT EXISTS post_reactions_daily (
+    post_id INTEGER NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    reaction VARCHAR NOT NULL,
+    total INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (post_id, day, reaction)
+);
+
+CREATE INDEX IF NOT EXISTS post_views_daily_day_idx ON post_views_daily (day);
+CREATE INDEX IF NOT EXISTS post_reactions_daily_day_idx ON post_reactions_daily (day);
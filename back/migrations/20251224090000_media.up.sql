@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS media (
+    id SERIAL PRIMARY KEY,
+    path VARCHAR NOT NULL UNIQUE,
+    original_filename VARCHAR,
+    mime_type VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    width INTEGER,
+    height INTEGER,
+    checksum VARCHAR(64) NOT NULL,
+    uploader_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    alt_text VARCHAR,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS media_checksum_idx ON media (checksum);
+CREATE INDEX IF NOT EXISTS media_created_at_idx ON media (created_at);
+
+CREATE TRIGGER update_media_updated_at
+BEFORE UPDATE ON media
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS media_orphaned_at_idx;
+ALTER TABLE media DROP COLUMN IF EXISTS orphaned_at;
@@ -0,0 +1,3 @@
+ALTER TABLE media ADD COLUMN IF NOT EXISTS orphaned_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS media_orphaned_at_idx ON media (orphaned_at) WHERE orphaned_at IS NOT NULL;
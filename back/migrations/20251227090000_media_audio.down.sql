@@ -0,0 +1,2 @@
+ALTER TABLE media DROP COLUMN IF EXISTS bitrate;
+ALTER TABLE media DROP COLUMN IF EXISTS duration;
@@ -0,0 +1,2 @@
+ALTER TABLE media ADD COLUMN IF NOT EXISTS duration INTEGER;
+ALTER TABLE media ADD COLUMN IF NOT EXISTS bitrate INTEGER;
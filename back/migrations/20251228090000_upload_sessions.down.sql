@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS update_upload_sessions_updated_at ON upload_sessions;
+DROP INDEX IF EXISTS upload_sessions_updated_at_idx;
+DROP TABLE IF EXISTS upload_sessions;
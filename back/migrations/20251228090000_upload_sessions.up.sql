@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    id VARCHAR(36) PRIMARY KEY,
+    filename VARCHAR,
+    length BIGINT NOT NULL,
+    mime_type VARCHAR,
+    uploader_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    media_id INTEGER REFERENCES media(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS upload_sessions_updated_at_idx ON upload_sessions (updated_at);
+
+CREATE TRIGGER update_upload_sessions_updated_at
+BEFORE UPDATE ON upload_sessions
+FOR EACH ROW
+EXECUTE PROCEDURE update_updated_at_column();
@@ -0,0 +1 @@
+ALTER TABLE posts ADD COLUMN IF NOT EXISTS cover_media_id INTEGER REFERENCES media(id) ON DELETE SET NULL;
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS media_uploader_id_idx;
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS media_uploader_id_idx ON media (uploader_id);
@@ -0,0 +1,478 @@
+mod signature;
+
+use std::time::SystemTime;
+
+use axum::http::HeaderMap;
+use once_cell::sync::Lazy;
+use reqwest::{Method, Url, header};
+use serde_json::{Value, json};
+use sqlx::postgres::PgPool;
+use tracing::{debug, error, info};
+
+use crate::constants::{
+    ACTIVITYPUB_SIGNATURE_MAX_AGE_SECONDS, DEFAULT_ACTIVITYPUB_USERNAME, DEFAULT_SITE_DESCRIPTION,
+    DEFAULT_SITE_TITLE, FEED_LIMIT,
+};
+use crate::models::{ActorKey, AppState, Comment, CommentKind, Follower, Post, RemoteComment, Setting};
+use crate::utils::{html_to_text, markdown_to_html, parse_public_url, public_client, read_limited};
+use crate::webmention::target_post;
+use signature::SignatureHeader;
+
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+// Las peticiones van firmadas con la ruta, así que no se siguen redirecciones
+static CLIENT: Lazy<reqwest::Client> =
+    Lazy::new(|| public_client(concat!("blog-activitypub/", env!("CARGO_PKG_VERSION")), 0));
+// Tamaño máximo de un documento remoto (actor, nota...)
+const MAX_DOCUMENT_SIZE: usize = 1024 * 1024;
+
+/// Crea las claves del actor la primera vez que arranca el servidor.
+pub async fn ensure_keys(pool: &PgPool) -> Result<(), String> {
+    if ActorKey::read(pool).await.map_err(|e| format!("{:?}", e))?.is_some() {
+        return Ok(());
+    }
+    let (public_key_pem, private_key_pem) = tokio::task::spawn_blocking(signature::generate_keys)
+        .await
+        .map_err(|e| format!("{:?}", e))??;
+    ActorKey::create(pool, &public_key_pem, &private_key_pem)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    info!("ActivityPub keys generated");
+    Ok(())
+}
+
+pub fn actor_url(base_url: &str) -> String {
+    format!("{}/ap/actor", base_url)
+}
+
+fn article_url(base_url: &str, post_id: i32) -> String {
+    format!("{}/ap/posts/{}", base_url, post_id)
+}
+
+/// `host[:puerto]` del sitio, el dominio de la dirección `@usuario@dominio`.
+pub fn domain(base_url: &str) -> String {
+    Url::parse(base_url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            })
+        })
+        .unwrap_or_default()
+}
+
+pub async fn username(pool: &PgPool) -> String {
+    Setting::get_value(pool, "activitypub_username", DEFAULT_ACTIVITYPUB_USERNAME).await
+}
+
+/// Respuesta de WebFinger para `acct:usuario@dominio` o la URL del actor.
+pub async fn webfinger(app_state: &AppState, resource: &str) -> Option<Value> {
+    let username = username(&app_state.pool).await;
+    let subject = format!("acct:{}@{}", username, domain(&app_state.base_url));
+    let actor = actor_url(&app_state.base_url);
+    if resource != subject && resource != actor {
+        return None;
+    }
+    Some(json!({
+        "subject": subject,
+        "aliases": [actor],
+        "links": [
+            {"rel": "self", "type": ACTIVITY_JSON, "href": actor},
+            {"rel": "http://webfinger.net/rel/profile-page", "type": "text/html", "href": app_state.base_url},
+        ],
+    }))
+}
+
+pub async fn actor(app_state: &AppState) -> Result<Value, String> {
+    let key = ActorKey::read(&app_state.pool)
+        .await
+        .map_err(|e| format!("{:?}", e))?
+        .ok_or("ActivityPub keys not generated")?;
+    let base_url = &app_state.base_url;
+    let actor = actor_url(base_url);
+    let title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+    let description = Setting::get_value(&app_state.pool, "site_description", DEFAULT_SITE_DESCRIPTION).await;
+    Ok(json!({
+        "@context": ["https://www.w3.org/ns/activitystreams", "https://w3id.org/security/v1"],
+        "id": actor,
+        "type": "Person",
+        "preferredUsername": username(&app_state.pool).await,
+        "name": title,
+        "summary": description,
+        "url": base_url,
+        "inbox": format!("{}/ap/inbox", base_url),
+        "outbox": format!("{}/ap/outbox", base_url),
+        "followers": format!("{}/ap/followers", base_url),
+        "endpoints": {"sharedInbox": format!("{}/ap/inbox", base_url)},
+        "manuallyApprovesFollowers": false,
+        "discoverable": true,
+        "publicKey": {
+            "id": format!("{}#main-key", actor),
+            "owner": actor,
+            "publicKeyPem": key.public_key_pem,
+        },
+    }))
+}
+
+pub fn article(base_url: &str, post: &Post) -> Value {
+    let actor = actor_url(base_url);
+    json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": article_url(base_url, post.id),
+        "type": "Article",
+        "name": post.title,
+        "summary": post.excerpt,
+        "content": markdown_to_html(&post.content),
+        "url": format!("{}/{}", base_url, post.slug),
+        "attributedTo": actor,
+        "published": post.published_at,
+        "updated": post.updated_at,
+        "to": [PUBLIC],
+        "cc": [format!("{}/ap/followers", base_url)],
+    })
+}
+
+fn create(base_url: &str, post: &Post) -> Value {
+    let object = article(base_url, post);
+    json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{}#create", object["id"].as_str().unwrap_or_default()),
+        "type": "Create",
+        "actor": actor_url(base_url),
+        "published": post.published_at,
+        "to": object["to"],
+        "cc": object["cc"],
+        "object": object,
+    })
+}
+
+fn update(base_url: &str, post: &Post) -> Value {
+    let object = article(base_url, post);
+    json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{}#update-{}", object["id"].as_str().unwrap_or_default(), post.updated_at.timestamp()),
+        "type": "Update",
+        "actor": actor_url(base_url),
+        "to": object["to"],
+        "cc": object["cc"],
+        "object": object,
+    })
+}
+
+/// Últimos posts publicados como actividades `Create`.
+pub async fn outbox(app_state: &AppState) -> Result<Value, String> {
+    let posts = Post::read_published(&app_state.pool, FEED_LIMIT, None)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    let items: Vec<Value> = posts.iter().map(|post| create(&app_state.base_url, post)).collect();
+    Ok(json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{}/ap/outbox", app_state.base_url),
+        "type": "OrderedCollection",
+        "totalItems": items.len(),
+        "orderedItems": items,
+    }))
+}
+
+pub async fn followers(app_state: &AppState) -> Result<Value, String> {
+    let total = Follower::count(&app_state.pool).await.map_err(|e| format!("{:?}", e))?;
+    Ok(json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{}/ap/followers", app_state.base_url),
+        "type": "OrderedCollection",
+        "totalItems": total,
+    }))
+}
+
+/// Envía el post a los seguidores: `Create` la primera vez y `Update` en
+/// las siguientes ediciones.
+pub async fn publish_post(app_state: &AppState, post: &Post) {
+    if !post.is_public() {
+        return;
+    }
+    let activity = match Post::mark_federated(&app_state.pool, post.id).await {
+        Ok(true) => create(&app_state.base_url, post),
+        Ok(false) => update(&app_state.base_url, post),
+        Err(e) => {
+            error!("Error marking post {} as federated: {:?}", post.id, e);
+            return;
+        }
+    };
+    let inboxes = match Follower::inboxes(&app_state.pool).await {
+        Ok(inboxes) => inboxes,
+        Err(e) => {
+            error!("Error reading followers: {:?}", e);
+            return;
+        }
+    };
+    for inbox in inboxes {
+        deliver(app_state, &inbox, &activity).await;
+    }
+}
+
+async fn deliver(app_state: &AppState, inbox: &str, activity: &Value) {
+    let Some(url) = parse_public_url(inbox) else {
+        error!("Invalid inbox {}", inbox);
+        return;
+    };
+    let body = activity.to_string().into_bytes();
+    match signed_request(app_state, Method::POST, url, Some(body)).await {
+        Ok(response) if response.status().is_success() => {
+            debug!("Activity {} delivered to {}", activity["type"], inbox);
+        }
+        Ok(response) => error!("Inbox {} returned {}", inbox, response.status()),
+        Err(e) => error!("Error delivering to {}: {}", inbox, e),
+    }
+}
+
+/// Petición firmada con la clave del actor; algunas instancias exigen
+/// firma incluso para leer.
+async fn signed_request(
+    app_state: &AppState,
+    method: Method,
+    url: Url,
+    body: Option<Vec<u8>>,
+) -> Result<reqwest::Response, String> {
+    let key = ActorKey::read(&app_state.pool)
+        .await
+        .map_err(|e| format!("{:?}", e))?
+        .ok_or("ActivityPub keys not generated")?;
+    let host = domain(url.as_str());
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let date = httpdate::fmt_http_date(SystemTime::now());
+    let digest = body.as_deref().map(signature::digest);
+    let mut signed = vec![("host", host.as_str()), ("date", date.as_str())];
+    if let Some(digest) = &digest {
+        signed.push(("digest", digest));
+    }
+    let key_id = format!("{}#main-key", actor_url(&app_state.base_url));
+    let signature = signature::sign(&key.private_key_pem, &key_id, method.as_str(), &path, &signed)?;
+    let mut request = CLIENT
+        .request(method, url)
+        .header(header::ACCEPT, ACTIVITY_JSON)
+        .header(header::DATE, &date)
+        .header("Signature", signature);
+    if let (Some(body), Some(digest)) = (body, digest) {
+        request = request
+            .header(header::CONTENT_TYPE, ACTIVITY_JSON)
+            .header("Digest", digest)
+            .body(body);
+    }
+    request.send().await.map_err(|e| format!("{:?}", e))
+}
+
+async fn fetch(app_state: &AppState, url: &str) -> Result<Value, String> {
+    let url = parse_public_url(url).ok_or_else(|| format!("Invalid url {}", url))?;
+    let response = signed_request(app_state, Method::GET, url, None).await?;
+    if !response.status().is_success() {
+        return Err(format!("Fetch returned {}", response.status()));
+    }
+    let body = read_limited(response, MAX_DOCUMENT_SIZE).await?;
+    serde_json::from_slice(&body).map_err(|e| format!("{:?}", e))
+}
+
+/// Verifica la firma HTTP de una petición al inbox y devuelve el actor que
+/// la firma.
+pub async fn verify_request(
+    app_state: &AppState,
+    method: &str,
+    path: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Value, String> {
+    let value = headers
+        .get("signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or("Missing Signature header")?;
+    let signature = SignatureHeader::parse(value)?;
+    for required in ["(request-target)", "host", "date", "digest"] {
+        if !signature.headers.iter().any(|name| name == required) {
+            return Err(format!("Header {} must be signed", required));
+        }
+    }
+    let date = headers
+        .get(header::DATE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+        .ok_or("Invalid Date header")?;
+    let age = SystemTime::now()
+        .duration_since(date)
+        .or_else(|_| date.duration_since(SystemTime::now()))
+        .unwrap_or_default();
+    if age.as_secs() > ACTIVITYPUB_SIGNATURE_MAX_AGE_SECONDS {
+        return Err("Date out of range".to_string());
+    }
+    let digest = headers.get("digest").and_then(|value| value.to_str().ok());
+    if digest != Some(signature::digest(body).as_str()) {
+        return Err("Digest does not match".to_string());
+    }
+    // La clave tiene que estar en el mismo servidor que el actor de la
+    // actividad, y el documento que se descarga ser de verdad ese actor
+    let activity: Value = serde_json::from_slice(body).map_err(|e| format!("Invalid activity: {:?}", e))?;
+    let activity_actor = id_of(&activity["actor"]).ok_or("Activity without actor")?;
+    if !same_origin(&signature.key_id, activity_actor) {
+        return Err("Key and actor are on different origins".to_string());
+    }
+    let actor_id = signature.key_id.split('#').next().unwrap_or_default();
+    let actor = fetch(app_state, actor_id).await?;
+    if actor["id"].as_str() != Some(actor_id) {
+        return Err("Actor id does not match keyId".to_string());
+    }
+    let key = match &actor["publicKey"] {
+        Value::Array(keys) => keys.iter().find(|key| key["id"] == signature.key_id.as_str()),
+        key if key["id"] == signature.key_id.as_str() => Some(key),
+        _ => None,
+    }
+    .ok_or("Key not found in actor")?;
+    if id_of(&key["owner"]) != Some(actor_id) {
+        return Err("Key not owned by the actor".to_string());
+    }
+    let pem = key["publicKeyPem"].as_str().ok_or("Key without publicKeyPem")?;
+    signature.verify(pem, method, path, headers)?;
+    Ok(actor)
+}
+
+fn id_of(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| value["id"].as_str())
+}
+
+fn same_origin(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin().is_tuple() && a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Procesa una actividad ya verificada: seguimientos, respuestas a los
+/// posts y borrados.
+pub async fn handle_activity(app_state: &AppState, actor: &Value, activity: &Value) -> Result<(), String> {
+    let actor_id = actor["id"].as_str().ok_or("Actor without id")?;
+    if id_of(&activity["actor"]) != Some(actor_id) {
+        return Err("Activity actor does not match signature".to_string());
+    }
+    let object = &activity["object"];
+    match activity["type"].as_str().unwrap_or_default() {
+        "Follow" => {
+            if id_of(object) != Some(actor_url(&app_state.base_url).as_str()) {
+                return Err("Can only follow the site actor".to_string());
+            }
+            let inbox = actor["inbox"].as_str().ok_or("Actor without inbox")?;
+            let shared_inbox = actor["endpoints"]["sharedInbox"].as_str();
+            Follower::upsert(&app_state.pool, actor_id, inbox, shared_inbox)
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+            info!("New follower {}", actor_id);
+            let accept = json!({
+                "@context": "https://www.w3.org/ns/activitystreams",
+                "id": format!("{}#accepts/{}", actor_url(&app_state.base_url), uuid::Uuid::new_v4().simple()),
+                "type": "Accept",
+                "actor": actor_url(&app_state.base_url),
+                "object": activity,
+            });
+            deliver(app_state, inbox, &accept).await;
+        }
+        "Undo" if object["type"] == "Follow" => {
+            Follower::delete(&app_state.pool, actor_id)
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+            info!("Follower removed {}", actor_id);
+        }
+        "Create" | "Update" if object["type"] == "Note" => reply(app_state, actor, object).await?,
+        "Delete" => {
+            let id = id_of(object).ok_or("Delete without object")?;
+            if id == actor_id {
+                Follower::delete(&app_state.pool, actor_id)
+                    .await
+                    .map_err(|e| format!("{:?}", e))?;
+            }
+            Comment::delete_remote(&app_state.pool, CommentKind::Activitypub, None, id, Some(actor_id))
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        other => debug!("Activity {} ignored", other),
+    }
+    Ok(())
+}
+
+/// Una nota que responde a uno de los posts se guarda como comentario
+/// pendiente de moderación.
+async fn reply(app_state: &AppState, actor: &Value, note: &Value) -> Result<(), String> {
+    let actor_id = actor["id"].as_str().unwrap_or_default();
+    if id_of(&note["attributedTo"]) != Some(actor_id) {
+        return Err("Note not attributed to the actor".to_string());
+    }
+    let Some(in_reply_to) = id_of(&note["inReplyTo"]) else {
+        return Ok(());
+    };
+    let Some(post) = reply_target(app_state, in_reply_to).await else {
+        debug!("Reply to {} ignored", in_reply_to);
+        return Ok(());
+    };
+    if !post.comment_on.unwrap_or(false) {
+        return Err(format!("Comments are disabled for post {}", post.id));
+    }
+    let note_id = note["id"].as_str().ok_or("Note without id")?;
+    if !same_origin(note_id, actor_id) {
+        return Err("Note and actor are on different origins".to_string());
+    }
+    let host = Url::parse(actor_id)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_default();
+    let username = actor["preferredUsername"].as_str().unwrap_or("unknown");
+    let remote = RemoteComment {
+        kind: CommentKind::Activitypub,
+        post_id: post.id,
+        source_url: note_id.to_string(),
+        nikename: format!("{}@{}", username, host),
+        website: Some(actor["url"].as_str().unwrap_or(actor_id).to_string()),
+        avatar_url: id_of(&actor["icon"]["url"]).map(|url| url.to_string()),
+        content: html_to_text(note["content"].as_str().unwrap_or_default()),
+        author_url: Some(actor_id.to_string()),
+    };
+    let comment = match Comment::upsert_remote(&app_state.pool, &remote).await {
+        Ok(comment) => comment,
+        Err(sqlx::Error::RowNotFound) => return Err("Note belongs to another actor".to_string()),
+        Err(e) => return Err(format!("{:?}", e)),
+    };
+    app_state.notifier.comment_pending(app_state, &comment).await;
+    Ok(())
+}
+
+/// Post al que responde una nota: por el id del `Article` o por su URL.
+async fn reply_target(app_state: &AppState, in_reply_to: &str) -> Option<Post> {
+    let prefix = format!("{}/ap/posts/", app_state.base_url);
+    match in_reply_to.strip_prefix(&prefix) {
+        Some(id) => {
+            let post = Post::read(&app_state.pool, id.parse().ok()?).await.ok()?;
+            post.is_public().then_some(post)
+        }
+        None => target_post(app_state, in_reply_to).await,
+    }
+}
+
+/// Artículo de un post público por su id.
+pub async fn read_article(app_state: &AppState, post_id: i32) -> Option<Value> {
+    let post = Post::read(&app_state.pool, post_id).await.ok()?;
+    post.is_public().then(|| article(&app_state.base_url, &post))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_origin_compares_scheme_host_and_port() {
+        assert!(same_origin("https://mastodon.social/users/ana#main-key", "https://mastodon.social/users/ana"));
+        assert!(!same_origin("https://evil.example/users/ana#main-key", "https://mastodon.social/users/ana"));
+        assert!(!same_origin("http://mastodon.social/users/ana", "https://mastodon.social/users/ana"));
+        assert!(!same_origin("https://mastodon.social:8443/users/ana", "https://mastodon.social/users/ana"));
+        assert!(!same_origin("not a url", "https://mastodon.social/users/ana"));
+    }
+}
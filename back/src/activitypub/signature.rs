@@ -0,0 +1,121 @@
+use axum::http::HeaderMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use rsa::{
+    RsaPrivateKey, RsaPublicKey,
+    pkcs1::DecodeRsaPublicKey,
+    pkcs1v15::{Signature, SigningKey, VerifyingKey},
+    pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding},
+    sha2::{Digest, Sha256},
+    signature::{SignatureEncoding, Signer, Verifier},
+};
+
+const KEY_BITS: usize = 2048;
+
+/// Cabecera `Signature` de una petición firmada (draft-cavage, rsa-sha256).
+#[derive(Debug)]
+pub struct SignatureHeader {
+    pub key_id: String,
+    pub headers: Vec<String>,
+    pub signature: Vec<u8>,
+}
+
+impl SignatureHeader {
+    pub fn parse(value: &str) -> Result<SignatureHeader, String> {
+        let mut key_id = None;
+        let mut headers = None;
+        let mut signature = None;
+        for param in value.split(',') {
+            let Some((name, value)) = param.trim().split_once('=') else {
+                continue;
+            };
+            let value = value.trim_matches('"');
+            match name {
+                "keyId" => key_id = Some(value.to_string()),
+                "headers" => headers = Some(value.split_whitespace().map(|h| h.to_lowercase()).collect()),
+                "signature" => {
+                    signature = Some(STANDARD.decode(value).map_err(|e| format!("Invalid signature: {:?}", e))?)
+                }
+                "algorithm" if !matches!(value, "rsa-sha256" | "hs2019") => {
+                    return Err(format!("Unsupported algorithm {}", value));
+                }
+                _ => {}
+            }
+        }
+        Ok(SignatureHeader {
+            key_id: key_id.ok_or("Missing keyId")?,
+            // Sin `headers` la especificación firma solo la fecha
+            headers: headers.unwrap_or_else(|| vec!["date".to_string()]),
+            signature: signature.ok_or("Missing signature")?,
+        })
+    }
+
+    /// Comprueba la firma con la clave pública (PEM) de su autor.
+    pub fn verify(&self, public_key_pem: &str, method: &str, path: &str, headers: &HeaderMap) -> Result<(), String> {
+        let public_key = RsaPublicKey::from_public_key_pem(public_key_pem)
+            .or_else(|_| RsaPublicKey::from_pkcs1_pem(public_key_pem))
+            .map_err(|e| format!("Invalid public key: {:?}", e))?;
+        let mut lines = Vec::with_capacity(self.headers.len());
+        for name in &self.headers {
+            let value = if name == "(request-target)" {
+                format!("{} {}", method.to_lowercase(), path)
+            } else {
+                let values: Vec<&str> = headers
+                    .get_all(name.as_str())
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .collect();
+                if values.is_empty() {
+                    return Err(format!("Missing signed header {}", name));
+                }
+                values.join(", ")
+            };
+            lines.push(format!("{}: {}", name, value));
+        }
+        let signature = Signature::try_from(self.signature.as_slice()).map_err(|e| format!("Invalid signature: {:?}", e))?;
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(lines.join("\n").as_bytes(), &signature)
+            .map_err(|_| "Signature does not match".to_string())
+    }
+}
+
+/// Genera el par de claves del actor: (pública, privada) en PEM.
+pub fn generate_keys() -> Result<(String, String), String> {
+    let private_key = RsaPrivateKey::new(&mut rand::rngs::OsRng, KEY_BITS).map_err(|e| format!("{:?}", e))?;
+    let public_pem = private_key
+        .to_public_key()
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|e| format!("{:?}", e))?;
+    let private_pem = private_key.to_pkcs8_pem(LineEnding::LF).map_err(|e| format!("{:?}", e))?;
+    Ok((public_pem, private_pem.to_string()))
+}
+
+/// Valor de la cabecera `Digest` para el cuerpo.
+pub fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", STANDARD.encode(Sha256::digest(body)))
+}
+
+/// Firma la petición con las cabeceras indicadas, en ese orden, y devuelve
+/// el valor de la cabecera `Signature`.
+pub fn sign(
+    private_key_pem: &str,
+    key_id: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+) -> Result<String, String> {
+    let private_key =
+        RsaPrivateKey::from_pkcs8_pem(private_key_pem).map_err(|e| format!("Invalid private key: {:?}", e))?;
+    let mut names = vec!["(request-target)"];
+    let mut lines = vec![format!("(request-target): {} {}", method.to_lowercase(), path)];
+    for (name, value) in headers {
+        names.push(name);
+        lines.push(format!("{}: {}", name, value));
+    }
+    let signature = SigningKey::<Sha256>::new(private_key).sign(lines.join("\n").as_bytes());
+    Ok(format!(
+        r#"keyId="{}",algorithm="rsa-sha256",headers="{}",signature="{}""#,
+        key_id,
+        names.join(" "),
+        STANDARD.encode(signature.to_bytes())
+    ))
+}
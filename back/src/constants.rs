@@ -1,3 +1,79 @@
 // Valores por defecto
 pub const DEFAULT_PAGE: u32 = 1;
 pub const DEFAULT_LIMIT: u32 = 9;
+
+// Valores por defecto del sitio si no están en `settings`
+pub const DEFAULT_SITE_TITLE: &str = "atareao con Linux";
+pub const DEFAULT_SITE_DESCRIPTION: &str = "El podcast sobre Linux y software de código abierto";
+pub const FEED_LIMIT: i64 = 20;
+
+// Niveles de anidamiento de comentarios si no se indica otro
+pub const COMMENTS_MAX_DEPTH: u32 = 5;
+
+// Roles cuyos comentarios se publican sin pasar por moderación
+pub const COMMENTS_TRUSTED_ROLES: [&str; 2] = ["admin", "author"];
+
+// Primeros segmentos de ruta que no pueden usar las páginas
+pub const RESERVED_PATHS: [&str; 11] = [
+    "api", "admin", "login", "author", "feed.xml", "home", "share", "uploads", "assets",
+    "webmention", "ap",
+];
+
+// Cola de webmentions: intentos, espera inicial entre ellos y cada cuánto
+// se revisa la cola
+pub const WEBMENTION_MAX_ATTEMPTS: i32 = 6;
+pub const WEBMENTION_RETRY_MINUTES: i32 = 5;
+pub const WEBMENTION_INTERVAL_SECONDS: u64 = 60;
+
+// ActivityPub: usuario del actor si no está en `settings` y antigüedad
+// máxima de la cabecera `Date` de una petición firmada
+pub const DEFAULT_ACTIVITYPUB_USERNAME: &str = "blog";
+pub const ACTIVITYPUB_SIGNATURE_MAX_AGE_SECONDS: u64 = 12 * 60 * 60;
+
+// Estadísticas: días por defecto de las gráficas, posts del ranking,
+// reacciones admitidas si no están en `settings` y cada cuánto se borran
+// los hashes de visitantes
+pub const STATS_DEFAULT_DAYS: i32 = 30;
+pub const STATS_TOP_LIMIT: i64 = 10;
+pub const DEFAULT_REACTIONS: &str = "like,love,laugh,celebrate,insightful";
+pub const STATS_PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+
+// Subidas: límites por defecto si no están en `settings` (las reanudables,
+// pensadas para audio, admiten más), cuota por rol (`rol:MB`, `*` para el
+// resto y 0 sin límite) y tope absoluto del cuerpo de la petición
+pub const DEFAULT_UPLOAD_MAX_SIZE_MB: u64 = 20;
+pub const DEFAULT_UPLOAD_RESUMABLE_MAX_SIZE_MB: u64 = 1024;
+pub const DEFAULT_UPLOAD_ALLOWED_TYPES: &str =
+    "image/png,image/jpeg,image/gif,image/webp,image/avif,audio/mpeg,audio/mp4,audio/ogg,application/pdf";
+pub const DEFAULT_UPLOAD_QUOTAS_MB: &str = "admin:0,*:500";
+pub const UPLOAD_BODY_LIMIT_BYTES: usize = 200 * 1024 * 1024;
+
+// Subidas reanudables (protocolo tus): versión, horas sin actividad tras
+// las que se borra una subida, cada cuánto se revisan y bytes iniciales
+// con los que se comprueba el tipo antes de recibir el resto
+pub const TUS_VERSION: &str = "1.0.0";
+pub const UPLOAD_SESSION_EXPIRATION_HOURS: i64 = 24;
+pub const UPLOAD_SESSION_PURGE_INTERVAL_SECONDS: u64 = 60 * 60;
+pub const UPLOAD_SNIFF_BYTES: u64 = 1024;
+
+// Variantes de imagen: anchos y formatos por defecto si no están en
+// `settings`, calidad de compresión, conversor externo (ImageMagick) y
+// atributo `sizes` de las imágenes de los posts
+pub const DEFAULT_IMAGE_VARIANT_WIDTHS: &str = "480,960,1440";
+pub const DEFAULT_IMAGE_VARIANT_FORMATS: &str = "webp,avif";
+pub const IMAGE_VARIANT_QUALITY: u8 = 80;
+pub const DEFAULT_IMAGE_CONVERTER: &str = "magick";
+pub const IMAGE_SIZES: &str = "(max-width: 800px) 100vw, 800px";
+
+// Tarjetas Open Graph de los posts sin portada: plantilla SVG, tamaño,
+// reparto del título en líneas y tiempo de caché en el navegador
+pub const OG_CARD_TEMPLATE: &str = "og_card.svg";
+pub const OG_CARD_WIDTH: u32 = 1200;
+pub const OG_CARD_HEIGHT: u32 = 630;
+pub const OG_CARD_LINE_CHARS: usize = 28;
+pub const OG_CARD_MAX_LINES: usize = 4;
+pub const OG_CARD_MAX_AGE_SECONDS: u64 = 24 * 60 * 60;
+
+// Recogida de basura de la biblioteca: días que se conserva un fichero
+// huérfano si no está en `settings`
+pub const DEFAULT_MEDIA_GC_GRACE_DAYS: i64 = 7;
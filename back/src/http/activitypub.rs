@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, error};
+
+use crate::activitypub::{self, ACTIVITY_JSON};
+use crate::models::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct WebfingerParams {
+    pub resource: String,
+}
+
+/// Actor de ActivityPub del sitio para que se le pueda seguir desde el
+/// fediverso.
+pub fn activitypub_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/.well-known/webfinger", routing::get(webfinger))
+        .route("/ap/actor", routing::get(actor))
+        .route("/ap/outbox", routing::get(outbox))
+        .route("/ap/followers", routing::get(followers))
+        .route("/ap/posts/{id}", routing::get(article))
+        .route("/ap/inbox", routing::post(inbox))
+}
+
+fn json_response(content_type: &str, value: Value) -> Response {
+    ([(header::CONTENT_TYPE, content_type.to_string())], value.to_string()).into_response()
+}
+
+fn activity_response(result: Result<Value, String>) -> Response {
+    match result {
+        Ok(value) => json_response(ACTIVITY_JSON, value),
+        Err(e) => {
+            error!("Error building ActivityPub document: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn webfinger(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<WebfingerParams>,
+) -> impl IntoResponse {
+    match activitypub::webfinger(&app_state, &params.resource).await {
+        Some(jrd) => json_response("application/jrd+json", jrd),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn actor(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    activity_response(activitypub::actor(&app_state).await)
+}
+
+async fn outbox(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    activity_response(activitypub::outbox(&app_state).await)
+}
+
+async fn followers(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    activity_response(activitypub::followers(&app_state).await)
+}
+
+async fn article(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    match activitypub::read_article(&app_state, id).await {
+        Some(article) => json_response(ACTIVITY_JSON, article),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Solo se aceptan actividades con firma HTTP válida de su autor.
+async fn inbox(
+    State(app_state): State<Arc<AppState>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or(uri.path());
+    let actor = match activitypub::verify_request(&app_state, method.as_str(), path, &headers, &body).await {
+        Ok(actor) => actor,
+        Err(e) => {
+            debug!("Invalid signature: {}", e);
+            return (StatusCode::UNAUTHORIZED, e).into_response();
+        }
+    };
+    let activity: Value = match serde_json::from_slice(&body) {
+        Ok(activity) => activity,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid activity: {:?}", e)).into_response(),
+    };
+    debug!("Activity: {}", activity);
+    match activitypub::handle_activity(&app_state, &actor, &activity).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => {
+            error!("Error handling activity: {}", e);
+            (StatusCode::BAD_REQUEST, e).into_response()
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{StatusCode, header, request::Parts},
+};
+use axum_extra::extract::cookie::CookieJar;
+use jsonwebtoken::{DecodingKey, Validation, decode};
+use tracing::debug;
+
+use crate::models::{ApiResponse, AppState, TokenClaims};
+
+/// Usuario autenticado: lee el JWT de la cabecera `Authorization: Bearer`
+/// o, en su defecto, de la cookie `token`.
+impl FromRequestParts<Arc<AppState>> for TokenClaims {
+    type Rejection = ApiResponse;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let bearer = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.to_string());
+        let token = bearer.or_else(|| {
+            CookieJar::from_headers(&parts.headers)
+                .get("token")
+                .map(|cookie| cookie.value().to_string())
+        });
+        let Some(token) = token else {
+            return Err(ApiResponse::new(StatusCode::UNAUTHORIZED, "Not authenticated", None));
+        };
+        decode::<TokenClaims>(
+            &token,
+            &DecodingKey::from_secret(app_state.secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+        .map_err(|e| {
+            debug!("Invalid token: {:?}", e);
+            ApiResponse::new(StatusCode::UNAUTHORIZED, "Invalid token", None)
+        })
+    }
+}
+
+/// Sesión opcional: sin token, o con uno no válido, es un visitante anónimo.
+impl OptionalFromRequestParts<Arc<AppState>> for TokenClaims {
+    type Rejection = ApiResponse;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(<TokenClaims as FromRequestParts<Arc<AppState>>>::from_request_parts(parts, app_state)
+            .await
+            .ok())
+    }
+}
+
+/// Usuario autenticado con rol de administrador.
+#[derive(Debug)]
+pub struct Admin(pub TokenClaims);
+
+impl FromRequestParts<Arc<AppState>> for Admin {
+    type Rejection = ApiResponse;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app_state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let claims =
+            <TokenClaims as FromRequestParts<Arc<AppState>>>::from_request_parts(parts, app_state).await?;
+        if !claims.is_admin() {
+            return Err(ApiResponse::new(StatusCode::FORBIDDEN, "Admin role required", None));
+        }
+        Ok(Admin(claims))
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use minijinja::context;
+use tracing::{debug, error};
+
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE, DEFAULT_SITE_TITLE};
+use crate::models::{
+    ApiResponse, AppState, Author, Comment, HtmlPost, PagedResponse, Pagination, Post, PostStats,
+    ReadAuthorParams, Setting,
+};
+
+pub fn author_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", routing::get(read))
+        .route("/posts", routing::get(read_posts))
+}
+
+/// Página pública de cada autor con su archivo, renderizada en el servidor.
+pub fn author_page_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/author/{slug}", routing::get(author_page))
+        .route("/author/{slug}/", routing::get(author_page))
+}
+
+pub async fn read(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadAuthorParams>,
+) -> impl IntoResponse {
+    debug!("Author: {:?}", params);
+    let result = match params.slug {
+        Some(slug) => Author::read_by_slug(&app_state.pool, &slug)
+            .await
+            .map(|author| serde_json::to_value(author).unwrap()),
+        None => Author::read_all(&app_state.pool)
+            .await
+            .map(|authors| serde_json::to_value(authors).unwrap()),
+    };
+    match result {
+        Ok(value) => ApiResponse::new(StatusCode::OK, "Authors", Some(value)),
+        Err(e) => {
+            let msg = format!("Error reading authors: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::NOT_FOUND, &msg, None)
+        }
+    }
+}
+
+/// Archivo del autor: sus posts públicos paginados.
+pub async fn read_posts(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadAuthorParams>,
+) -> impl IntoResponse {
+    debug!("Author posts: {:?}", params);
+    let Some(slug) = params.slug.as_ref() else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "slug is mandatory", None)
+            .into_response();
+    };
+    let author = match Author::read_by_slug(&app_state.pool, slug).await {
+        Ok(author) => author,
+        Err(e) => {
+            let msg = format!("Error reading author: {:?}", e);
+            error!("{}", &msg);
+            return ApiResponse::new(StatusCode::NOT_FOUND, &msg, None).into_response();
+        }
+    };
+    if let Ok(posts) = Author::read_posts(&app_state.pool, author.id, &params).await
+        && let Ok(count) = Author::count_posts(&app_state.pool, author.id).await
+    {
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+        let offset = params.page.unwrap_or(DEFAULT_PAGE) - 1;
+        let total_pages = (count as f32 / limit as f32).ceil() as u32;
+        let pagination = Pagination {
+            page: offset + 1,
+            limit,
+            pages: total_pages,
+            records: count,
+            prev: if offset > 0 {
+                Some(format!("/records?page={}&limit={}", offset, limit))
+            } else {
+                None
+            },
+            next: if (offset + 1) < total_pages {
+                Some(format!("/records?page={}&limit={}", offset + 2, limit))
+            } else {
+                None
+            },
+        };
+        let html_posts = html_posts(&app_state, &posts).await;
+        PagedResponse::new(
+            StatusCode::OK,
+            "results",
+            Some(serde_json::json!({ "author": author, "posts": html_posts })),
+            pagination,
+        )
+        .into_response()
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "Error reading author posts", None)
+            .into_response()
+    }
+}
+
+async fn html_posts(app_state: &AppState, posts: &[Post]) -> Vec<HtmlPost> {
+    let post_ids: Vec<i32> = posts.iter().map(|p| p.id).collect();
+    let mut authors = Author::read_for_posts(&app_state.pool, &post_ids)
+        .await
+        .unwrap_or_default();
+    let comments_count = Comment::count_for_posts(&app_state.pool, &post_ids)
+        .await
+        .unwrap_or_default();
+    let views_count = PostStats::views_for_posts(&app_state.pool, &post_ids)
+        .await
+        .unwrap_or_default();
+    posts
+        .iter()
+        .map(|post| {
+            let mut html_post = HtmlPost::new(post);
+            html_post.authors = authors.remove(&post.id).unwrap_or_default();
+            html_post.comments_count = comments_count.get(&post.id).copied().unwrap_or_default();
+            html_post.views_count = views_count.get(&post.id).copied().unwrap_or_default();
+            html_post
+        })
+        .collect()
+}
+
+/// Handler para GET /author/{slug}/: perfil y posts publicados, con
+/// `?page=` para avanzar en el archivo.
+async fn author_page(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    Query(mut params): Query<ReadAuthorParams>,
+) -> Response {
+    let author = match Author::read_by_slug(&app_state.pool, &slug).await {
+        Ok(author) => author,
+        Err(sqlx::Error::RowNotFound) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Error reading author '{}': {:?}", slug, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    params.page = Some(params.page.unwrap_or(DEFAULT_PAGE).max(1));
+    let result = match Author::read_posts(&app_state.pool, author.id, &params).await {
+        Ok(posts) => Author::count_posts(&app_state.pool, author.id)
+            .await
+            .map(|count| (posts, count)),
+        Err(e) => Err(e),
+    };
+    let (posts, count) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Error reading posts of author '{}': {:?}", slug, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let page = params.page.unwrap_or(DEFAULT_PAGE);
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    let pages = (count as f32 / limit as f32).ceil() as u32;
+    let posts = html_posts(&app_state, &posts).await;
+    let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+    let rendered = app_state.templates.get_template("author.html").and_then(|template| {
+        template.render(context! {
+            author => author,
+            posts => posts,
+            page => page,
+            pages => pages,
+            site_title => site_title,
+            base_url => app_state.base_url,
+        })
+    });
+    match rendered {
+        Ok(html) => ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+        Err(e) => {
+            error!("Error rendering author '{}': {:?}", slug, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -3,16 +3,22 @@ use std::sync::Arc;
 use axum::{
     Json, Router,
     extract::{Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
     routing,
 };
+use minijinja::context;
 use tracing::{debug, error};
 
-use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+use crate::constants::{COMMENTS_TRUSTED_ROLES, DEFAULT_LIMIT, DEFAULT_PAGE, DEFAULT_SITE_TITLE};
+use super::auth::Admin;
 use crate::models::{
-    ApiResponse, AppState, NewComment, PagedResponse, Pagination, Comment, ReadCommentParams,
+    ApiResponse, AppState, NewComment, PagedResponse, Pagination, Comment, CommentStatus,
+    Moderation, ModerationAction, ReadCommentParams, ReadCommentTreeParams, Setting, TokenClaims,
+    UnsubscribeParams, User, Post,
 };
+use crate::spam::{FormToken, SpamContext};
+use crate::utils::{ClientIp, gravatar_url};
 
 pub fn comment_router() -> Router<Arc<AppState>> {
     Router::new()
@@ -20,16 +26,82 @@ pub fn comment_router() -> Router<Arc<AppState>> {
         .route("/", routing::patch(update))
         .route("/", routing::get(read))
         .route("/", routing::delete(delete))
+        .route("/tree", routing::get(read_tree))
+        .route("/token", routing::get(form_token))
+        .route("/unsubscribe", routing::get(unsubscribe_confirm))
+        .route("/unsubscribe", routing::post(unsubscribe))
+        .route("/moderation", routing::get(read_moderation))
+        .route("/moderation", routing::post(moderate))
+        .route("/moderation/stats", routing::get(stats))
 }
 
 pub async fn create(
     State(app_state): State<Arc<AppState>>,
+    claims: Option<TokenClaims>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(mut comment): Json<NewComment>,
 ) -> impl IntoResponse {
     debug!("Comment: {:?}", comment);
-    match Comment::create(&app_state.pool, &mut comment).await {
+    let header_value = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    comment.author_ip = Some(ip.to_string());
+    comment.user_agent = header_value(header::USER_AGENT);
+    let user = match claims {
+        Some(claims) => match User::read(&app_state.pool, claims.user_id).await {
+            Ok(user) => Some(user),
+            Err(e) => {
+                let msg = format!("Error reading user: {:?}", e);
+                error!("{}", &msg);
+                return ApiResponse::new(StatusCode::UNAUTHORIZED, &msg, None);
+            }
+        },
+        None => None,
+    };
+    match user.as_ref() {
+        Some(user) => {
+            if comment.nikename.trim().is_empty() {
+                comment.nikename = user.username.clone();
+            }
+            comment.avatar_url = user.avatar_url.clone().or(Some(gravatar_url(&user.email)));
+            comment.email = Some(user.email.clone());
+            comment.user_id = Some(user.id);
+        }
+        None => comment.avatar_url = comment.email.as_deref().map(gravatar_url),
+    }
+    // Los autores del blog comentan sin pasar por moderación; el resto de
+    // usuarios, registrados o no, pasan por el filtro de spam
+    let trusted = user
+        .as_ref()
+        .is_some_and(|user| COMMENTS_TRUSTED_ROLES.contains(&user.role.as_str()));
+    let (status, score) = if trusted {
+        (CommentStatus::Approved, 0.0)
+    } else {
+        let context = SpamContext {
+            comment: &comment,
+            referrer: header_value(header::REFERER),
+        };
+        let verdict = app_state.spam.evaluate(&app_state.pool, &context).await;
+        debug!("Spam verdict: {:?}", verdict);
+        if verdict.spam {
+            (CommentStatus::Spam, verdict.score)
+        } else {
+            (CommentStatus::Pending, verdict.score)
+        }
+    };
+    match Comment::create(&app_state.pool, &comment, status, score).await {
         Ok(comment) => {
             debug!("Comment created: {:?}", comment);
+            let notified = comment.clone();
+            tokio::spawn(async move {
+                let notifier = app_state.notifier.clone();
+                notifier.comment_pending(&app_state, &notified).await;
+                notifier.comment_approved(&app_state, &notified).await;
+            });
             ApiResponse::new(
                 StatusCode::CREATED,
                 "Created",
@@ -37,7 +109,7 @@ pub async fn create(
             )
         }
         Err(e) => {
-            let msg = format!("Error updating comment: {:?}", e);
+            let msg = format!("Error creating comment: {:?}", e);
             error!("{}", &msg);
             ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
         }
@@ -46,10 +118,11 @@ pub async fn create(
 
 pub async fn update(
     State(app_state): State<Arc<AppState>>,
-    Json(mut comment): Json<Comment>,
+    _admin: Admin,
+    Json(comment): Json<Comment>,
 ) -> impl IntoResponse {
     debug!("Update comment: {:?}", comment);
-    match Comment::update(&app_state.pool, &mut comment).await {
+    match Comment::update(&app_state.pool, &comment).await {
         Ok(comment) => {
             debug!("Comment updated: {:?}", comment);
             ApiResponse::new(
@@ -66,14 +139,16 @@ pub async fn update(
     }
 }
 
+/// Lectura pública: solo devuelve comentarios aprobados.
 pub async fn read(
     State(app_state): State<Arc<AppState>>,
-    Query(params): Query<ReadCommentParams>,
+    Query(mut params): Query<ReadCommentParams>,
 ) -> impl IntoResponse {
     debug!("Comment: {:?}", params);
-    if let Some(id) = params.id {
+    params.status = Some(CommentStatus::Approved);
+    if let Some(id) = params.id.as_ref() {
         let comment_id: i32 = id.parse().unwrap_or(0);
-        match Comment::read(&app_state.pool, comment_id).await {
+        match Comment::read_approved(&app_state.pool, comment_id).await {
             Ok(comments) => {
                 debug!("Comments: {:?}", comments);
                 ApiResponse::new(
@@ -125,8 +200,102 @@ pub async fn read(
     }
 }
 
+/// Token que el formulario debe devolver al enviar el comentario.
+pub async fn form_token(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadCommentParams>,
+) -> impl IntoResponse {
+    let Some(post_id) = params.post_id.and_then(|id| id.parse::<i32>().ok()) else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "post_id is mandatory", None);
+    };
+    match FormToken::new(&app_state.secret).issue(post_id) {
+        Ok(token) => ApiResponse::new(
+            StatusCode::OK,
+            "Ok",
+            Some(serde_json::json!({ "token": token })),
+        ),
+        Err(e) => {
+            let msg = format!("Error issuing form token: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None)
+        }
+    }
+}
+
+/// Baja de los avisos de respuestas desde el enlace del correo. Admite
+/// POST para la baja en un clic (RFC 8058).
+/// El enlace del correo solo muestra la confirmación: los escáneres de
+/// enlaces y la precarga de los clientes de correo hacen peticiones GET.
+pub async fn unsubscribe_confirm(
+    State(app_state): State<Arc<AppState>>,
+    Query(_params): Query<UnsubscribeParams>,
+) -> Response {
+    unsubscribe_page(&app_state, StatusCode::OK, context! {}).await
+}
+
+/// Baja en un clic (RFC 8058): la hacen el formulario de la confirmación y
+/// los clientes de correo que envían `List-Unsubscribe=One-Click`.
+pub async fn unsubscribe(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Response {
+    match Comment::unsubscribe(&app_state.pool, &params.token).await {
+        Ok(total) => {
+            debug!("Unsubscribed {} comments", total);
+            unsubscribe_page(&app_state, StatusCode::OK, context! { unsubscribed => true }).await
+        }
+        Err(e) => {
+            error!("Error unsubscribing: {:?}", e);
+            unsubscribe_page(&app_state, StatusCode::NOT_FOUND, context! { invalid => true }).await
+        }
+    }
+}
+
+async fn unsubscribe_page(app_state: &AppState, status: StatusCode, ctx: minijinja::Value) -> Response {
+    let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+    let rendered = app_state.templates.get_template("unsubscribe.html").and_then(|template| {
+        template.render(context! {
+            site_title => site_title,
+            base_url => app_state.base_url,
+            ..ctx
+        })
+    });
+    match rendered {
+        Ok(html) => (status, [(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+        Err(e) => {
+            error!("Error rendering unsubscribe page: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Comentarios aprobados de un post publicado como árbol de respuestas.
+pub async fn read_tree(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadCommentTreeParams>,
+) -> impl IntoResponse {
+    debug!("Comment tree: {:?}", params);
+    match Post::read(&app_state.pool, params.post_id).await {
+        Ok(post) if post.is_public() => {}
+        _ => return ApiResponse::new(StatusCode::NOT_FOUND, "Post not found", None),
+    }
+    match Comment::read_tree(&app_state.pool, &params).await {
+        Ok(tree) => ApiResponse::new(
+            StatusCode::OK,
+            "Comments",
+            Some(serde_json::to_value(tree).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error reading comments: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
 pub async fn delete(
     State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
     Query(params): Query<ReadCommentParams>,
 ) -> impl IntoResponse {
     if let Some(id) = params.id {
@@ -149,3 +318,118 @@ pub async fn delete(
         ApiResponse::new(StatusCode::BAD_REQUEST, "comment_id is mandatory", None)
     }
 }
+
+/// Cola de moderación: por defecto los pendientes, los más antiguos primero.
+pub async fn read_moderation(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Query(mut params): Query<ReadCommentParams>,
+) -> impl IntoResponse {
+    debug!("Moderation queue: {:?}", params);
+    if params.status.is_none() {
+        params.status = Some(CommentStatus::Pending);
+    }
+    if params.sort_by.is_none() {
+        params.sort_by = Some("created_at".to_string());
+    }
+    if let Ok(comments) = Comment::read_paged(&app_state.pool, &params).await
+        && let Ok(count) = Comment::count_paged(&app_state.pool, &params).await
+    {
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+        let offset = params.page.unwrap_or(DEFAULT_PAGE) - 1;
+        let total_pages = (count as f32 / limit as f32).ceil() as u32;
+        let pagination = Pagination {
+            page: offset + 1,
+            limit,
+            pages: total_pages,
+            records: count,
+            prev: if offset > 0 {
+                Some(format!("/records?page={}&limit={}", offset, limit))
+            } else {
+                None
+            },
+            next: if (offset + 1) < total_pages {
+                Some(format!("/records?page={}&limit={}", offset + 2, limit))
+            } else {
+                None
+            },
+        };
+        // La puntuación de spam no sale en la API pública, solo aquí
+        let comments: Vec<serde_json::Value> = comments
+            .iter()
+            .map(|comment| {
+                let mut value = serde_json::to_value(comment).unwrap();
+                value["spam_score"] = serde_json::json!(comment.spam_score);
+                value
+            })
+            .collect();
+        PagedResponse::new(
+            StatusCode::OK,
+            "results",
+            Some(serde_json::Value::Array(comments)),
+            pagination,
+        )
+        .into_response()
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "Error reading moderation queue", None)
+            .into_response()
+    }
+}
+
+/// Aprueba, rechaza, marca como spam o borra varios comentarios a la vez.
+pub async fn moderate(
+    State(app_state): State<Arc<AppState>>,
+    Admin(claims): Admin,
+    Json(moderation): Json<Moderation>,
+) -> impl IntoResponse {
+    debug!("Moderation by {}: {:?}", claims.sub, moderation);
+    if moderation.ids.is_empty() {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "ids is mandatory", None);
+    }
+    match Comment::moderate(&app_state.pool, &moderation).await {
+        Ok(comments) => {
+            match moderation.action {
+                ModerationAction::Approve => {
+                    app_state.spam.learn(&app_state.pool, &comments, false).await;
+                    let approved = comments.clone();
+                    let app_state = app_state.clone();
+                    tokio::spawn(async move {
+                        for comment in &approved {
+                            app_state.notifier.comment_approved(&app_state, comment).await;
+                        }
+                    });
+                }
+                ModerationAction::Spam => app_state.spam.learn(&app_state.pool, &comments, true).await,
+                _ => {}
+            }
+            ApiResponse::new(
+            StatusCode::OK,
+                "Comments moderated",
+                Some(serde_json::to_value(comments).unwrap()),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error moderating comments: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn stats(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> impl IntoResponse {
+    match Comment::stats(&app_state.pool).await {
+        Ok(stats) => ApiResponse::new(
+            StatusCode::OK,
+            "Moderation stats",
+            Some(serde_json::to_value(stats).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error reading moderation stats: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
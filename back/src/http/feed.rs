@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing, Router,
+};
+use tracing::error;
+
+use crate::constants::{DEFAULT_SITE_DESCRIPTION, DEFAULT_SITE_TITLE, FEED_LIMIT};
+use crate::media;
+use crate::models::{AppState, Author, HtmlPost, Post, Setting};
+use crate::utils::xml_escape;
+
+/// Feeds RSS públicos: el del sitio y uno por autor.
+pub fn feed_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/feed.xml", routing::get(site_feed))
+        .route("/author/{slug}/feed.xml", routing::get(author_feed))
+}
+
+async fn site_feed(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    match Post::read_published(&app_state.pool, FEED_LIMIT, None).await {
+        Ok(posts) => {
+            let title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+            let description =
+                Setting::get_value(&app_state.pool, "site_description", DEFAULT_SITE_DESCRIPTION).await;
+            let self_url = format!("{}/feed.xml", app_state.base_url);
+            rss_response(&app_state, &title, &description, &self_url, &posts).await
+        }
+        Err(e) => {
+            error!("Error reading feed posts: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn author_feed(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> impl IntoResponse {
+    let author = match Author::read_by_slug(&app_state.pool, &slug).await {
+        Ok(author) => author,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    match Post::read_published(&app_state.pool, FEED_LIMIT, Some(author.id)).await {
+        Ok(posts) => {
+            let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+            let title = format!("{} | {}", author.username, site_title);
+            let description = author.bio.clone().unwrap_or_default();
+            let self_url = format!("{}/author/{}/feed.xml", app_state.base_url, slug);
+            rss_response(&app_state, &title, &description, &self_url, &posts).await
+        }
+        Err(e) => {
+            error!("Error reading feed posts for author '{}': {:?}", slug, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn rss_response(
+    app_state: &AppState,
+    title: &str,
+    description: &str,
+    self_url: &str,
+    posts: &[Post],
+) -> Response {
+    let post_ids: Vec<i32> = posts.iter().map(|p| p.id).collect();
+    let mut authors = Author::read_for_posts(&app_state.pool, &post_ids)
+        .await
+        .unwrap_or_default();
+    let mut audio = media::read_audio(app_state, posts).await;
+    let mut items = String::new();
+    for post in posts {
+        let mut html_post = HtmlPost::new(post);
+        html_post.authors = authors.remove(&post.id).unwrap_or_default();
+        html_post.audio = audio.remove(&post.id);
+        items.push_str(&rss_item(&app_state.base_url, &html_post));
+    }
+    let last_build = posts
+        .first()
+        .and_then(|p| p.published_at)
+        .map(|date| format!("\n    <lastBuildDate>{}</lastBuildDate>", date.to_rfc2822()))
+        .unwrap_or_default();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>{}</title>
+    <link>{}</link>
+    <description>{}</description>
+    <atom:link href="{}" rel="self" type="application/rss+xml"/>{}
+{}  </channel>
+</rss>
+"#,
+        xml_escape(title),
+        xml_escape(&app_state.base_url),
+        xml_escape(description),
+        xml_escape(self_url),
+        last_build,
+        items
+    );
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn rss_item(base_url: &str, post: &HtmlPost) -> String {
+    let link = format!("{}/{}", base_url, post.slug);
+    let mut item = format!(
+        "    <item>\n      <title>{}</title>\n      <link>{}</link>\n      <guid isPermaLink=\"true\">{}</guid>\n",
+        xml_escape(&post.title),
+        xml_escape(&link),
+        xml_escape(&link)
+    );
+    if let Some(published_at) = post.published_at {
+        item.push_str(&format!("      <pubDate>{}</pubDate>\n", published_at.to_rfc2822()));
+    }
+    for author in &post.authors {
+        item.push_str(&format!("      <dc:creator>{}</dc:creator>\n", xml_escape(&author.username)));
+    }
+    if let Some(description) = post.html_excerpt.as_ref().or(post.clean_meta.as_ref()) {
+        item.push_str(&format!("      <description>{}</description>\n", xml_escape(description)));
+    }
+    // Episodio de podcast: solo si el audio está en la biblioteca y se
+    // conoce su tamaño
+    if let Some(audio) = &post.audio {
+        item.push_str(&format!(
+            "      <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+            xml_escape(&audio.url),
+            audio.size,
+            xml_escape(&audio.mime_type)
+        ));
+        if let Some(duration) = audio.duration {
+            item.push_str(&format!(
+                "      <itunes:duration>{:02}:{:02}:{:02}</itunes:duration>\n",
+                duration / 3600,
+                duration / 60 % 60,
+                duration % 60
+            ));
+        }
+    }
+    item.push_str(&format!(
+        "      <content:encoded>{}</content:encoded>\n    </item>\n",
+        xml_escape(&post.html_content)
+    ));
+    item
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing,
+};
+use tracing::{debug, error};
+
+use super::auth::Admin;
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+use crate::media::{self, UploadQuota};
+use crate::models::{
+    ApiResponse, AppState, Media, MediaVariant, OrphanParams, PagedResponse, Pagination, ReadMediaParams,
+    TokenClaims, UpdateMedia,
+};
+
+/// Biblioteca de medios: los ficheros subidos con `/uploads`. Cada usuario
+/// ve y gestiona los suyos; los administradores, todos.
+pub fn media_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", routing::get(read))
+        .route("/", routing::patch(update))
+        .route("/", routing::delete(delete))
+        .route("/usage", routing::get(read_usage))
+        .route("/orphans", routing::get(read_orphans))
+        .route("/orphans", routing::delete(delete_orphans))
+}
+
+/// Medio que el usuario puede gestionar: suyo, o cualquiera si es
+/// administrador.
+async fn read_owned(app_state: &AppState, claims: &TokenClaims, id: i32) -> Result<Media, ApiResponse> {
+    match Media::read(&app_state.pool, id).await {
+        Ok(media) if claims.is_admin() || media.uploader_id == Some(claims.user_id) => Ok(media),
+        Ok(_) => Err(ApiResponse::new(
+            StatusCode::FORBIDDEN,
+            "Media belongs to another user",
+            None,
+        )),
+        Err(e) => {
+            let msg = format!("Error reading media: {:?}", e);
+            error!("{}", &msg);
+            Err(ApiResponse::new(StatusCode::NOT_FOUND, &msg, None))
+        }
+    }
+}
+
+pub async fn read(
+    State(app_state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Query(mut params): Query<ReadMediaParams>,
+) -> impl IntoResponse {
+    debug!("Media: {:?}", params);
+    if !claims.is_admin() {
+        params.uploader_id = Some(claims.user_id);
+    }
+    if let Some(id) = params.id.as_ref() {
+        let media_id: i32 = id.parse().unwrap_or(0);
+        return match read_owned(&app_state, &claims, media_id).await {
+            Ok(mut media) => {
+                media.variants = MediaVariant::read_for_media(&app_state.pool, media.id)
+                    .await
+                    .unwrap_or_default();
+                media.posts = Media::read_posts(&app_state.pool, std::slice::from_ref(&media.path))
+                    .await
+                    .unwrap_or_default()
+                    .remove(&media.path)
+                    .unwrap_or_default();
+                ApiResponse::new(
+                    StatusCode::OK,
+                    "Media",
+                    Some(serde_json::to_value(media::with_url(&app_state, media)).unwrap()),
+                )
+                .into_response()
+            }
+            Err(response) => response.into_response(),
+        };
+    }
+    if let Ok(items) = Media::read_paged(&app_state.pool, &params).await
+        && let Ok(count) = Media::count_paged(&app_state.pool, &params).await
+    {
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+        let offset = params.page.unwrap_or(DEFAULT_PAGE) - 1;
+        let total_pages = (count as f32 / limit as f32).ceil() as u32;
+        let pagination = Pagination {
+            page: offset + 1,
+            limit,
+            pages: total_pages,
+            records: count,
+            prev: if offset > 0 {
+                Some(format!("/records?page={}&limit={}", offset, limit))
+            } else {
+                None
+            },
+            next: if (offset + 1) < total_pages {
+                Some(format!("/records?page={}&limit={}", offset + 2, limit))
+            } else {
+                None
+            },
+        };
+        let paths: Vec<String> = items.iter().map(|item| item.path.clone()).collect();
+        let mut posts = Media::read_posts(&app_state.pool, &paths).await.unwrap_or_default();
+        let items: Vec<Media> = items
+            .into_iter()
+            .map(|mut item| {
+                item.posts = posts.remove(&item.path).unwrap_or_default();
+                media::with_url(&app_state, item)
+            })
+            .collect();
+        PagedResponse::new(
+            StatusCode::OK,
+            "results",
+            Some(serde_json::to_value(items).unwrap()),
+            pagination,
+        )
+        .into_response()
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "Error reading media", None).into_response()
+    }
+}
+
+/// Solo se puede editar el texto alternativo.
+pub async fn update(
+    State(app_state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Json(update): Json<UpdateMedia>,
+) -> impl IntoResponse {
+    if let Err(response) = read_owned(&app_state, &claims, update.id).await {
+        return response;
+    }
+    match Media::update_alt_text(&app_state.pool, update.id, update.alt_text.as_deref()).await {
+        Ok(media) => ApiResponse::new(
+            StatusCode::OK,
+            "Media updated",
+            Some(serde_json::to_value(media::with_url(&app_state, media)).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error updating media: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+/// Borra el registro y los ficheros del disco, original y variantes.
+pub async fn delete(
+    State(app_state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Query(params): Query<ReadMediaParams>,
+) -> impl IntoResponse {
+    let Some(id) = params.id else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "id is mandatory", None);
+    };
+    let media_id: i32 = id.parse().unwrap_or(0);
+    if let Err(response) = read_owned(&app_state, &claims, media_id).await {
+        return response;
+    }
+    match media::delete_media(&app_state, media_id).await {
+        Ok(deleted) => ApiResponse::new(
+            StatusCode::OK,
+            "Media deleted",
+            Some(serde_json::to_value(media::with_url(&app_state, deleted)).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error deleting media: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+/// Cuota del usuario y lo que lleva ocupado. `quota` es `null` si no tiene
+/// límite.
+pub async fn read_usage(State(app_state): State<Arc<AppState>>, claims: TokenClaims) -> impl IntoResponse {
+    let used = match Media::used_space(&app_state.pool, claims.user_id).await {
+        Ok(used) => used,
+        Err(e) => {
+            let msg = format!("Error reading used space: {:?}", e);
+            error!("{}", &msg);
+            return ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None);
+        }
+    };
+    match UploadQuota::load(&app_state.pool, &claims).await {
+        Ok(quota) => ApiResponse::new(
+            StatusCode::OK,
+            "Media usage",
+            Some(serde_json::json!({ "quota": quota.quota, "used": used })),
+        ),
+        Err(e) => ApiResponse::from(e),
+    }
+}
+
+/// Vista previa de la recogida de basura: ficheros que ningún post ni
+/// página enlaza, separados según hayan pasado o no el periodo de gracia.
+pub async fn read_orphans(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Query(params): Query<OrphanParams>,
+) -> impl IntoResponse {
+    let grace_days = media::grace_days(&app_state.pool, params.grace_days).await;
+    match media::scan_orphans(&app_state, grace_days).await {
+        Ok(orphans) => {
+            let with_urls = |items: Vec<Media>| -> Vec<Media> {
+                items.into_iter().map(|item| media::with_url(&app_state, item)).collect()
+            };
+            ApiResponse::new(
+                StatusCode::OK,
+                "Orphaned media",
+                Some(serde_json::json!({
+                    "grace_days": grace_days,
+                    "deletable": with_urls(orphans.deletable),
+                    "waiting": with_urls(orphans.waiting),
+                })),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error scanning media: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None)
+        }
+    }
+}
+
+/// Borra los huérfanos que han pasado el periodo de gracia.
+pub async fn delete_orphans(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Query(params): Query<OrphanParams>,
+) -> impl IntoResponse {
+    let grace_days = media::grace_days(&app_state.pool, params.grace_days).await;
+    match media::delete_orphans(&app_state, grace_days).await {
+        Ok(deleted) => {
+            let deleted: Vec<Media> = deleted
+                .into_iter()
+                .map(|item| media::with_url(&app_state, item))
+                .collect();
+            ApiResponse::new(
+                StatusCode::OK,
+                "Orphaned media deleted",
+                Some(serde_json::json!({ "grace_days": grace_days, "deleted": deleted })),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error deleting orphaned media: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None)
+        }
+    }
+}
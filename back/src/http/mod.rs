@@ -1,9 +1,21 @@
+mod auth;
 mod user;
 mod health;
 mod post;
 mod comment;
 mod tag;
 mod upload;
+mod resumable;
+mod redirect;
+mod series;
+mod author;
+mod feed;
+mod page;
+mod webmention;
+mod activitypub;
+mod stats;
+mod media;
+mod og;
 
 pub use health::health_router;
 pub use user::{
@@ -13,4 +25,15 @@ pub use user::{
 pub use post::post_router;
 pub use comment::comment_router;
 pub use tag::tag_router;
-pub use upload::upload_router;
+pub use upload::{upload_router, uploads_router};
+pub use resumable::{TUS_REQUEST_HEADERS, TUS_RESPONSE_HEADERS};
+pub use redirect::redirect_router;
+pub use series::{series_page_router, series_router};
+pub use author::{author_page_router, author_router};
+pub use feed::feed_router;
+pub use page::{page_router, serve_page};
+pub use webmention::{webmention_router, api_webmention_router};
+pub use activitypub::activitypub_router;
+pub use stats::stats_router;
+pub use media::media_router;
+pub use og::og_router;
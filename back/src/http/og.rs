@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use tracing::error;
+
+use crate::constants::OG_CARD_MAX_AGE_SECONDS;
+use crate::media;
+use crate::models::{AppState, Post};
+
+/// Tarjetas Open Graph de los posts sin portada.
+pub fn og_router() -> Router<Arc<AppState>> {
+    Router::new().route("/og/{file}", routing::get(og_card))
+}
+
+/// Handler para GET /og/{id}.png. Solo para posts públicos; el navegador
+/// la revalida con `ETag` cuando caduca.
+async fn og_card(
+    State(app_state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(post_id) = file.strip_suffix(".png").and_then(|id| id.parse::<i32>().ok()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let post = match Post::read(&app_state.pool, post_id).await {
+        Ok(post) if post.is_public() => post,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let (hash, png) = match media::og_card(&app_state, &post).await {
+        Ok(card) => card,
+        Err(e) => {
+            error!("Error generating Open Graph card for post {}: {:?}", post.id, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = format!("\"{}\"", &hash[..16]);
+    let cache_control = format!("public, max-age={}", OG_CARD_MAX_AGE_SECONDS);
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|value| value.as_bytes() == etag.as_bytes())
+    {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "image/png".to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        png,
+    )
+        .into_response()
+}
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Query, Request, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use minijinja::context;
+use tower::ServiceExt;
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::{debug, error};
+
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE, DEFAULT_SITE_TITLE};
+use crate::models::{
+    ApiResponse, AppState, HtmlPage, NewPage, Page, PagedResponse, Pagination, ReadPageParams,
+    Setting,
+};
+
+pub fn page_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", routing::post(create))
+        .route("/", routing::patch(update))
+        .route("/", routing::get(read))
+        .route("/", routing::delete(delete))
+        .route("/html", routing::get(read_html))
+}
+
+pub async fn create(
+    State(app_state): State<Arc<AppState>>,
+    Json(page): Json<NewPage>,
+) -> impl IntoResponse {
+    debug!("Page: {:?}", page);
+    match Page::create(&app_state.pool, &page).await {
+        Ok(page) => {
+            debug!("Page created: {:?}", page);
+            ApiResponse::new(
+                StatusCode::CREATED,
+                "Created",
+                Some(serde_json::to_value(page).unwrap()),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error creating page: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn update(
+    State(app_state): State<Arc<AppState>>,
+    Json(page): Json<Page>,
+) -> impl IntoResponse {
+    debug!("Update page: {:?}", page);
+    match Page::update(&app_state.pool, &page).await {
+        Ok(page) => {
+            debug!("Page updated: {:?}", page);
+            ApiResponse::new(
+                StatusCode::OK,
+                "Page updated",
+                Some(serde_json::to_value(page).unwrap()),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error updating page: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn read(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadPageParams>,
+) -> impl IntoResponse {
+    debug!("Page: {:?}", params);
+    if let Some(id) = params.id {
+        let page_id: i32 = id.parse().unwrap_or(0);
+        match Page::read(&app_state.pool, page_id).await {
+            Ok(page) => ApiResponse::new(
+                StatusCode::OK,
+                "Page",
+                Some(serde_json::to_value(page).unwrap()),
+            )
+            .into_response(),
+            Err(e) => {
+                let msg = format!("Error reading pages: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None).into_response()
+            }
+        }
+    } else if let Ok(pages) = Page::read_paged(&app_state.pool, &params).await
+        && let Ok(count) = Page::count_paged(&app_state.pool, &params).await
+    {
+        debug!("Pages: {:?}", pages);
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+        let offset = params.page.unwrap_or(DEFAULT_PAGE) - 1;
+        let total_pages = (count as f32 / limit as f32).ceil() as u32;
+        let pagination = Pagination {
+            page: offset + 1,
+            limit,
+            pages: total_pages,
+            records: count,
+            prev: if offset > 0 {
+                Some(format!("/records?page={}&limit={}", offset, limit))
+            } else {
+                None
+            },
+            next: if (offset + 1) < total_pages {
+                Some(format!("/records?page={}&limit={}", offset + 2, limit))
+            } else {
+                None
+            },
+        };
+        PagedResponse::new(
+            StatusCode::OK,
+            "results",
+            Some(serde_json::to_value(pages).unwrap()),
+            pagination,
+        )
+        .into_response()
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "Error reading pages", None)
+            .into_response()
+    }
+}
+
+pub async fn read_html(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadPageParams>,
+) -> impl IntoResponse {
+    let Some(path) = params.path else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "path is mandatory", None);
+    };
+    match Page::read_public_by_path(&app_state.pool, &path).await {
+        Ok(Some(page)) => {
+            let html_page = html_page(&app_state, &page).await;
+            ApiResponse::new(
+                StatusCode::OK,
+                "Page",
+                Some(serde_json::to_value(html_page).unwrap()),
+            )
+        }
+        Ok(None) => ApiResponse::new(StatusCode::NOT_FOUND, "Page not found", None),
+        Err(e) => {
+            let msg = format!("Error reading page: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn delete(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadPageParams>,
+) -> impl IntoResponse {
+    if let Some(id) = params.id {
+        let page_id: i32 = id.parse().unwrap_or(0);
+        match Page::delete(&app_state.pool, page_id).await {
+            Ok(page) => ApiResponse::new(
+                StatusCode::OK,
+                "Page deleted",
+                Some(serde_json::to_value(page).unwrap()),
+            ),
+            Err(e) => {
+                let msg = format!("Error deleting page: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+            }
+        }
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "page_id is mandatory", None)
+    }
+}
+
+async fn html_page(app_state: &AppState, page: &Page) -> HtmlPage {
+    let mut html_page = HtmlPage::new(page);
+    html_page.breadcrumbs = Page::read_breadcrumbs(&app_state.pool, page)
+        .await
+        .unwrap_or_default();
+    html_page.children = Page::read_children(&app_state.pool, page.id)
+        .await
+        .unwrap_or_default();
+    html_page
+}
+
+/// Rutas que pueden ser una página: las que no acaban en un fichero con
+/// extensión (`/favicon.ico`, `/assets/app.js`...).
+pub fn is_page_path(path: &str) -> bool {
+    !path.rsplit('/').next().unwrap_or_default().contains('.')
+}
+
+/// Resuelve cualquier ruta pública. Los ficheros estáticos se sirven sin
+/// pasar por la base de datos; el resto, si corresponde a una página, se
+/// renderiza en el servidor y, si no, se deja a la SPA.
+pub async fn serve_page(State(app_state): State<Arc<AppState>>, request: Request) -> Response {
+    let path = request.uri().path().to_string();
+    if !is_page_path(&path) {
+        return ServeDir::new(&app_state.static_dir)
+            .oneshot(request)
+            .await
+            .into_response();
+    }
+    match Page::read_public_by_path(&app_state.pool, &path).await {
+        Ok(Some(page)) => match render_page(&app_state, &page).await {
+            Ok(html) => {
+                return Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+                    .body(Body::from(html))
+                    .unwrap();
+            }
+            Err(e) => {
+                error!("Error rendering page '{}': {:?}", path, e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        },
+        Ok(None) => {}
+        Err(e) => error!("Error reading page '{}': {:?}", path, e),
+    }
+    let index = app_state.static_dir.join("index.html");
+    ServeDir::new(&app_state.static_dir)
+        .fallback(ServeFile::new(index))
+        .oneshot(request)
+        .await
+        .into_response()
+}
+
+async fn render_page(app_state: &AppState, page: &Page) -> Result<String, minijinja::Error> {
+    let html_page = html_page(app_state, page).await;
+    let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+    let template = app_state.templates.get_template("page.html")?;
+    template.render(context! {
+        page => html_page,
+        site_title => site_title,
+        base_url => app_state.base_url,
+    })
+}
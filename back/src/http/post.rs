@@ -11,8 +11,12 @@ use axum::{
 use tracing::{debug, error};
 
 use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+use crate::webmention::enqueue_for_post;
+use crate::activitypub::publish_post;
+use crate::media;
 use crate::models::{
-    ApiResponse, AppState, NewPost, PagedResponse, Pagination, Post, ReadPostParams, Tag, HtmlPost
+    ApiResponse, AppState, NewPost, PagedResponse, Pagination, Post, ReadPostParams, Tag, HtmlPost,
+    FrontMatter, SlugRedirect, Series, Author, PostAuthors, Comment, PostStats, Media, TokenClaims,
 };
 
 pub fn post_router() -> Router<Arc<AppState>> {
@@ -22,23 +26,28 @@ pub fn post_router() -> Router<Arc<AppState>> {
         .route("/", routing::get(read))
         .route("/", routing::delete(delete))
         .route("/html", routing::get(read_html))
+        .route("/redirects", routing::get(read_redirects))
+        .route("/authors", routing::put(update_authors))
 }
 
 pub async fn create(
     State(app_state): State<Arc<AppState>>,
-    Json(post): Json<NewPost>,
+    claims: Option<TokenClaims>,
+    Json(mut post): Json<NewPost>,
 ) -> impl IntoResponse {
     debug!("Post: {:?}", post);
+    post.cover_media_id = match resolve_cover(&app_state, claims.as_ref(), &post.markdown, post.cover_media_id, None).await {
+        Ok(cover_media_id) => cover_media_id,
+        Err(response) => return response,
+    };
     match Post::create(&app_state.pool, &post).await {
         Ok(post) => {
-            let string_tags = get_tags( &post.content);
-            let tags = Tag::create_or_update(&app_state.pool, string_tags)
-                .await
-                .unwrap_or_default();
-            let id_tags = tags.iter().map(|t| t.id).collect::<Vec<i32>>();
-            Post::assign_tags(&app_state.pool, post.id, id_tags)
-                .await
-                .unwrap_or_default();
+            assign_tags(&app_state, &post).await;
+            assign_authors(&app_state, &post).await;
+            enqueue_for_post(&app_state, &post).await;
+            let state = app_state.clone();
+            let federated = post.clone();
+            tokio::spawn(async move { publish_post(&state, &federated).await });
             debug!("Post created: {:?}", post);
             ApiResponse::new(
                 StatusCode::CREATED,
@@ -56,11 +65,26 @@ pub async fn create(
 
 pub async fn update(
     State(app_state): State<Arc<AppState>>,
+    claims: Option<TokenClaims>,
     Json(mut post): Json<Post>,
 ) -> impl IntoResponse {
     debug!("Update post: {:?}", post);
-    match Post::update(&app_state.pool, &mut post).await {
+    let current = Post::read(&app_state.pool, post.id)
+        .await
+        .ok()
+        .and_then(|current| current.cover_media_id);
+    post.cover_media_id = match resolve_cover(&app_state, claims.as_ref(), &post.markdown, post.cover_media_id, current).await {
+        Ok(cover_media_id) => cover_media_id,
+        Err(response) => return response,
+    };
+    match Post::update(&app_state.pool, &post).await {
         Ok(post) => {
+            assign_tags(&app_state, &post).await;
+            assign_authors(&app_state, &post).await;
+            enqueue_for_post(&app_state, &post).await;
+            let state = app_state.clone();
+            let federated = post.clone();
+            tokio::spawn(async move { publish_post(&state, &federated).await });
             debug!("Post updated: {:?}", post);
             ApiResponse::new(
                 StatusCode::OK,
@@ -85,7 +109,27 @@ pub async fn read_html(
         let post_id: i32 = id.parse().unwrap_or(0);
         match Post::read(&app_state.pool, post_id).await {
             Ok(post) => {
-                let html_post = HtmlPost::new(&post);
+                let mut html_post = HtmlPost::new(&post);
+                html_post.series = Series::navigation(&app_state.pool, post.id)
+                    .await
+                    .unwrap_or_default();
+                html_post.authors = Author::read_for_post(&app_state.pool, post.id)
+                    .await
+                    .unwrap_or_default();
+                html_post.comments_count = Comment::count_for_posts(&app_state.pool, &[post.id])
+                    .await
+                    .unwrap_or_default()
+                    .remove(&post.id)
+                    .unwrap_or_default();
+                html_post.views_count = PostStats::views_for_posts(&app_state.pool, &[post.id])
+                    .await
+                    .unwrap_or_default()
+                    .remove(&post.id)
+                    .unwrap_or_default();
+                html_post.audio = media::read_audio(&app_state, std::slice::from_ref(&post))
+                    .await
+                    .remove(&post.id);
+                add_covers(&app_state, std::slice::from_ref(&post), std::slice::from_mut(&mut html_post)).await;
                 debug!("Post: {:?}", html_post);
                 ApiResponse::new(
                     StatusCode::OK,
@@ -103,7 +147,27 @@ pub async fn read_html(
     }else if let Some(slug) = params.slug {
         match Post::read_by_slug(&app_state.pool, slug.as_str()).await {
             Ok(post) => {
-                let html_post = HtmlPost::new(&post);
+                let mut html_post = HtmlPost::new(&post);
+                html_post.series = Series::navigation(&app_state.pool, post.id)
+                    .await
+                    .unwrap_or_default();
+                html_post.authors = Author::read_for_post(&app_state.pool, post.id)
+                    .await
+                    .unwrap_or_default();
+                html_post.comments_count = Comment::count_for_posts(&app_state.pool, &[post.id])
+                    .await
+                    .unwrap_or_default()
+                    .remove(&post.id)
+                    .unwrap_or_default();
+                html_post.views_count = PostStats::views_for_posts(&app_state.pool, &[post.id])
+                    .await
+                    .unwrap_or_default()
+                    .remove(&post.id)
+                    .unwrap_or_default();
+                html_post.audio = media::read_audio(&app_state, std::slice::from_ref(&post))
+                    .await
+                    .remove(&post.id);
+                add_covers(&app_state, std::slice::from_ref(&post), std::slice::from_mut(&mut html_post)).await;
                 debug!("Post: {:?}", html_post);
                 ApiResponse::new(
                     StatusCode::OK,
@@ -141,7 +205,27 @@ pub async fn read_html(
                 None
             },
         };
-        let html_posts: Vec<HtmlPost> = posts.iter().map(HtmlPost::new).collect();
+        let post_ids: Vec<i32> = posts.iter().map(|p| p.id).collect();
+        let mut authors = Author::read_for_posts(&app_state.pool, &post_ids)
+            .await
+            .unwrap_or_default();
+        let comments_count = Comment::count_for_posts(&app_state.pool, &post_ids)
+            .await
+            .unwrap_or_default();
+        let views_count = PostStats::views_for_posts(&app_state.pool, &post_ids)
+            .await
+            .unwrap_or_default();
+        let mut html_posts: Vec<HtmlPost> = posts
+            .iter()
+            .map(|post| {
+                let mut html_post = HtmlPost::new(post);
+                html_post.authors = authors.remove(&post.id).unwrap_or_default();
+                html_post.comments_count = comments_count.get(&post.id).copied().unwrap_or_default();
+                html_post.views_count = views_count.get(&post.id).copied().unwrap_or_default();
+                html_post
+            })
+            .collect();
+        add_covers(&app_state, &posts, &mut html_posts).await;
         PagedResponse::new(
             StatusCode::OK,
             "results",
@@ -231,6 +315,51 @@ pub async fn read(
     }
 }
 
+pub async fn read_redirects(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadPostParams>,
+) -> impl IntoResponse {
+    if let Some(id) = params.id {
+        let post_id: i32 = id.parse().unwrap_or(0);
+        match SlugRedirect::read_for_post(&app_state.pool, post_id).await {
+            Ok(redirects) => {
+                debug!("Redirects: {:?}", redirects);
+                ApiResponse::new(
+                    StatusCode::OK,
+                    "Redirects",
+                    Some(serde_json::to_value(redirects).unwrap()),
+                )
+            }
+            Err(e) => {
+                let msg = format!("Error reading redirects: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+            }
+        }
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "post_id is mandatory", None)
+    }
+}
+
+pub async fn update_authors(
+    State(app_state): State<Arc<AppState>>,
+    Json(post_authors): Json<PostAuthors>,
+) -> impl IntoResponse {
+    debug!("Post authors: {:?}", post_authors);
+    match Author::assign_to_post(&app_state.pool, post_authors.post_id, &post_authors.user_ids).await {
+        Ok(authors) => ApiResponse::new(
+            StatusCode::OK,
+            "Authors updated",
+            Some(serde_json::to_value(authors).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error updating authors: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
 pub async fn delete(
     State(app_state): State<Arc<AppState>>,
     Query(params): Query<ReadPostParams>,
@@ -261,6 +390,93 @@ pub async fn delete(
     }
 }
 
+/// Portada del post: la `cover` del front matter, que tiene que ser la URL
+/// de un fichero de la biblioteca, o `cover_media_id`. Tiene que ser una
+/// imagen y, si cambia, de quien guarda el post (un administrador puede
+/// usar cualquiera).
+async fn resolve_cover(
+    app_state: &AppState,
+    claims: Option<&TokenClaims>,
+    markdown: &str,
+    cover_media_id: Option<i32>,
+    current: Option<i32>,
+) -> Result<Option<i32>, ApiResponse> {
+    let bad_request = |msg: String| ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None);
+    let front_matter = FrontMatter::parse(markdown).ok().flatten().and_then(|front_matter| front_matter.cover);
+    let cover_media_id = match front_matter {
+        Some(url) => {
+            let path = media::path_from_url(app_state, &url).unwrap_or_default().to_string();
+            let cover = Media::read_by_paths(&app_state.pool, &[path])
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .next()
+                .ok_or_else(|| bad_request(format!("Cover '{}' is not in the media library", url)))?;
+            if cover_media_id.is_some_and(|id| id != cover.id) {
+                return Err(bad_request("Front matter cover and cover_media_id are different media".to_string()));
+            }
+            Some(cover.id)
+        }
+        None => cover_media_id,
+    };
+    let Some(id) = cover_media_id.filter(|id| Some(*id) != current) else {
+        return Ok(cover_media_id);
+    };
+    let cover = Media::read(&app_state.pool, id)
+        .await
+        .map_err(|_| bad_request(format!("Cover media {} not found", id)))?;
+    if !cover.mime_type.starts_with("image/") {
+        return Err(bad_request(format!("Cover media {} is not an image ({})", id, cover.mime_type)));
+    }
+    match claims {
+        Some(claims) if claims.is_admin() || cover.uploader_id == Some(claims.user_id) => Ok(cover_media_id),
+        Some(_) => Err(ApiResponse::new(StatusCode::FORBIDDEN, "Media belongs to another user", None)),
+        None => Err(ApiResponse::new(StatusCode::UNAUTHORIZED, "Login required to set a cover", None)),
+    }
+}
+
+/// Portada de la biblioteca de cada post y, en los que no tienen ninguna,
+/// la tarjeta Open Graph generada.
+async fn add_covers(app_state: &AppState, posts: &[Post], html_posts: &mut [HtmlPost]) {
+    let mut covers = media::read_covers(app_state, posts).await;
+    for html_post in html_posts.iter_mut() {
+        if let Some(cover) = covers.remove(&html_post.id) {
+            html_post.set_cover(cover);
+        }
+        if html_post.og_image.is_none() {
+            html_post.og_image = Some(media::og_card_url(&app_state.base_url, html_post.id));
+        }
+    }
+}
+
+async fn assign_tags(app_state: &AppState, post: &Post) {
+    let mut string_tags = get_tags(&post.content);
+    if let Ok(Some(front_matter)) = FrontMatter::parse(&post.markdown) {
+        string_tags.extend(front_matter.tags.unwrap_or_default());
+    }
+    let tags = Tag::create_or_update(&app_state.pool, string_tags)
+        .await
+        .unwrap_or_default();
+    let id_tags = tags.iter().map(|t| t.id).collect::<Vec<i32>>();
+    Post::assign_tags(&app_state.pool, post.id, id_tags)
+        .await
+        .unwrap_or_default();
+}
+
+async fn assign_authors(app_state: &AppState, post: &Post) {
+    let Ok(Some(FrontMatter { authors: Some(slugs), .. })) = FrontMatter::parse(&post.markdown) else {
+        return;
+    };
+    match Author::ids_from_slugs(&app_state.pool, &slugs).await {
+        Ok(user_ids) => {
+            if let Err(e) = Author::assign_to_post(&app_state.pool, post.id, &user_ids).await {
+                error!("Error assigning authors to post {}: {:?}", post.id, e);
+            }
+        }
+        Err(e) => error!("Error resolving authors for post {}: {:?}", post.id, e),
+    }
+}
+
 fn get_tags(content: &str) -> Vec<String> {
     let re = Regex::new(r"#(\w+)").unwrap();
     let mut tags = Vec::new();
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{Path, Request, State},
+    http::{StatusCode, header},
+    response::Response,
+    routing,
+};
+use tracing::{debug, error};
+
+use super::page::{is_page_path, serve_page};
+use crate::models::{AppState, Post, SlugRedirect};
+
+/// Rutas públicas `/{slug}`: redirige los slugs antiguos al actual y, si no,
+/// sirve la página o los ficheros estáticos (o la SPA).
+pub fn redirect_router() -> Router<Arc<AppState>> {
+    Router::new().route("/{slug}", routing::get(redirect_old_slug))
+}
+
+async fn redirect_old_slug(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    request: Request,
+) -> Response {
+    if !is_page_path(&slug) {
+        return serve_page(State(app_state), request).await;
+    }
+    match SlugRedirect::read_by_slug(&app_state.pool, &slug).await {
+        Ok(Some(redirect)) => match Post::read(&app_state.pool, redirect.post_id).await {
+            Ok(post) => {
+                debug!("Redirecting /{} to /{}", slug, post.slug);
+                return Response::builder()
+                    .status(StatusCode::MOVED_PERMANENTLY)
+                    .header(header::LOCATION, format!("/{}", post.slug))
+                    .body(Body::empty())
+                    .unwrap();
+            }
+            Err(e) => error!("Error reading post for redirect '{}': {:?}", slug, e),
+        },
+        Ok(None) => {}
+        Err(e) => error!("Error reading redirect '{}': {:?}", slug, e),
+    }
+    serve_page(State(app_state), request).await
+}
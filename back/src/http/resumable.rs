@@ -0,0 +1,457 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use super::upload::{save_staged_upload, save_upload, uploaded_response};
+use crate::constants::{TUS_VERSION, UPLOAD_SESSION_EXPIRATION_HOURS, UPLOAD_SNIFF_BYTES};
+use crate::media::{self, UploadError, UploadPolicy, UploadQuota};
+use crate::models::{ApiResponse, AppState, Media, NewUploadSession, TokenClaims, UploadSession};
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+const OFFSET_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+/// Cabeceras del protocolo que el navegador tiene que poder enviar y leer
+/// desde otro origen.
+pub const TUS_REQUEST_HEADERS: [HeaderName; 4] = [TUS_RESUMABLE, UPLOAD_OFFSET, UPLOAD_LENGTH, UPLOAD_METADATA];
+pub const TUS_RESPONSE_HEADERS: [HeaderName; 8] = [
+    header::LOCATION,
+    TUS_RESUMABLE,
+    TUS_VERSION_HEADER,
+    TUS_EXTENSION,
+    TUS_MAX_SIZE,
+    UPLOAD_OFFSET,
+    UPLOAD_LENGTH,
+    UPLOAD_EXPIRES,
+];
+
+/// Subida reanudable compatible con tus 1.0 (extensiones `creation`,
+/// `expiration` y `termination`): `POST` crea la subida, `PATCH` añade
+/// trozos desde `Upload-Offset` y `HEAD` dice cuánto ha llegado para
+/// seguir tras un corte. Al recibir el último byte el fichero pasa a la
+/// biblioteca de medios igual que con `POST /uploads`.
+pub fn resumable_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", routing::post(create_upload))
+        .route(
+            "/{id}",
+            routing::get(read_upload)
+                .head(upload_offset)
+                .patch(upload_chunk)
+                .delete(cancel_upload),
+        )
+}
+
+/// Cabeceras comunes; con la subida, también su tamaño y caducidad.
+fn tus_headers(session: Option<&UploadSession>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    if let Some(session) = session {
+        headers.insert(UPLOAD_LENGTH, HeaderValue::from(session.length));
+        headers.insert(UPLOAD_EXPIRES, expires_at(session));
+    }
+    headers
+}
+
+fn expires_at(session: &UploadSession) -> HeaderValue {
+    let updated_at = SystemTime::UNIX_EPOCH + Duration::from_secs(session.updated_at.timestamp().max(0) as u64);
+    let expires_at = updated_at + Duration::from_secs(UPLOAD_SESSION_EXPIRATION_HOURS as u64 * 60 * 60);
+    HeaderValue::from_str(&httpdate::fmt_http_date(expires_at)).unwrap()
+}
+
+fn with_offset(mut headers: HeaderMap, offset: u64) -> HeaderMap {
+    headers.insert(UPLOAD_OFFSET, HeaderValue::from(offset));
+    headers
+}
+
+fn header_u64(headers: &HeaderMap, name: &HeaderName) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Sin `Tus-Resumable` se acepta igual, para poder probar con `curl`; con
+/// otra versión se responde 412 como pide el protocolo.
+fn unsupported_version(headers: &HeaderMap) -> Option<Response> {
+    let version = headers.get(TUS_RESUMABLE)?;
+    if version == TUS_VERSION {
+        return None;
+    }
+    let mut headers = tus_headers(None);
+    headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+    Some((StatusCode::PRECONDITION_FAILED, headers).into_response())
+}
+
+/// Nombre del fichero en `Upload-Metadata`: pares `clave valor-base64`
+/// separados por comas. Los clientes usan `filename` o `name`.
+fn metadata_filename(headers: &HeaderMap) -> Option<String> {
+    let metadata = headers.get(UPLOAD_METADATA)?.to_str().ok()?;
+    let pairs: Vec<(&str, &str)> = metadata
+        .split(',')
+        .filter_map(|pair| pair.trim().split_once(' '))
+        .collect();
+    let value = ["filename", "name"]
+        .iter()
+        .find_map(|key| pairs.iter().find(|(name, _)| name == key).map(|(_, value)| *value))?;
+    String::from_utf8(STANDARD.decode(value.trim()).ok()?).ok()
+}
+
+/// Una subida solo la puede continuar quien la creó, o un administrador.
+async fn read_session(state: &AppState, id: &str, claims: &TokenClaims) -> Result<UploadSession, Response> {
+    let not_found = || ApiResponse::new(StatusCode::NOT_FOUND, "Upload not found", None).into_response();
+    if Uuid::parse_str(id).is_err() {
+        return Err(not_found());
+    }
+    match UploadSession::read(&state.pool, id).await {
+        Ok(Some(session)) if claims.is_admin() || session.uploader_id == Some(claims.user_id) => Ok(session),
+        Ok(_) => Err(not_found()),
+        Err(e) => {
+            let msg = format!("Error reading upload: {:?}", e);
+            error!("{msg}");
+            Err(ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None).into_response())
+        }
+    }
+}
+
+/// Bytes recibidos; una subida terminada ya no tiene fichero temporal.
+async fn received(session: &UploadSession) -> Result<u64, Response> {
+    if session.media_id.is_some() {
+        return Ok(session.length as u64);
+    }
+    media::received_bytes(&session.id).await.map_err(|e| {
+        let msg = format!("Error reading upload '{}': {:?}", session.id, e);
+        error!("{msg}");
+        ApiResponse::from(UploadError::Storage(msg)).into_response()
+    })
+}
+
+/// Descarta la subida y lo recibido, p. ej. cuando el contenido no se
+/// acepta.
+async fn discard(state: &AppState, id: &str) {
+    if let Err(e) = UploadSession::delete(&state.pool, id).await {
+        error!("Error deleting upload {}: {:?}", id, e);
+    }
+    media::remove_upload(id).await;
+}
+
+/// Handler para POST /uploads/resumable. El tamaño total va en
+/// `Upload-Length` y se comprueba contra el máximo de las subidas
+/// reanudables y la cuota del usuario antes de recibir nada.
+/// Lo que admite el servidor va en la respuesta: los `OPTIONS` los
+/// contesta la capa de CORS.
+async fn create_upload(
+    State(state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = unsupported_version(&headers) {
+        return response;
+    }
+    let Some(length) = header_u64(&headers, &UPLOAD_LENGTH) else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Missing or invalid Upload-Length header", None)
+            .into_response();
+    };
+    let policy = UploadPolicy::load(&state.pool).await;
+    let checked = match UploadQuota::load(&state.pool, &claims).await {
+        Ok(quota) => policy.check_resumable_size(length).and_then(|_| quota.check(length)),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = checked {
+        debug!("Resumable upload rejected: {:?}", e);
+        return ApiResponse::from(e).into_response();
+    }
+    let new_session = NewUploadSession {
+        id: Uuid::new_v4().to_string(),
+        filename: metadata_filename(&headers),
+        length: length as i64,
+        uploader_id: Some(claims.user_id),
+    };
+    let session = match UploadSession::create(&state.pool, &new_session).await {
+        Ok(session) => session,
+        Err(e) => {
+            let msg = format!("Error creating upload: {:?}", e);
+            error!("{msg}");
+            return ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None).into_response();
+        }
+    };
+    let location = format!("{}/api/v1/uploads/resumable/{}", state.base_url.trim_end_matches('/'), session.id);
+    let mut headers = with_offset(tus_headers(Some(&session)), 0);
+    headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+    headers.insert(TUS_EXTENSION, HeaderValue::from_static("creation,expiration,termination"));
+    headers.insert(TUS_MAX_SIZE, HeaderValue::from(policy.max_resumable_size));
+    if let Ok(value) = HeaderValue::from_str(&location) {
+        headers.insert(header::LOCATION, value);
+    }
+    let data = serde_json::json!({ "id": session.id, "location": location, "length": length, "offset": 0 });
+    (headers, ApiResponse::new(StatusCode::CREATED, "Upload created", Some(data))).into_response()
+}
+
+/// Handler para HEAD /uploads/resumable/{id}: bytes recibidos, para
+/// reanudar desde ahí.
+async fn upload_offset(
+    State(state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Path(id): Path<String>,
+) -> Response {
+    let session = match read_session(&state, &id, &claims).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let offset = match received(&session).await {
+        Ok(offset) => offset,
+        Err(response) => return response,
+    };
+    let mut headers = with_offset(tus_headers(Some(&session)), offset);
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    (StatusCode::OK, headers).into_response()
+}
+
+/// Handler para GET /uploads/resumable/{id}: estado de la subida y, si ya
+/// terminó, el medio creado.
+async fn read_upload(
+    State(state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Path(id): Path<String>,
+) -> Response {
+    let session = match read_session(&state, &id, &claims).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let offset = match received(&session).await {
+        Ok(offset) => offset,
+        Err(response) => return response,
+    };
+    let media = match session.media_id {
+        Some(media_id) => Media::read(&state.pool, media_id)
+            .await
+            .ok()
+            .map(|media| media::with_url(&state, media)),
+        None => None,
+    };
+    let data = serde_json::json!({
+        "id": session.id,
+        "filename": session.filename,
+        "length": session.length,
+        "offset": offset,
+        "mime_type": session.mime_type,
+        "media": media,
+    });
+    (tus_headers(Some(&session)), ApiResponse::new(StatusCode::OK, "Upload", Some(data))).into_response()
+}
+
+/// Handler para PATCH /uploads/resumable/{id}. El trozo tiene que empezar
+/// justo donde acaba lo recibido. Con el primer KB se comprueba el tipo y
+/// con el último byte se guarda el fichero en la biblioteca.
+async fn upload_chunk(
+    State(state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    if let Some(response) = unsupported_version(&headers) {
+        return response;
+    }
+    if headers.get(header::CONTENT_TYPE).is_none_or(|value| value != OFFSET_CONTENT_TYPE) {
+        let msg = format!("Content-Type must be {}", OFFSET_CONTENT_TYPE);
+        return ApiResponse::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, &msg, None).into_response();
+    }
+    let Some(offset) = header_u64(&headers, &UPLOAD_OFFSET) else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Missing or invalid Upload-Offset header", None)
+            .into_response();
+    };
+    let session = match read_session(&state, &id, &claims).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let Some(_lock) = media::lock_upload(&session.id) else {
+        return ApiResponse::new(StatusCode::LOCKED, "Upload is receiving another chunk", None).into_response();
+    };
+    let length = session.length as u64;
+    let received = match received(&session).await {
+        Ok(received) => received,
+        Err(response) => return response,
+    };
+    if offset != received {
+        let msg = format!("Upload-Offset {} does not match the {} bytes received", offset, received);
+        let headers = with_offset(tus_headers(Some(&session)), received);
+        return (headers, ApiResponse::new(StatusCode::CONFLICT, &msg, None)).into_response();
+    }
+    if let Some(media_id) = session.media_id {
+        return completed_response(&state, &session, media_id).await;
+    }
+    if header_u64(&headers, &header::CONTENT_LENGTH).is_some_and(|size| offset + size > length) {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Chunk goes past Upload-Length", None).into_response();
+    }
+
+    let written = match media::append_body(&session.id, body, offset, length).await {
+        Ok(written) => written,
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+            return ApiResponse::new(StatusCode::BAD_REQUEST, &e.to_string(), None).into_response();
+        }
+        Err(e) => {
+            let msg = format!("Error writing upload '{}': {:?}", session.id, e);
+            error!("{msg}");
+            return ApiResponse::from(UploadError::Storage(msg)).into_response();
+        }
+    };
+
+    // El tipo se comprueba en cuanto hay bytes suficientes, para no recibir
+    // cientos de MB de algo que se va a rechazar. Las imágenes no pueden
+    // pasar del máximo normal
+    let policy = UploadPolicy::load(&state.pool).await;
+    let mut mime_type = None;
+    if session.mime_type.is_none() && (written >= UPLOAD_SNIFF_BYTES || written == length) {
+        let head = media::read_head(&session.id, UPLOAD_SNIFF_BYTES).await.unwrap_or_default();
+        let checked = policy.check_type(&head).and_then(|file_type| {
+            if file_type.is_image() {
+                policy.check_size(length)?;
+            }
+            Ok(file_type)
+        });
+        match checked {
+            Ok(file_type) => mime_type = Some(file_type.mime_type),
+            Err(e) => {
+                debug!("Resumable upload {} rejected: {:?}", session.id, e);
+                discard(&state, &session.id).await;
+                return ApiResponse::from(e).into_response();
+            }
+        }
+    }
+    let session = match UploadSession::touch(&state.pool, &session.id, mime_type).await {
+        Ok(session) => session,
+        Err(e) => {
+            let msg = format!("Error updating upload: {:?}", e);
+            error!("{msg}");
+            return ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None).into_response();
+        }
+    };
+    if written < length {
+        return (StatusCode::NO_CONTENT, with_offset(tus_headers(Some(&session)), written)).into_response();
+    }
+    finish_upload(&state, &session, &policy, &claims).await
+}
+
+/// Último byte recibido: el fichero pasa por las mismas comprobaciones que
+/// una subida normal y se guarda en la biblioteca. Las imágenes se
+/// procesan en memoria como en `POST /uploads`; el resto (audio, PDF) se
+/// mueve al almacenamiento sin cargarlo entero. Si falla el almacenamiento
+/// se conserva lo recibido y un `PATCH` vacío lo reintenta; si el fichero
+/// ya se movió, la subida se descarta. La cuota se vuelve a mirar:
+/// mientras tanto pudo haber otras subidas.
+async fn finish_upload(
+    state: &Arc<AppState>,
+    session: &UploadSession,
+    policy: &UploadPolicy,
+    claims: &TokenClaims,
+) -> Response {
+    let storage_error = |e: std::io::Error| {
+        let msg = format!("Error reading upload '{}': {:?}", session.id, e);
+        error!("{msg}");
+        ApiResponse::from(UploadError::Storage(msg)).into_response()
+    };
+    let head = match media::read_head(&session.id, UPLOAD_SNIFF_BYTES).await {
+        Ok(head) => head,
+        Err(e) => return storage_error(e),
+    };
+    let file_type = match policy.check_type(&head) {
+        Ok(file_type) => file_type,
+        Err(e) => {
+            debug!("Resumable upload {} rejected: {:?}", session.id, e);
+            discard(state, &session.id).await;
+            return ApiResponse::from(e).into_response();
+        }
+    };
+    let quota = match UploadQuota::load(&state.pool, claims).await {
+        Ok(quota) => quota,
+        Err(e) => return ApiResponse::from(e).into_response(),
+    };
+    let file_name = session.filename.clone().unwrap_or_default();
+    let uploader_id = session.uploader_id.unwrap_or(claims.user_id);
+    let length = session.length as u64;
+    let saved = if file_type.is_image() {
+        let data = match media::read_upload(&session.id).await {
+            Ok(data) => data,
+            Err(e) => return storage_error(e),
+        };
+        if let Err(e) = policy.check_size(data.len() as u64) {
+            debug!("Resumable upload {} rejected: {:?}", session.id, e);
+            discard(state, &session.id).await;
+            return ApiResponse::from(e).into_response();
+        }
+        save_upload(state, file_name, data, file_type, uploader_id, &quota).await
+    } else {
+        save_staged_upload(state, file_name, &session.id, length, file_type, uploader_id, &quota).await
+    };
+    let (media, duplicate) = match saved {
+        Ok(saved) => saved,
+        Err(e) => {
+            if media::received_bytes(&session.id).await.is_ok_and(|received| received < length) {
+                discard(state, &session.id).await;
+            }
+            return ApiResponse::from(e).into_response();
+        }
+    };
+    if let Err(e) = UploadSession::complete(&state.pool, &session.id, media.id).await {
+        error!("Error completing upload {}: {:?}", session.id, e);
+    }
+    media::remove_upload(&session.id).await;
+    let headers = with_offset(tus_headers(Some(session)), length);
+    (headers, uploaded_response(media, duplicate)).into_response()
+}
+
+/// Repetición del último `PATCH` (p. ej. se perdió la respuesta): se
+/// devuelve el medio ya creado.
+async fn completed_response(state: &Arc<AppState>, session: &UploadSession, media_id: i32) -> Response {
+    match Media::read(&state.pool, media_id).await {
+        Ok(media) => {
+            let headers = with_offset(tus_headers(Some(session)), session.length as u64);
+            (headers, uploaded_response(media::with_url(state, media), false)).into_response()
+        }
+        Err(e) => {
+            let msg = format!("Error reading media: {:?}", e);
+            error!("{msg}");
+            ApiResponse::new(StatusCode::NOT_FOUND, &msg, None).into_response()
+        }
+    }
+}
+
+/// Handler para DELETE /uploads/resumable/{id}: cancela la subida y borra
+/// lo recibido. El medio, si ya se creó, se queda en la biblioteca.
+async fn cancel_upload(
+    State(state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = unsupported_version(&headers) {
+        return response;
+    }
+    let session = match read_session(&state, &id, &claims).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let Some(_lock) = media::lock_upload(&session.id) else {
+        return ApiResponse::new(StatusCode::LOCKED, "Upload is receiving another chunk", None).into_response();
+    };
+    discard(&state, &session.id).await;
+    (StatusCode::NO_CONTENT, tus_headers(None)).into_response()
+}
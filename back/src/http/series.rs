@@ -0,0 +1,344 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing,
+};
+use minijinja::context;
+use tracing::{debug, error};
+
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE, DEFAULT_SITE_TITLE};
+use crate::models::{
+    ApiResponse, AppState, HtmlPost, HtmlSeries, NewSeries, PagedResponse, Pagination,
+    ReadSeriesParams, Series, SeriesMembership, SeriesOrder, Setting,
+};
+
+pub fn series_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", routing::post(create))
+        .route("/", routing::patch(update))
+        .route("/", routing::get(read))
+        .route("/", routing::delete(delete))
+        .route("/html", routing::get(read_html))
+        .route("/posts", routing::get(read_posts))
+        .route("/posts", routing::post(add_post))
+        .route("/posts", routing::delete(remove_post))
+        .route("/order", routing::put(reorder))
+}
+
+/// Página índice de cada serie, renderizada en el servidor.
+pub fn series_page_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/series/{slug}", routing::get(series_page))
+        .route("/series/{slug}/", routing::get(series_page))
+}
+
+pub async fn create(
+    State(app_state): State<Arc<AppState>>,
+    Json(series): Json<NewSeries>,
+) -> impl IntoResponse {
+    debug!("Series: {:?}", series);
+    match Series::create(&app_state.pool, &series).await {
+        Ok(series) => {
+            debug!("Series created: {:?}", series);
+            ApiResponse::new(
+                StatusCode::CREATED,
+                "Created",
+                Some(serde_json::to_value(series).unwrap()),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error creating series: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn update(
+    State(app_state): State<Arc<AppState>>,
+    Json(series): Json<Series>,
+) -> impl IntoResponse {
+    debug!("Update series: {:?}", series);
+    match Series::update(&app_state.pool, &series).await {
+        Ok(series) => {
+            debug!("Series updated: {:?}", series);
+            ApiResponse::new(
+                StatusCode::OK,
+                "Series updated",
+                Some(serde_json::to_value(series).unwrap()),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error updating series: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn read(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadSeriesParams>,
+) -> impl IntoResponse {
+    debug!("Series: {:?}", params);
+    if let Some(id) = params.id {
+        let series_id: i32 = id.parse().unwrap_or(0);
+        match Series::read(&app_state.pool, series_id).await {
+            Ok(series) => {
+                debug!("Series: {:?}", series);
+                ApiResponse::new(
+                    StatusCode::OK,
+                    "Series",
+                    Some(serde_json::to_value(series).unwrap()),
+                )
+                .into_response()
+            }
+            Err(e) => {
+                let msg = format!("Error reading series: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None).into_response()
+            }
+        }
+    } else if let Some(post_id) = params.post_id {
+        match Series::read_for_post(&app_state.pool, post_id).await {
+            Ok(series) => {
+                debug!("Series for post {}: {:?}", post_id, series);
+                ApiResponse::new(
+                    StatusCode::OK,
+                    "Series",
+                    Some(serde_json::to_value(series).unwrap()),
+                )
+                .into_response()
+            }
+            Err(e) => {
+                let msg = format!("Error reading series: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None).into_response()
+            }
+        }
+    } else if let Ok(series) = Series::read_paged(&app_state.pool, &params).await
+        && let Ok(count) = Series::count_paged(&app_state.pool, &params).await
+    {
+        debug!("Series: {:?}", series);
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+        let offset = params.page.unwrap_or(DEFAULT_PAGE) - 1;
+        let total_pages = (count as f32 / limit as f32).ceil() as u32;
+        let pagination = Pagination {
+            page: offset + 1,
+            limit,
+            pages: total_pages,
+            records: count,
+            prev: if offset > 0 {
+                Some(format!("/records?page={}&limit={}", offset, limit))
+            } else {
+                None
+            },
+            next: if (offset + 1) < total_pages {
+                Some(format!("/records?page={}&limit={}", offset + 2, limit))
+            } else {
+                None
+            },
+        };
+        PagedResponse::new(
+            StatusCode::OK,
+            "results",
+            Some(serde_json::to_value(series).unwrap()),
+            pagination,
+        )
+        .into_response()
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "Error reading series", None)
+            .into_response()
+    }
+}
+
+/// Página índice de la serie: la serie y sus posts públicos en orden.
+pub async fn read_html(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadSeriesParams>,
+) -> impl IntoResponse {
+    debug!("Series: {:?}", params);
+    let series = if let Some(id) = params.id {
+        Series::read(&app_state.pool, id.parse().unwrap_or(0)).await
+    } else if let Some(slug) = params.slug {
+        Series::read_by_slug(&app_state.pool, &slug).await
+    } else {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "id or slug is mandatory", None);
+    };
+    let series = match series {
+        Ok(series) => series,
+        Err(e) => {
+            let msg = format!("Error reading series: {:?}", e);
+            error!("{}", &msg);
+            return ApiResponse::new(StatusCode::NOT_FOUND, &msg, None);
+        }
+    };
+    match html_series(&app_state, series).await {
+        Ok(html_series) => {
+            ApiResponse::new(
+                StatusCode::OK,
+                "Series",
+                Some(serde_json::to_value(html_series).unwrap()),
+            )
+        }
+        Err(e) => {
+            let msg = format!("Error reading series posts: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+/// La serie con sus posts públicos, en orden.
+async fn html_series(app_state: &AppState, series: Series) -> Result<HtmlSeries, sqlx::Error> {
+    let posts = Series::read_posts(&app_state.pool, series.id, true).await?;
+    Ok(HtmlSeries {
+        series,
+        posts: posts.iter().filter(|post| post.is_public()).map(HtmlPost::new).collect(),
+    })
+}
+
+/// Handler para GET /series/{slug}/.
+async fn series_page(State(app_state): State<Arc<AppState>>, Path(slug): Path<String>) -> Response {
+    let series = match Series::read_by_slug(&app_state.pool, &slug).await {
+        Ok(series) => series,
+        Err(sqlx::Error::RowNotFound) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Error reading series '{}': {:?}", slug, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let html_series = match html_series(&app_state, series).await {
+        Ok(html_series) => html_series,
+        Err(e) => {
+            error!("Error reading posts of series '{}': {:?}", slug, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+    let rendered = app_state.templates.get_template("series.html").and_then(|template| {
+        template.render(context! {
+            series => html_series.series,
+            posts => html_series.posts,
+            site_title => site_title,
+            base_url => app_state.base_url,
+        })
+    });
+    match rendered {
+        Ok(html) => ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+        Err(e) => {
+            error!("Error rendering series '{}': {:?}", slug, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub async fn read_posts(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadSeriesParams>,
+) -> impl IntoResponse {
+    if let Some(id) = params.id {
+        let series_id: i32 = id.parse().unwrap_or(0);
+        match Series::read_posts(&app_state.pool, series_id, false).await {
+            Ok(posts) => ApiResponse::new(
+                StatusCode::OK,
+                "Posts",
+                Some(serde_json::to_value(posts).unwrap()),
+            ),
+            Err(e) => {
+                let msg = format!("Error reading series posts: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+            }
+        }
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "series_id is mandatory", None)
+    }
+}
+
+pub async fn add_post(
+    State(app_state): State<Arc<AppState>>,
+    Json(membership): Json<SeriesMembership>,
+) -> impl IntoResponse {
+    debug!("Add post to series: {:?}", membership);
+    match Series::add_post(&app_state.pool, &membership).await {
+        Ok(series_post) => ApiResponse::new(
+            StatusCode::CREATED,
+            "Post added to series",
+            Some(serde_json::to_value(series_post).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error adding post to series: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn remove_post(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadSeriesParams>,
+) -> impl IntoResponse {
+    if let Some(post_id) = params.post_id {
+        match Series::remove_post(&app_state.pool, post_id).await {
+            Ok(series_post) => ApiResponse::new(
+                StatusCode::OK,
+                "Post removed from series",
+                Some(serde_json::to_value(series_post).unwrap()),
+            ),
+            Err(e) => {
+                let msg = format!("Error removing post from series: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::NOT_FOUND, &msg, None)
+            }
+        }
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "post_id is mandatory", None)
+    }
+}
+
+pub async fn reorder(
+    State(app_state): State<Arc<AppState>>,
+    Json(order): Json<SeriesOrder>,
+) -> impl IntoResponse {
+    debug!("Reorder series: {:?}", order);
+    match Series::reorder(&app_state.pool, &order).await {
+        Ok(series_posts) => ApiResponse::new(
+            StatusCode::OK,
+            "Series reordered",
+            Some(serde_json::to_value(series_posts).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error reordering series: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn delete(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadSeriesParams>,
+) -> impl IntoResponse {
+    if let Some(id) = params.id {
+        let series_id: i32 = id.parse().unwrap_or(0);
+        match Series::delete(&app_state.pool, series_id).await {
+            Ok(series) => ApiResponse::new(
+                StatusCode::OK,
+                "Series deleted",
+                Some(serde_json::to_value(series).unwrap()),
+            ),
+            Err(e) => {
+                let msg = format!("Error deleting series: {:?}", e);
+                error!("{}", &msg);
+                ApiResponse::new(StatusCode::NOT_FOUND, &msg, None)
+            }
+        }
+    } else {
+        ApiResponse::new(StatusCode::BAD_REQUEST, "series_id is mandatory", None)
+    }
+}
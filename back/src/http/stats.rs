@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing,
+};
+use serde_json::json;
+use tracing::{debug, error};
+
+use super::auth::Admin;
+use crate::constants::DEFAULT_REACTIONS;
+use crate::models::{
+    ApiResponse, AppState, NewReaction, NewView, Post, PostStats, ReadReactionsParams, ReadStatsParams,
+    Setting,
+};
+use crate::utils::{ClientIp, visitor_hash};
+
+pub fn stats_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/views", routing::post(record_view))
+        .route("/reactions", routing::get(read_reactions))
+        .route("/reactions", routing::post(react))
+        .route("/daily", routing::get(daily))
+        .route("/top", routing::get(top))
+}
+
+/// Solo cuentan las visitas y reacciones a posts publicados.
+async fn check_post(app_state: &AppState, post_id: i32) -> Result<(), ApiResponse> {
+    match Post::read(&app_state.pool, post_id).await {
+        Ok(post) if post.is_public() => Ok(()),
+        _ => Err(ApiResponse::new(StatusCode::NOT_FOUND, "Post not found", None)),
+    }
+}
+
+/// Reacciones admitidas, en el ajuste `reactions` separadas por comas.
+async fn allowed_reactions(app_state: &AppState) -> Vec<String> {
+    Setting::get_value(&app_state.pool, "reactions", DEFAULT_REACTIONS)
+        .await
+        .split(',')
+        .map(|reaction| reaction.trim().to_string())
+        .filter(|reaction| !reaction.is_empty())
+        .collect()
+}
+
+pub async fn record_view(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(view): Json<NewView>,
+) -> impl IntoResponse {
+    if let Err(response) = check_post(&app_state, view.post_id).await {
+        return response;
+    }
+    let visitor = visitor_hash(&app_state.secret, &headers, ip);
+    match PostStats::record_view(&app_state.pool, view.post_id, &visitor).await {
+        Ok(counted) => {
+            debug!("View of post {} counted: {}", view.post_id, counted);
+            ApiResponse::new(StatusCode::ACCEPTED, "View recorded", Some(json!({"counted": counted})))
+        }
+        Err(e) => {
+            let msg = format!("Error recording view: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn read_reactions(
+    State(app_state): State<Arc<AppState>>,
+    Query(params): Query<ReadReactionsParams>,
+) -> impl IntoResponse {
+    if let Err(response) = check_post(&app_state, params.post_id).await {
+        return response;
+    }
+    reactions_response(&app_state, params.post_id, StatusCode::OK).await
+}
+
+pub async fn react(
+    State(app_state): State<Arc<AppState>>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(reaction): Json<NewReaction>,
+) -> impl IntoResponse {
+    if let Err(response) = check_post(&app_state, reaction.post_id).await {
+        return response;
+    }
+    if !allowed_reactions(&app_state).await.contains(&reaction.reaction) {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Reaction not allowed", None);
+    }
+    let visitor = visitor_hash(&app_state.secret, &headers, ip);
+    match PostStats::record_reaction(&app_state.pool, reaction.post_id, &reaction.reaction, &visitor).await {
+        Ok(_) => reactions_response(&app_state, reaction.post_id, StatusCode::CREATED).await,
+        Err(e) => {
+            let msg = format!("Error recording reaction: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+/// Recuento de cada reacción admitida, incluidas las que aún no tiene.
+async fn reactions_response(app_state: &AppState, post_id: i32, status: StatusCode) -> ApiResponse {
+    match PostStats::reactions(&app_state.pool, post_id).await {
+        Ok(counts) => {
+            let reactions: serde_json::Map<String, serde_json::Value> = allowed_reactions(app_state)
+                .await
+                .into_iter()
+                .map(|reaction| {
+                    let total = counts
+                        .iter()
+                        .find(|count| count.reaction == reaction)
+                        .map(|count| count.total)
+                        .unwrap_or_default();
+                    (reaction, json!(total))
+                })
+                .collect();
+            ApiResponse::new(status, "Reactions", Some(json!(reactions)))
+        }
+        Err(e) => {
+            let msg = format!("Error reading reactions: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn daily(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Query(params): Query<ReadStatsParams>,
+) -> impl IntoResponse {
+    match PostStats::daily(&app_state.pool, &params).await {
+        Ok(stats) => ApiResponse::new(
+            StatusCode::OK,
+            "Daily stats",
+            Some(serde_json::to_value(stats).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error reading daily stats: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+pub async fn top(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Query(params): Query<ReadStatsParams>,
+) -> impl IntoResponse {
+    match PostStats::top(&app_state.pool, &params).await {
+        Ok(posts) => ApiResponse::new(
+            StatusCode::OK,
+            "Top posts",
+            Some(serde_json::to_value(posts).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error reading top posts: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
@@ -1,24 +1,50 @@
-use std::sync::Arc;
+use std::{path::Path as FsPath, sync::Arc};
 
 use axum::{
-    Router, extract::Multipart, extract::State, http::StatusCode, response::IntoResponse, routing,
+    Router,
+    extract::{DefaultBodyLimit, Multipart, State, multipart::Field},
+    http::StatusCode,
+    response::IntoResponse,
+    routing,
 };
 use chrono::Utc;
-use tokio::fs;
+use tower_http::services::ServeDir;
 use tracing::{debug, error};
 use uuid::Uuid;
 
-use crate::models::{ApiResponse, AppState};
+use super::resumable::resumable_router;
+use crate::constants::UPLOAD_BODY_LIMIT_BYTES;
+use crate::media::{self, FileType, UploadError, UploadPolicy, UploadQuota};
+use crate::models::{ApiResponse, AppState, Media, NewMedia, TokenClaims};
+use crate::storage::Storage;
 
 pub fn upload_router() -> Router<Arc<AppState>> {
-    Router::new().route("/", routing::post(upload_image))
+    Router::new()
+        .route("/", routing::post(upload_image))
+        .layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT_BYTES))
+        .nest("/resumable", resumable_router())
 }
 
-/// Handler para el endpoint POST /uploads
+/// Sirve los ficheros del almacenamiento en disco en la ruta de su URL
+/// pública, con peticiones `Range` para poder avanzar en un audio. En S3 los
+/// sirve el propio bucket.
+pub fn uploads_router(storage: &dyn Storage) -> Router<Arc<AppState>> {
+    let path = storage.base_url().path().trim_end_matches('/');
+    match storage.local_dir() {
+        Some(dir) if !path.is_empty() => Router::new().nest_service(path, ServeDir::new(dir)),
+        _ => Router::new(),
+    }
+}
+
+/// Handler para el endpoint POST /uploads. Comprueba el contenido real del
+/// fichero y lo guarda en la biblioteca de medios a nombre del usuario.
+/// Para ficheros grandes está la subida reanudable de `/uploads/resumable`.
 async fn upload_image(
     State(state): State<Arc<AppState>>,
+    claims: TokenClaims,
     mut multipart: Multipart,
 ) -> impl IntoResponse {
+    let policy = UploadPolicy::load(&state.pool).await;
     // Iterar sobre las partes del formulario multipart
     while let Ok(Some(field)) = multipart.next_field().await {
         let name = field.name().unwrap_or_default().to_string();
@@ -29,86 +55,215 @@ async fn upload_image(
         }
 
         let file_name = field.file_name().unwrap_or_default().to_string();
-        let content_type = field.content_type().unwrap_or_default().to_string();
-        let data = match field.bytes().await {
+        let data = match read_field(field, policy.max_size).await {
             Ok(data) => data,
             Err(e) => {
-                let msg = format!("Error reading file '{}': {:?}", file_name, e);
-                error!("{msg}");
-                return ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None)
-                    .into_response();
+                debug!("Upload of '{}' rejected: {:?}", file_name, e);
+                return ApiResponse::from(e).into_response();
             }
         };
-        if data.is_empty() {
-            return ApiResponse::new(StatusCode::BAD_REQUEST, "File is empty", None)
-                .into_response();
-        }
-        let extension = get_extension_from_mime(&content_type).unwrap_or_else(|| {
-            debug!(
-                "Can't determine extension from MIME type '{}'",
-                content_type
-            );
-            "dat".to_string()
-        });
+        let file_type = match policy.check(&data) {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                debug!("Upload of '{}' rejected: {:?}", file_name, e);
+                return ApiResponse::from(e).into_response();
+            }
+        };
+        let quota = match UploadQuota::load(&state.pool, &claims).await {
+            Ok(quota) => quota,
+            Err(e) => return ApiResponse::from(e).into_response(),
+        };
+        return match save_upload(&state, file_name, data, file_type, claims.user_id, &quota).await {
+            Ok((media, duplicate)) => uploaded_response(media, duplicate).into_response(),
+            Err(e) => ApiResponse::from(e).into_response(),
+        };
+    }
+    ApiResponse::from(UploadError::MissingFile).into_response()
+}
 
-        // --- 2. Generar la ruta basada en fecha (YY/MM/DD) ---
-        let now = Utc::now();
-        let date_path = now.format("%Y/%m/%d").to_string();
+/// Guarda un fichero ya comprobado: le quita los metadatos, lo sube al
+/// almacenamiento y lo registra en la biblioteca de medios. Si el mismo
+/// usuario ya subió uno idéntico se reutiliza y se indica con `true`; como
+/// no ocupa más, no cuenta para la cuota. Las variantes de las imágenes se generan después.
+pub(super) async fn save_upload(
+    state: &Arc<AppState>,
+    file_name: String,
+    data: Vec<u8>,
+    file_type: FileType,
+    uploader_id: i32,
+    quota: &UploadQuota,
+) -> Result<(Media, bool), UploadError> {
+    // Fuera EXIF/GPS antes de guardar y calcular el checksum
+    let data = media::strip_metadata(data);
+    let checksum = media::checksum(&data);
+    match Media::read_by_checksum(&state.pool, &checksum, uploader_id).await {
+        Ok(Some(existing)) => return Ok((reuse_media(state, existing, Content::Data(&data)).await?, true)),
+        Ok(None) => {}
+        Err(e) => error!("Error looking for duplicated media: {:?}", e),
+    }
+    quota.check(data.len() as u64)?;
 
-        // --- 3. Generar UUID para el nombre del archivo ---
-        let file_uuid = Uuid::new_v4().to_string();
-        let final_filename = format!("{}.{}", file_uuid, extension);
+    let final_path = new_path(&file_type);
+    Content::Data(&data).store(state, &final_path, file_type.mime_type).await?;
+    let dimensions = media::image_dimensions(&data);
+    let audio = media::probe_audio(file_type.mime_type, &data);
+    let new_media = NewMedia {
+        path: final_path,
+        original_filename: Some(file_name).filter(|name| !name.is_empty()),
+        mime_type: file_type.mime_type.to_string(),
+        size: data.len() as i64,
+        width: dimensions.map(|(width, _)| width as i32),
+        height: dimensions.map(|(_, height)| height as i32),
+        duration: audio.map(|audio| audio.duration as i32),
+        bitrate: audio.map(|audio| audio.bitrate as i32),
+        checksum,
+        uploader_id: Some(uploader_id),
+    };
+    Ok((create_media(state, &new_media).await?, false))
+}
 
-        // --- 4. Construir la ruta completa de guardado ---
-        let target_dir = state.static_dir
-            .join(state.upload_dir.as_path())
-            .join(&date_path);
-        let final_path = target_dir
-            .join(&final_filename);
+/// Como [`save_upload`], para lo que llega por una subida reanudable y no
+/// es una imagen (un podcast puede ocupar cientos de MB): el checksum se
+/// fue calculando con cada trozo, la duración se lee solo de las partes
+/// del fichero que hacen falta y el fichero se mueve al almacenamiento sin
+/// cargarlo en memoria.
+pub(super) async fn save_staged_upload(
+    state: &Arc<AppState>,
+    file_name: String,
+    upload_id: &str,
+    size: u64,
+    file_type: FileType,
+    uploader_id: i32,
+    quota: &UploadQuota,
+) -> Result<(Media, bool), UploadError> {
+    let staged = media::staging_path(upload_id);
+    let checksum = media::upload_checksum(upload_id, size).await.map_err(|e| {
+        let msg = format!("Error reading upload '{}': {:?}", upload_id, e);
+        error!("{msg}");
+        UploadError::Storage(msg)
+    })?;
+    match Media::read_by_checksum(&state.pool, &checksum, uploader_id).await {
+        Ok(Some(existing)) => return Ok((reuse_media(state, existing, Content::Staged(&staged)).await?, true)),
+        Ok(None) => {}
+        Err(e) => error!("Error looking for duplicated media: {:?}", e),
+    }
+    quota.check(size)?;
 
-        debug!("Guardando archivo en: {:?}", final_path);
+    let audio = media::probe_audio_file(file_type.mime_type, &staged)
+        .await
+        .unwrap_or_else(|e| {
+            error!("Error reading audio of upload '{}': {:?}", upload_id, e);
+            None
+        });
+    let final_path = new_path(&file_type);
+    Content::Staged(&staged).store(state, &final_path, file_type.mime_type).await?;
+    let new_media = NewMedia {
+        path: final_path,
+        original_filename: Some(file_name).filter(|name| !name.is_empty()),
+        mime_type: file_type.mime_type.to_string(),
+        size: size as i64,
+        width: None,
+        height: None,
+        duration: audio.map(|audio| audio.duration as i32),
+        bitrate: audio.map(|audio| audio.bitrate as i32),
+        checksum,
+        uploader_id: Some(uploader_id),
+    };
+    Ok((create_media(state, &new_media).await?, false))
+}
 
-        // --- 5. Crear directorios recursivamente ---
-        match fs::create_dir_all(&target_dir).await {
-            Ok(result) => debug!("Directorios creados: {:?}", result),
-            Err(e) => {
-                let msg = format!("Error creating directories '{:?}': {:?}", target_dir, e);
-                error!("{msg}");
-                return ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None)
-                    .into_response();
-            }
-        }
-        match fs::write(&final_path, &data).await {
-            Ok(result) => debug!("File saved: {:?}", result),
-            Err(e) => {
-                let msg = format!("Error saving file '{:?}': {:?}", final_path, e);
-                error!("{msg}");
-                return ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, &msg, None)
-                    .into_response();
+/// Contenido de una subida: en memoria o, el de una subida reanudable, en
+/// el fichero donde se juntaron los trozos.
+enum Content<'a> {
+    Data(&'a [u8]),
+    Staged(&'a FsPath),
+}
+
+impl Content<'_> {
+    async fn store(&self, state: &AppState, path: &str, content_type: &str) -> Result<(), UploadError> {
+        debug!("Guardando archivo en: {}", path);
+        let stored = match self {
+            Content::Data(data) => state.storage.put(path, data, content_type).await,
+            Content::Staged(source) => state.storage.put_file(path, source, content_type).await,
+        };
+        stored.map_err(|e| {
+            let msg = format!("Error saving file '{}': {:?}", path, e);
+            error!("{msg}");
+            UploadError::Storage(msg)
+        })
+    }
+}
+
+/// Ruta basada en fecha (YY/MM/DD) con un UUID como nombre.
+fn new_path(file_type: &FileType) -> String {
+    let date_path = Utc::now().format("%Y/%m/%d").to_string();
+    let file_uuid = Uuid::new_v4().to_string();
+    format!("{}/{}.{}", date_path, file_uuid, file_type.extension)
+}
+
+/// Registra en la biblioteca un fichero ya guardado; si no se puede, lo
+/// borra del almacenamiento para no dejarlo huérfano.
+async fn create_media(state: &Arc<AppState>, new_media: &NewMedia) -> Result<Media, UploadError> {
+    let media = match Media::create(&state.pool, new_media).await {
+        Ok(media) => media::with_url(state, media),
+        Err(e) => {
+            let msg = format!("Error saving media: {:?}", e);
+            error!("{msg}");
+            if let Err(e) = state.storage.delete(&new_media.path).await {
+                error!("Error removing file '{}': {:?}", new_media.path, e);
             }
+            return Err(UploadError::Storage(msg));
         }
-        debug!("Base url: {}", state.base_url);
-        let relative_path = format!("{}/{}/{}/{}",
-            state.base_url,
-            state.upload_dir.to_str().unwrap_or("uploads"),
-            date_path, final_filename);
-        debug!("File url: {}", &relative_path);
-        return ApiResponse::new(
-            StatusCode::OK,
-            "File uploaded successfully",
-            Some(serde_json::json!({ "file_path": relative_path })),
-        )
-        .into_response();
-    }
+    };
+    debug!("File url: {}", &media.url);
+    media::register_image(&media, &[]);
+    tokio::spawn(media::generate_variants(state.clone(), media.clone()));
+    Ok(media)
+}
+
+pub(super) fn uploaded_response(media: Media, duplicate: bool) -> ApiResponse {
+    let message = if duplicate { "File already uploaded" } else { "File uploaded successfully" };
     ApiResponse::new(
-        StatusCode::BAD_REQUEST,
-        "Did not find 'file' field in the form",
-        None,
+        StatusCode::OK,
+        message,
+        Some(serde_json::json!({ "file_path": media.url, "media": media, "duplicate": duplicate })),
     )
-    .into_response()
 }
 
-fn get_extension_from_mime(mime_type_str: &str) -> Option<String> {
-    mime2ext::mime2ext(mime_type_str)
-        .map(|ext| ext.to_string())
+/// Mismo contenido que un fichero ya subido por el mismo usuario: se
+/// devuelve ese medio en vez de guardar una copia. Si el fichero se perdió
+/// del almacenamiento se vuelve a escribir en su ruta. El dueño no cambia
+/// nunca por subir una copia.
+async fn reuse_media(state: &Arc<AppState>, existing: Media, content: Content<'_>) -> Result<Media, UploadError> {
+    let path = &existing.path;
+    if !state.storage.exists(path).await.unwrap_or(false) {
+        debug!("Restoring missing file: {}", path);
+        content.store(state, path, &existing.mime_type).await?;
+    }
+    let media = media::with_url(state, existing);
+    debug!("Duplicated upload, reusing: {}", &media.url);
+    Ok(media)
+}
+
+/// Lee el campo por trozos y corta en cuanto supera el tamaño máximo, sin
+/// llegar a cargar el fichero entero en memoria.
+async fn read_field(mut field: Field<'_>, max_size: u64) -> Result<Vec<u8>, UploadError> {
+    let mut data = Vec::new();
+    loop {
+        match field.chunk().await {
+            Ok(Some(chunk)) => {
+                if (data.len() + chunk.len()) as u64 > max_size {
+                    return Err(UploadError::TooLarge { max_size });
+                }
+                data.extend_from_slice(&chunk);
+            }
+            Ok(None) => return Ok(data),
+            Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                return Err(UploadError::TooLarge {
+                    max_size: max_size.min(UPLOAD_BODY_LIMIT_BYTES as u64),
+                });
+            }
+            Err(e) => return Err(UploadError::Storage(format!("Error reading file: {:?}", e))),
+        }
+    }
 }
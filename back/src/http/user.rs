@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use axum::{
     body,
-    extract::State,
+    extract::{Query, State},
     http::{header, StatusCode},
     response::{IntoResponse, Response},
     routing, Json, Router,
@@ -13,7 +13,9 @@ use tracing::{debug, error};
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use jsonwebtoken::{encode, EncodingKey, Header};
 
-use crate::models::{ApiResponse, AppState, TokenClaims, User, UserSchema, UserRegister};
+use crate::models::{
+    ApiResponse, AppState, ProfileParams, TokenClaims, User, UserSchema, UserRegister, UserProfile,
+};
 
 pub fn user_router() -> Router<Arc<AppState>> {
     Router::new()
@@ -26,6 +28,7 @@ pub fn api_user_router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/", routing::get(read))
         .route("/any", routing::get(any_user_exists))
+        .route("/profile", routing::patch(update_profile))
 }
 
 
@@ -103,6 +106,35 @@ pub async fn register(
     }
 }
 
+/// Cada usuario edita su propio perfil; un administrador, el de cualquiera
+/// con `?user_id=`.
+pub async fn update_profile(
+    State(app_state): State<Arc<AppState>>,
+    claims: TokenClaims,
+    Query(params): Query<ProfileParams>,
+    Json(profile): Json<UserProfile>,
+) -> impl IntoResponse {
+    debug!("User profile: {:?}", profile);
+    let user_id = match params.user_id {
+        Some(user_id) if user_id != claims.user_id && !claims.is_admin() => {
+            return ApiResponse::new(StatusCode::FORBIDDEN, "Admin role required", None);
+        }
+        Some(user_id) => user_id,
+        None => claims.user_id,
+    };
+    match User::update_profile(&app_state.pool, user_id, &profile).await {
+        Ok(user) => {
+            debug!("Profile updated: {:?}", user);
+            ApiResponse::new(StatusCode::OK, "Profile updated", Some(serde_json::to_value(user).unwrap()))
+        },
+        Err(e) => {
+            let msg = format!("Error updating profile: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
 pub async fn logout() -> impl IntoResponse {
     debug!("Logout");
     let cookie = Cookie::build(("token", ""))
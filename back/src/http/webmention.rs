@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use axum::{
+    Form, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing,
+};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+use super::auth::Admin;
+use crate::models::{ApiResponse, AppState, ReadWebmentionParams, Webmention, WebmentionDirection};
+use crate::utils::parse_public_url;
+use crate::webmention::target_post;
+
+#[derive(Debug, Deserialize)]
+pub struct IncomingWebmention {
+    pub source: String,
+    pub target: String,
+}
+
+/// Endpoint público de webmentions, anunciado en el `<head>` de las páginas.
+pub fn webmention_router() -> Router<Arc<AppState>> {
+    Router::new().route("/webmention", routing::post(receive))
+}
+
+pub fn api_webmention_router() -> Router<Arc<AppState>> {
+    Router::new().route("/", routing::get(read))
+}
+
+/// Valida la petición y la deja en la cola; la verificación de la fuente se
+/// hace después, por eso se responde `202 Accepted`.
+pub async fn receive(
+    State(app_state): State<Arc<AppState>>,
+    Form(webmention): Form<IncomingWebmention>,
+) -> impl IntoResponse {
+    debug!("Webmention: {:?}", webmention);
+    if parse_public_url(&webmention.source).is_none() {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Invalid source", None);
+    }
+    if webmention.source == webmention.target {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Source and target must differ", None);
+    }
+    if target_post(&app_state, &webmention.target).await.is_none() {
+        return ApiResponse::new(StatusCode::BAD_REQUEST, "Target is not a published post", None);
+    }
+    match Webmention::enqueue(
+        &app_state.pool,
+        WebmentionDirection::Incoming,
+        &webmention.source,
+        &webmention.target,
+    )
+    .await
+    {
+        Ok(webmention) => ApiResponse::new(
+            StatusCode::ACCEPTED,
+            "Webmention accepted",
+            Some(serde_json::to_value(webmention).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error queuing webmention: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
+
+/// Estado de la cola de webmentions enviadas y recibidas.
+pub async fn read(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Query(params): Query<ReadWebmentionParams>,
+) -> impl IntoResponse {
+    match Webmention::read(&app_state.pool, &params).await {
+        Ok(webmentions) => ApiResponse::new(
+            StatusCode::OK,
+            "Webmentions",
+            Some(serde_json::to_value(webmentions).unwrap()),
+        ),
+        Err(e) => {
+            let msg = format!("Error reading webmentions: {:?}", e);
+            error!("{}", &msg);
+            ApiResponse::new(StatusCode::BAD_REQUEST, &msg, None)
+        }
+    }
+}
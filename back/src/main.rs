@@ -2,6 +2,12 @@ mod http;
 mod models;
 mod constants;
 mod utils;
+mod spam;
+mod notifications;
+mod webmention;
+mod activitypub;
+mod media;
+mod storage;
 
 use axum::{
     Router,
@@ -11,6 +17,7 @@ use axum::{
             AUTHORIZATION,
             CONTENT_TYPE
         },
+        HeaderName,
         Method,
     },
 };
@@ -23,7 +30,7 @@ use tower_http::{
 };
 use std::sync::Arc;
 use sqlx::{
-    postgres::PgPoolOptions,
+    postgres::{PgPool, PgPoolOptions},
     migrate::{
         Migrator,
         MigrateDatabase
@@ -34,12 +41,13 @@ use tracing_subscriber::{
     layer::SubscriberExt,
     util::SubscriberInitExt
 };
-use tower_http::services::{ServeDir, ServeFile};
-use tracing::info;
+use tracing::{error, info};
 use std::{
     str::FromStr,
     env::var,
+    net::SocketAddr,
     path::Path,
+    time::Duration,
 };
 use http::{
     health_router,
@@ -49,11 +57,35 @@ use http::{
     tag_router,
     comment_router,
     upload_router,
+    uploads_router,
+    TUS_REQUEST_HEADERS,
+    TUS_RESPONSE_HEADERS,
+    redirect_router,
+    series_page_router,
+    series_router,
+    author_page_router,
+    author_router,
+    feed_router,
+    page_router,
+    webmention_router,
+    api_webmention_router,
+    activitypub_router,
+    stats_router,
+    media_router,
+    og_router,
+    serve_page,
 };
 use dotenv::dotenv;
+use constants::{STATS_PURGE_INTERVAL_SECONDS, UPLOAD_SESSION_PURGE_INTERVAL_SECONDS};
+use spam::SpamPipeline;
+use notifications::Notifier;
+use minijinja::{Environment, path_loader};
+use utils::TrustedProxies;
 use models::{
     AppState,
+    Comment,
     Error,
+    PostStats,
 };
 
 #[tokio::main]
@@ -99,6 +131,51 @@ async fn main() -> Result<(), Error> {
         .await
         .unwrap();
 
+    match Comment::render_missing(&pool).await {
+        Ok(0) => {}
+        Ok(total) => info!("Rendered {} comments", total),
+        Err(e) => error!("Error rendering comments: {:?}", e),
+    }
+
+    if let Err(e) = activitypub::ensure_keys(&pool).await {
+        error!("Error generating ActivityPub keys: {}", e);
+    }
+
+    let mut templates = Environment::new();
+    templates.set_loader(path_loader("templates"));
+
+    let spam = Arc::new(SpamPipeline::default_filters(&secret, &base_url));
+    let notifier = Arc::new(Notifier::from_env());
+    let storage = storage::from_env(&base_url, Path::new("static")).expect("Storage configuration failed");
+
+    let app_state = Arc::new(AppState {
+        pool,
+        secret,
+        static_dir: "static".into(),
+        storage,
+        base_url,
+        templates,
+        spam,
+        notifier,
+        trusted_proxies: TrustedProxies::from_env(),
+    });
+
+    // `back media-gc [--delete] [--grace-days=N]`: revisa la biblioteca y
+    // termina sin arrancar el servidor
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("media-gc") {
+        return media_gc(&app_state, &args[1..]).await;
+    }
+
+    match media::load_images(&app_state.pool, app_state.storage.base_url()).await {
+        Ok(total) => info!("Loaded {} images", total),
+        Err(e) => error!("Error loading images: {:?}", e),
+    }
+
+    tokio::spawn(webmention::worker(app_state.clone()));
+    tokio::spawn(purge_visitors(app_state.pool.clone()));
+    tokio::spawn(purge_uploads(app_state.pool.clone()));
+
     let api_routes = Router::new()
         .nest("/health", health_router())
         .nest("/auth", user_router())
@@ -107,33 +184,92 @@ async fn main() -> Result<(), Error> {
         .nest("/tags", tag_router())
         .nest("/comments", comment_router())
         .nest("/uploads", upload_router())
-        .with_state(Arc::new(AppState {
-            pool,
-            secret,
-            static_dir: "static".into(),
-            upload_dir: "uploads".into(),
-            base_url,
-    }));
+        .nest("/series", series_router())
+        .nest("/authors", author_router())
+        .nest("/pages", page_router())
+        .nest("/webmentions", api_webmention_router())
+        .nest("/stats", stats_router())
+        .nest("/media", media_router());
 
     let cors = CorsLayer::new()
         //.allow_origin(url.parse::<HeaderValue>().unwrap())
         .allow_origin(Any)
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH,
-            Method::DELETE])
+            Method::DELETE, Method::HEAD, Method::OPTIONS])
         //.allow_credentials(true)
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE].into_iter()
+            .chain(TUS_REQUEST_HEADERS)
+            .collect::<Vec<HeaderName>>())
+        .expose_headers(TUS_RESPONSE_HEADERS);
 
     let app = Router::new()
         .nest("/api/v1", api_routes)
-        .fallback_service(ServeDir::new("static")
-        .fallback(ServeFile::new("static/index.html")))
+        .merge(feed_router())
+        .merge(webmention_router())
+        .merge(activitypub_router())
+        .merge(series_page_router())
+        .merge(author_page_router())
+        .merge(redirect_router())
+        .merge(og_router())
+        .merge(uploads_router(app_state.storage.as_ref()))
+        .fallback(serve_page)
+        .with_state(app_state)
         .layer(TraceLayer::new_for_http())
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
     tracing::info!("🚀 Server started successfully 🚀");
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+
+    Ok(())
+}
 
+/// Lista los ficheros huérfanos de la biblioteca o, con `--delete`, borra
+/// los que han pasado el periodo de gracia.
+async fn media_gc(app_state: &AppState, args: &[String]) -> Result<(), Error> {
+    let requested = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--grace-days="))
+        .and_then(|days| days.parse().ok());
+    let grace_days = media::grace_days(&app_state.pool, requested).await;
+    if args.iter().any(|arg| arg == "--delete") {
+        for deleted in media::delete_orphans(app_state, grace_days).await? {
+            println!("deleted\t{}", deleted.path);
+        }
+        return Ok(());
+    }
+    let orphans = media::scan_orphans(app_state, grace_days).await?;
+    for (state, items) in [("deletable", orphans.deletable), ("waiting", orphans.waiting)] {
+        for item in items {
+            let orphaned_at = item.orphaned_at.map(|date| date.to_rfc3339()).unwrap_or_default();
+            println!("{}\t{}\t{}", state, orphaned_at, item.path);
+        }
+    }
     Ok(())
 }
 
+/// Borra periódicamente los hashes de visitantes de días anteriores.
+async fn purge_visitors(pool: PgPool) {
+    let mut interval = tokio::time::interval(Duration::from_secs(STATS_PURGE_INTERVAL_SECONDS));
+    loop {
+        interval.tick().await;
+        match PostStats::purge_visitors(&pool).await {
+            Ok(0) => {}
+            Ok(total) => info!("Purged {} visitor hashes", total),
+            Err(e) => error!("Error purging visitor hashes: {:?}", e),
+        }
+    }
+}
+
+/// Borra periódicamente las subidas reanudables abandonadas.
+async fn purge_uploads(pool: PgPool) {
+    let mut interval = tokio::time::interval(Duration::from_secs(UPLOAD_SESSION_PURGE_INTERVAL_SECONDS));
+    loop {
+        interval.tick().await;
+        match media::purge_uploads(&pool).await {
+            Ok(0) => {}
+            Ok(total) => info!("Purged {} abandoned uploads", total),
+            Err(e) => error!("Error purging uploads: {:?}", e),
+        }
+    }
+}
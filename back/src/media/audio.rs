@@ -0,0 +1,461 @@
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+};
+
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+use super::{be_u32, le_u32};
+
+/// Duración y bitrate medio de un fichero de audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioInfo {
+    /// Segundos
+    pub duration: u32,
+    /// Kbit/s
+    pub bitrate: u32,
+}
+
+impl AudioInfo {
+    /// El bitrate medio sale del tamaño total y la duración.
+    fn from_seconds(seconds: f64, size: usize) -> Option<Self> {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return None;
+        }
+        Some(AudioInfo {
+            duration: seconds.round() as u32,
+            bitrate: (size as f64 * 8.0 / seconds / 1000.0).round() as u32,
+        })
+    }
+}
+
+/// Lee la duración de un MP3, M4A u OGG (Vorbis u Opus) sin decodificar el
+/// audio: cabeceras de trama y Xing/VBRI, la caja `mvhd` o la posición del
+/// último paquete.
+pub fn probe_audio(mime_type: &str, data: &[u8]) -> Option<AudioInfo> {
+    match mime_type {
+        "audio/mpeg" => probe_mp3(data),
+        "audio/mp4" | "video/mp4" => probe_mp4(data),
+        "audio/ogg" => probe_ogg(data),
+        _ => None,
+    }
+}
+
+/// Cabecera de una trama MPEG de audio, capa III.
+#[derive(Debug)]
+struct FrameHeader {
+    mpeg1: bool,
+    mono: bool,
+    bitrate: u32,
+    sample_rate: u32,
+    padding: bool,
+}
+
+impl FrameHeader {
+    fn parse(data: &[u8]) -> Option<Self> {
+        const MPEG1_BITRATES: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+        const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+        const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+        let header = data.get(..4)?;
+        if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 || (header[1] >> 1) & 0x03 != 0x01 {
+            return None;
+        }
+        let version = (header[1] >> 3) & 0x03;
+        let bitrate_index = (header[2] >> 4) as usize;
+        let sample_rate_index = ((header[2] >> 2) & 0x03) as usize;
+        if version == 0x01 || bitrate_index == 0 || bitrate_index == 0x0F || sample_rate_index == 0x03 {
+            return None;
+        }
+        let mpeg1 = version == 0x03;
+        Some(FrameHeader {
+            mpeg1,
+            mono: header[3] >> 6 == 0x03,
+            bitrate: if mpeg1 { MPEG1_BITRATES } else { MPEG2_BITRATES }[bitrate_index],
+            // MPEG 2 a la mitad y MPEG 2.5 a la cuarta parte
+            sample_rate: SAMPLE_RATES[sample_rate_index] >> (3 - version).min(2),
+            padding: header[2] & 0x02 != 0,
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        if self.mpeg1 { 1152 } else { 576 }
+    }
+
+    fn len(&self) -> usize {
+        (self.samples() / 8 * self.bitrate * 1000 / self.sample_rate) as usize + self.padding as usize
+    }
+
+    /// Tamaño de la información lateral, tras la que va la cabecera Xing.
+    fn side_info_len(&self) -> usize {
+        match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+}
+
+fn probe_mp3(data: &[u8]) -> Option<AudioInfo> {
+    // Etiqueta ID3v1 al final
+    let id3v1 = data.len() >= 128 && &data[data.len() - 128..data.len() - 125] == b"TAG";
+    probe_mp3_head(data, data.len(), id3v1)
+}
+
+/// Tamaño de la etiqueta ID3v2 del principio, si la hay.
+fn id3v2_len(data: &[u8]) -> usize {
+    if !data.starts_with(b"ID3") || data.len() < 10 {
+        return 0;
+    }
+    // Tamaño en enteros "synchsafe" de 7 bits
+    let size = data[6..10].iter().fold(0usize, |size, byte| (size << 7) | (*byte & 0x7F) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    10 + size + footer
+}
+
+/// Basta con el principio del fichero (etiqueta ID3v2 y primeras tramas),
+/// su tamaño total y si acaba en una etiqueta ID3v1.
+fn probe_mp3_head(data: &[u8], size: usize, id3v1: bool) -> Option<AudioInfo> {
+    let start = id3v2_len(data);
+    let end = if id3v1 { size.checked_sub(128)? } else { size };
+    let data = &data[..data.len().min(end)];
+    // Primera trama cuya siguiente también es válida, para no confundir
+    // datos sueltos con una cabecera
+    let (offset, header) = (start..data.len().saturating_sub(4)).find_map(|i| {
+        let header = FrameHeader::parse(&data[i..])?;
+        let next = i + header.len();
+        (next + 4 > data.len() || FrameHeader::parse(&data[next..]).is_some()).then_some((i, header))
+    })?;
+    let audio_size = end - offset;
+
+    // Los ficheros VBR llevan el número de tramas en una cabecera Xing/Info
+    // o VBRI en la primera trama
+    let xing = offset + 4 + header.side_info_len();
+    let frames = match data.get(xing..xing + 4) {
+        Some(b"Xing") | Some(b"Info") if be_u32(data, xing + 4).is_some_and(|flags| flags & 0x01 != 0) => {
+            be_u32(data, xing + 8)
+        }
+        _ if data.get(offset + 36..offset + 40) == Some(b"VBRI") => be_u32(data, offset + 36 + 14),
+        _ => None,
+    };
+    match frames.filter(|frames| *frames > 0) {
+        Some(frames) => {
+            let seconds = frames as f64 * header.samples() as f64 / header.sample_rate as f64;
+            AudioInfo::from_seconds(seconds, audio_size)
+        }
+        None => Some(AudioInfo {
+            duration: (audio_size as f64 * 8.0 / (header.bitrate as f64 * 1000.0)).round() as u32,
+            bitrate: header.bitrate,
+        }),
+    }
+}
+
+fn probe_mp4(data: &[u8]) -> Option<AudioInfo> {
+    probe_moov(find_box(data, b"moov")?, data.len())
+}
+
+fn probe_moov(moov: &[u8], size: usize) -> Option<AudioInfo> {
+    let mvhd = find_box(moov, b"mvhd")?;
+    let (timescale, duration) = match mvhd.first()? {
+        0 => (be_u32(mvhd, 12)?, be_u32(mvhd, 16)? as u64),
+        1 => (be_u32(mvhd, 20)?, u64::from_be_bytes(mvhd.get(24..32)?.try_into().ok()?)),
+        _ => return None,
+    };
+    if timescale == 0 {
+        return None;
+    }
+    AudioInfo::from_seconds(duration as f64 / timescale as f64, size)
+}
+
+/// Tamaño de la cabecera y de la caja que empieza en `data`; `remaining`
+/// es lo que queda hasta el final del nivel.
+fn box_size(data: &[u8], remaining: usize) -> Option<(usize, usize)> {
+    let (header, size) = match be_u32(data, 0)? {
+        0 => (8, remaining),
+        1 => (16, usize::try_from(u64::from_be_bytes(data.get(8..16)?.try_into().ok()?)).ok()?),
+        size => (8, size as usize),
+    };
+    // Con el tamaño mínimo de la cabecera cada caja avanza; un tamaño
+    // absurdo no puede desbordar ni dejar el bucle en el sitio
+    (size >= header).then_some((header, size))
+}
+
+/// Contenido de la primera caja de un tipo en este nivel.
+fn find_box<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut i = 0;
+    while data.len().saturating_sub(i) >= 8 {
+        let (header, size) = box_size(&data[i..], data.len() - i)?;
+        let end = i.checked_add(size)?;
+        if &data[i + 4..i + 8] == box_type {
+            return data.get(i + header..end.min(data.len()));
+        }
+        i = end;
+    }
+    None
+}
+
+fn probe_ogg(data: &[u8]) -> Option<AudioInfo> {
+    probe_ogg_parts(data, data, data.len())
+}
+
+/// La cabecera del flujo está en la primera página (`head`) y la duración
+/// en la última (`tail`).
+fn probe_ogg_parts(data: &[u8], tail: &[u8], size: usize) -> Option<AudioInfo> {
+    if !data.starts_with(b"OggS") {
+        return None;
+    }
+    let serial = le_u32(data, 14)?;
+    let segments = *data.get(26)? as usize;
+    let packet = data.get(27 + segments..)?;
+    // Vorbis da la frecuencia en su cabecera; Opus siempre cuenta a 48 kHz
+    // y descuenta las muestras iniciales
+    let (sample_rate, pre_skip) = if packet.starts_with(b"\x01vorbis") {
+        (le_u32(packet, 12)?, 0)
+    } else if packet.starts_with(b"OpusHead") {
+        (48000, u16::from_le_bytes(packet.get(10..12)?.try_into().ok()?) as u64)
+    } else {
+        return None;
+    };
+    if sample_rate == 0 {
+        return None;
+    }
+    // La posición del último paquete del flujo está en su última página
+    let granule = (0..tail.len().saturating_sub(27))
+        .rev()
+        .filter(|&i| &tail[i..i + 4] == b"OggS" && le_u32(tail, i + 14) == Some(serial))
+        .find_map(|i| {
+            let granule = u64::from_le_bytes(tail.get(i + 6..i + 14)?.try_into().ok()?);
+            (granule != u64::MAX).then_some(granule)
+        })?;
+    AudioInfo::from_seconds(granule.saturating_sub(pre_skip) as f64 / sample_rate as f64, size)
+}
+
+/// Lo que se lee del fichero para las tramas MP3 tras la etiqueta ID3v2 y
+/// para la primera y la última página OGG (cada una ocupa menos de 64 KB).
+const PROBE_WINDOW: u64 = 256 * 1024;
+/// Mayor caja `moov` que se llega a leer.
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Como [`probe_audio`], pero leyendo del fichero solo lo necesario: el
+/// principio y el final, o la caja `moov`. Para los ficheros grandes de
+/// las subidas reanudables.
+pub async fn probe_audio_file(mime_type: &str, path: &Path) -> io::Result<Option<AudioInfo>> {
+    let mut file = File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let info = match mime_type {
+        "audio/mpeg" => {
+            let head = read_at(&mut file, 0, 10).await?;
+            let head = read_at(&mut file, 0, id3v2_len(&head) as u64 + PROBE_WINDOW).await?;
+            let tail = read_at(&mut file, size.saturating_sub(128), 128).await?;
+            let id3v1 = size >= 128 && tail.starts_with(b"TAG");
+            probe_mp3_head(&head, size as usize, id3v1)
+        }
+        "audio/mp4" | "video/mp4" => match find_box_in_file(&mut file, size, b"moov").await? {
+            Some(moov) => probe_moov(&moov, size as usize),
+            None => None,
+        },
+        "audio/ogg" => {
+            let head = read_at(&mut file, 0, PROBE_WINDOW).await?;
+            let tail = read_at(&mut file, size.saturating_sub(PROBE_WINDOW), PROBE_WINDOW).await?;
+            probe_ogg_parts(&head, &tail, size as usize)
+        }
+        _ => None,
+    };
+    Ok(info)
+}
+
+async fn read_at(file: &mut File, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut data = Vec::new();
+    file.take(len).read_to_end(&mut data).await?;
+    Ok(data)
+}
+
+/// Recorre las cajas del primer nivel saltando de cabecera en cabecera.
+async fn find_box_in_file(file: &mut File, size: u64, box_type: &[u8; 4]) -> io::Result<Option<Vec<u8>>> {
+    let mut i: u64 = 0;
+    while size.saturating_sub(i) >= 8 {
+        let head = read_at(file, i, 16).await?;
+        let Some((header, box_size)) = box_size(&head, (size - i) as usize) else {
+            return Ok(None);
+        };
+        let Some(end) = i.checked_add(box_size as u64) else {
+            return Ok(None);
+        };
+        if &head[4..8] == box_type {
+            let len = end.min(size) - i - header as u64;
+            if len > MAX_MOOV_SIZE {
+                return Ok(None);
+            }
+            return read_at(file, i + header as u64, len).await.map(Some);
+        }
+        i = end;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trama MPEG 1 capa III a 128 kbit/s y 44,1 kHz: 417 bytes.
+    fn mp3_frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.extend_from_slice(payload);
+        frame.resize(417, 0);
+        frame
+    }
+
+    #[test]
+    fn mp3_cbr_duration_from_size() {
+        let data: Vec<u8> = (0..300).flat_map(|_| mp3_frame(&[])).collect();
+        assert_eq!(probe_audio("audio/mpeg", &data), Some(AudioInfo { duration: 8, bitrate: 128 }));
+    }
+
+    #[test]
+    fn mp3_vbr_duration_from_xing_header() {
+        let mut xing = vec![0u8; 32];
+        xing.extend_from_slice(b"Xing");
+        xing.extend_from_slice(&1u32.to_be_bytes());
+        xing.extend_from_slice(&1000u32.to_be_bytes());
+        let mut data = b"ID3\x04\0\0\0\0\0\x0a".to_vec();
+        data.extend_from_slice(&[0u8; 10]);
+        data.extend(mp3_frame(&xing));
+        data.extend((0..10).flat_map(|_| mp3_frame(&[])));
+        // 1000 tramas de 1152 muestras a 44,1 kHz
+        assert_eq!(probe_audio("audio/mpeg", &data).map(|info| info.duration), Some(26));
+    }
+
+    #[test]
+    fn mp3_without_frames_is_unknown() {
+        assert_eq!(probe_audio("audio/mpeg", b""), None);
+        assert_eq!(probe_audio("audio/mpeg", &[0xFF; 64]), None);
+        assert_eq!(probe_audio("audio/mpeg", b"ID3\x04\0\0\x7f\x7f\x7f\x7f"), None);
+    }
+
+    fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut mp4_box = (payload.len() as u32 + 8).to_be_bytes().to_vec();
+        mp4_box.extend_from_slice(box_type);
+        mp4_box.extend_from_slice(payload);
+        mp4_box
+    }
+
+    fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut mvhd = vec![0u8; 12];
+        mvhd.extend_from_slice(&timescale.to_be_bytes());
+        mvhd.extend_from_slice(&duration.to_be_bytes());
+        mvhd.resize(100, 0);
+        mp4_box(b"mvhd", &mvhd)
+    }
+
+    #[test]
+    fn mp4_duration_from_mvhd() {
+        let mut data = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        data.extend(mp4_box(b"free", &[0u8; 16]));
+        data.extend(mp4_box(b"moov", &mvhd(1000, 60_000)));
+        let info = probe_audio("audio/mp4", &data).unwrap();
+        assert_eq!(info.duration, 60);
+        assert_eq!(probe_audio("audio/mp4", &mp4_box(b"moov", &mvhd(0, 60_000))), None);
+    }
+
+    #[test]
+    fn mp4_rejects_bogus_box_sizes() {
+        // `largesize` que desbordaría la posición
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        data.extend(mp4_box(b"moov", &mvhd(1000, 60_000)));
+        assert_eq!(find_box(&data, b"moov"), None);
+        assert_eq!(probe_audio("audio/mp4", &data), None);
+        // Tamaños menores que la cabecera
+        let mut data = 4u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"free");
+        assert_eq!(find_box(&data, b"moov"), None);
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&8u64.to_be_bytes());
+        assert_eq!(find_box(&data, b"moov"), None);
+        // Tamaño 0: la caja llega hasta el final
+        let mut data = 0u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"moov");
+        data.extend(mvhd(1000, 5000));
+        assert_eq!(probe_audio("audio/mp4", &data).map(|info| info.duration), Some(5));
+        assert_eq!(probe_audio("audio/mp4", &data[..20]), None);
+    }
+
+    fn ogg_page(granule: u64, serial: u32, packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&[0u8; 8]);
+        page.push(1);
+        page.push(packet.len() as u8);
+        page.extend_from_slice(packet);
+        page
+    }
+
+    #[test]
+    fn ogg_opus_duration_from_last_granule() {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&48000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        let mut data = ogg_page(0, 7, &head);
+        data.extend(ogg_page(48000 * 5, 7, &[0u8; 100]));
+        data.extend(ogg_page(48000 * 10 + 312, 7, &[0u8; 100]));
+        // Página de otro flujo al final
+        data.extend(ogg_page(48000 * 99, 8, &[0u8; 10]));
+        assert_eq!(probe_audio("audio/ogg", &data).map(|info| info.duration), Some(10));
+    }
+
+    #[test]
+    fn ogg_vorbis_duration_uses_its_sample_rate() {
+        let mut head = b"\x01vorbis\0\0\0\0\x02".to_vec();
+        head.extend_from_slice(&44100u32.to_le_bytes());
+        head.resize(30, 0);
+        let mut data = ogg_page(0, 1, &head);
+        data.extend(ogg_page(44100 * 3, 1, &[0u8; 50]));
+        assert_eq!(probe_audio("audio/ogg", &data).map(|info| info.duration), Some(3));
+    }
+
+    #[test]
+    fn ogg_rejects_unknown_and_truncated_streams() {
+        assert_eq!(probe_audio("audio/ogg", b"OggS"), None);
+        assert_eq!(probe_audio("audio/ogg", &ogg_page(0, 1, b"\x80theora")), None);
+        let mut head = b"\x01vorbis\0\0\0\0\x02".to_vec();
+        head.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(probe_audio("audio/ogg", &ogg_page(100, 1, &head)), None);
+        // Solo la cabecera, sin paquetes de audio
+        let mut head = b"\x01vorbis\0\0\0\0\x02".to_vec();
+        head.extend_from_slice(&44100u32.to_le_bytes());
+        assert_eq!(probe_audio("audio/ogg", &ogg_page(0, 1, &head)), None);
+    }
+
+    /// Lo leído a trozos del disco tiene que dar lo mismo que el fichero
+    /// entero en memoria, también cuando pasa de la ventana que se lee.
+    #[tokio::test]
+    async fn file_probe_matches_in_memory_probe() {
+        let mp3: Vec<u8> = (0..1000).flat_map(|_| mp3_frame(&[])).collect();
+        let mut mp4 = mp4_box(b"ftyp", b"M4A \0\0\0\0");
+        mp4.extend(mp4_box(b"mdat", &vec![0u8; 1024 * 1024]));
+        mp4.extend(mp4_box(b"moov", &mvhd(1000, 90_000)));
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend_from_slice(&0u16.to_le_bytes());
+        head.extend_from_slice(&48000u32.to_le_bytes());
+        let mut ogg = ogg_page(0, 7, &head);
+        for i in 1..4000 {
+            ogg.extend(ogg_page(960 * i, 7, &[0u8; 200]));
+        }
+        let path = std::env::temp_dir().join(format!("probe-{}", uuid::Uuid::new_v4()));
+        for (mime_type, data) in [("audio/mpeg", mp3), ("audio/mp4", mp4), ("audio/ogg", ogg)] {
+            assert!(data.len() as u64 > PROBE_WINDOW);
+            tokio::fs::write(&path, &data).await.unwrap();
+            let info = probe_audio_file(mime_type, &path).await.unwrap();
+            assert!(info.is_some(), "{}", mime_type);
+            assert_eq!(info, probe_audio(mime_type, &data), "{}", mime_type);
+        }
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
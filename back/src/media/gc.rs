@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use regex::Regex;
+use sqlx::{Error, postgres::PgPool};
+use tracing::{error, info};
+
+use super::delete_media;
+use crate::constants::DEFAULT_MEDIA_GC_GRACE_DAYS;
+use crate::models::{AppState, Media, Setting};
+
+/// Resultado de revisar la biblioteca: huérfanos que ya han pasado el
+/// periodo de gracia y los que todavía no.
+#[derive(Debug)]
+pub struct Orphans {
+    pub deletable: Vec<Media>,
+    pub waiting: Vec<Media>,
+}
+
+/// Periodo de gracia pedido o, si no, el de `media_gc_grace_days`.
+pub async fn grace_days(pool: &PgPool, requested: Option<i64>) -> i64 {
+    match requested {
+        Some(days) => days.max(0),
+        None => Setting::get_value(pool, "media_gc_grace_days", &DEFAULT_MEDIA_GC_GRACE_DAYS.to_string())
+            .await
+            .trim()
+            .parse()
+            .unwrap_or(DEFAULT_MEDIA_GC_GRACE_DAYS),
+    }
+}
+
+/// Rutas de la biblioteca enlazadas desde el markdown, la portada o el
+/// audio de los posts y desde las páginas.
+async fn referenced_paths(app_state: &AppState) -> Result<Vec<String>, Error> {
+    // URL completa o solo la ruta (`/uploads/`), como en las imágenes
+    let base_url = app_state.storage.base_url();
+    let mut prefixes = regex::escape(base_url.as_str());
+    if base_url.path().len() > 1 {
+        prefixes = format!("{}|{}", prefixes, regex::escape(base_url.path()));
+    }
+    let url_regex = Regex::new(&format!(r#"(?:{})([^\s"'()<>\[\]?#]+)"#, prefixes)).unwrap();
+    let mut paths = HashSet::new();
+    for source in Media::read_reference_sources(&app_state.pool).await? {
+        for caps in url_regex.captures_iter(&source) {
+            paths.insert(caps[1].to_string());
+        }
+    }
+    Ok(paths.into_iter().collect())
+}
+
+/// Vuelve a calcular qué ficheros están huérfanos y los separa según el
+/// periodo de gracia.
+pub async fn scan_orphans(app_state: &AppState, grace_days: i64) -> Result<Orphans, Error> {
+    let referenced = referenced_paths(app_state).await?;
+    let changed = Media::mark_orphans(&app_state.pool, &referenced).await?;
+    info!("Media references: {} paths, {} changes", referenced.len(), changed);
+    let limit = Utc::now() - Duration::days(grace_days);
+    let (deletable, waiting) = Media::read_orphans(&app_state.pool)
+        .await?
+        .into_iter()
+        .partition(|media| media.orphaned_at.is_some_and(|orphaned_at| orphaned_at <= limit));
+    Ok(Orphans { deletable, waiting })
+}
+
+/// Borra los huérfanos que llevan más de `grace_days` sin usarse.
+pub async fn delete_orphans(app_state: &AppState, grace_days: i64) -> Result<Vec<Media>, Error> {
+    let orphans = scan_orphans(app_state, grace_days).await?;
+    let mut deleted = Vec::with_capacity(orphans.deletable.len());
+    for media in orphans.deletable {
+        match delete_media(app_state, media.id).await {
+            Ok(media) => deleted.push(media),
+            Err(e) => error!("Error deleting orphan '{}': {:?}", media.path, e),
+        }
+    }
+    info!("Deleted {} orphaned media", deleted.len());
+    Ok(deleted)
+}
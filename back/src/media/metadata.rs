@@ -0,0 +1,339 @@
+use super::{be_u16, be_u32, le_u32};
+
+/// Quita los metadatos (EXIF con la posición GPS, XMP, IPTC, comentarios y
+/// textos) de una imagen JPEG, PNG o WebP sin tocar los píxeles. En los
+/// JPEG se conserva solo la orientación para que la foto no aparezca
+/// girada. Cualquier otro formato, o un fichero que no se puede recorrer,
+/// se devuelve tal cual.
+pub fn strip_metadata(data: Vec<u8>) -> Vec<u8> {
+    let stripped = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        strip_png(&data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(&data)
+    } else if data.len() > 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        strip_webp(&data)
+    } else {
+        None
+    };
+    stripped.unwrap_or(data)
+}
+
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = Vec::with_capacity(data.len());
+    stripped.extend_from_slice(data.get(..2)?);
+    let mut i = 2;
+    loop {
+        if *data.get(i)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(i + 1)?;
+        match marker {
+            // Relleno entre segmentos
+            0xFF => {
+                i += 1;
+                continue;
+            }
+            // A partir de SOS (o EOI) solo quedan los datos de la imagen
+            0xDA | 0xD9 => {
+                stripped.extend_from_slice(&data[i..]);
+                return Some(stripped);
+            }
+            0x01 | 0xD0..=0xD7 => {
+                stripped.extend_from_slice(&data[i..i + 2]);
+                i += 2;
+                continue;
+            }
+            _ => {}
+        }
+        // La longitud incluye sus dos bytes, así que nunca es menor que 2
+        let length = be_u16(data, i + 2)? as usize;
+        if length < 2 {
+            return None;
+        }
+        let end = i + 2 + length;
+        let segment = data.get(i..end)?;
+        match marker {
+            // APP1: EXIF o XMP
+            0xE1 => {
+                if let Some(orientation) = segment.get(4..).and_then(exif_orientation)
+                    && orientation != 1
+                {
+                    stripped.extend_from_slice(&orientation_segment(orientation));
+                }
+            }
+            // JFIF, perfil ICC y Adobe afectan a cómo se ven los colores
+            0xE0 | 0xE2 | 0xEE => stripped.extend_from_slice(segment),
+            // Resto de APPn (IPTC, Photoshop...) y comentarios
+            0xE3..=0xEF | 0xFE => {}
+            _ => stripped.extend_from_slice(segment),
+        }
+        i = end;
+    }
+}
+
+/// Valor de la etiqueta `Orientation` (0x0112) del IFD0 de un bloque EXIF
+/// (`Exif\0\0` seguido de la cabecera TIFF).
+pub(super) fn exif_orientation(exif: &[u8]) -> Option<u16> {
+    let tiff = exif.strip_prefix(b"Exif\0\0")?;
+    let little_endian = match tiff.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |at: usize| -> Option<u16> {
+        let bytes: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|entry| ifd + 2 + entry * 12)
+        .find(|&at| u16_at(at) == Some(0x0112))
+        .and_then(|at| u16_at(at + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// Segmento APP1 con un EXIF mínimo que solo lleva la orientación.
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut segment = vec![0xFF, 0xE1, 0x00, 0x22];
+    segment.extend_from_slice(b"Exif\0\0");
+    // Cabecera TIFF big-endian con el IFD0 justo detrás
+    segment.extend_from_slice(b"MM\0\x2A\0\0\0\x08");
+    segment.extend_from_slice(&1u16.to_be_bytes());
+    // Orientation, tipo SHORT, un valor
+    segment.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01]);
+    segment.extend_from_slice(&orientation.to_be_bytes());
+    segment.extend_from_slice(&[0x00, 0x00]);
+    // Sin más IFDs
+    segment.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+    segment
+}
+
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = data.get(..8)?.to_vec();
+    let mut i: usize = 8;
+    loop {
+        let end = (i + 12).checked_add(be_u32(data, i)? as usize)?;
+        let chunk = data.get(i..end)?;
+        let chunk_type = &chunk[4..8];
+        if !matches!(chunk_type, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            stripped.extend_from_slice(chunk);
+        }
+        if chunk_type == b"IEND" {
+            return Some(stripped);
+        }
+        i = end;
+    }
+}
+
+/// Solo el formato extendido (VP8X) puede llevar metadatos.
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    // Cabecera RIFF y el chunk VP8X completo (diez bytes)
+    if data.get(12..16)? != b"VP8X" || data.len() < 30 {
+        return None;
+    }
+    let mut stripped = data.get(..12)?.to_vec();
+    let mut i = 12;
+    while i + 8 <= data.len() {
+        let size = le_u32(data, i + 4)? as usize;
+        // Los chunks de tamaño impar llevan un byte de relleno
+        let end = (i + 8).saturating_add(size + size % 2).min(data.len());
+        let chunk = &data[i..end];
+        if !matches!(&chunk[..4], b"EXIF" | b"XMP ") {
+            stripped.extend_from_slice(chunk);
+        }
+        i = end;
+    }
+    // Quitar las marcas de EXIF y XMP de VP8X y corregir el tamaño RIFF
+    *stripped.get_mut(20)? &= !(0x08 | 0x04);
+    let riff_size = (stripped.len() - 8) as u32;
+    stripped[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    /// EXIF little-endian con la orientación y una etiqueta GPS.
+    fn exif(orientation: u16) -> Vec<u8> {
+        let mut exif = b"Exif\0\0II\x2A\0\x08\0\0\0".to_vec();
+        exif.extend_from_slice(&2u16.to_le_bytes());
+        exif.extend_from_slice(&[0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        exif.extend_from_slice(&orientation.to_le_bytes());
+        exif.extend_from_slice(&[0x00, 0x00]);
+        exif.extend_from_slice(&[0x25, 0x88, 0x04, 0x00, 0x01, 0x00, 0x00, 0x00, 0x26, 0x00, 0x00, 0x00]);
+        exif.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        exif
+    }
+
+    fn jpeg(orientation: u16) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(jpeg_segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0"));
+        data.extend(jpeg_segment(0xE1, &exif(orientation)));
+        data.extend(jpeg_segment(0xE2, b"ICC_PROFILE\0"));
+        data.extend(jpeg_segment(0xED, b"Photoshop 3.0\0"));
+        data.extend(jpeg_segment(0xFE, b"secret comment"));
+        data.extend(jpeg_segment(0xDB, &[0u8; 65]));
+        data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+        data
+    }
+
+    fn contains(data: &[u8], needle: &[u8]) -> bool {
+        data.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn jpeg_keeps_only_orientation_and_color_segments() {
+        let stripped = strip_jpeg(&jpeg(6)).unwrap();
+        assert!(contains(&stripped, b"JFIF"));
+        assert!(contains(&stripped, b"ICC_PROFILE"));
+        assert!(!contains(&stripped, b"Photoshop"));
+        assert!(!contains(&stripped, b"secret comment"));
+        assert!(!contains(&stripped, &[0x25, 0x88]));
+        assert!(contains(&stripped, &orientation_segment(6)));
+        assert!(stripped.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]));
+        assert_eq!(exif_orientation(&orientation_segment(6)[4..]), Some(6));
+    }
+
+    #[test]
+    fn jpeg_drops_exif_without_rotation() {
+        let stripped = strip_jpeg(&jpeg(1)).unwrap();
+        assert!(!contains(&stripped, b"Exif"));
+    }
+
+    #[test]
+    fn jpeg_rejects_malformed_segments() {
+        // Longitud de APP1 menor que 2
+        let data = [0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x00, 0xFF, 0xD9];
+        assert_eq!(strip_jpeg(&data), None);
+        assert_eq!(strip_metadata(data.to_vec()), data);
+        let data = [0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x01, 0xFF, 0xD9];
+        assert_eq!(strip_jpeg(&data), None);
+        // APP1 vacío: no hay EXIF que leer
+        let data = [0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x02, 0xFF, 0xD9];
+        assert_eq!(strip_jpeg(&data).unwrap(), [0xFF, 0xD8, 0xFF, 0xD9]);
+        // Basura donde debería haber un marcador
+        assert_eq!(strip_jpeg(&[0xFF, 0xD8, 0x00, 0x00]), None);
+        assert_eq!(strip_jpeg(&[0xFF]), None);
+    }
+
+    #[test]
+    fn jpeg_rejects_truncated_input() {
+        let data = jpeg(6);
+        for len in [2, 3, 5, 20, 40] {
+            assert_eq!(strip_jpeg(&data[..len]), None, "{}", len);
+        }
+    }
+
+    fn png_chunk(chunk_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = (payload.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(payload);
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        chunk
+    }
+
+    fn png() -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend(png_chunk(b"IHDR", &[0u8; 13]));
+        data.extend(png_chunk(b"tEXt", b"Author\0Ana"));
+        data.extend(png_chunk(b"eXIf", b"MM\0\x2A"));
+        data.extend(png_chunk(b"iCCP", b"icc"));
+        data.extend(png_chunk(b"IDAT", b"pixels"));
+        data.extend(png_chunk(b"tIME", &[0u8; 7]));
+        data.extend(png_chunk(b"IEND", b""));
+        data
+    }
+
+    #[test]
+    fn png_drops_text_exif_and_time_chunks() {
+        let stripped = strip_png(&png()).unwrap();
+        let mut expected = b"\x89PNG\r\n\x1a\n".to_vec();
+        expected.extend(png_chunk(b"IHDR", &[0u8; 13]));
+        expected.extend(png_chunk(b"iCCP", b"icc"));
+        expected.extend(png_chunk(b"IDAT", b"pixels"));
+        expected.extend(png_chunk(b"IEND", b""));
+        assert_eq!(stripped, expected);
+    }
+
+    #[test]
+    fn png_rejects_truncated_and_malformed_input() {
+        let data = png();
+        for len in [4, 8, 12, 30, data.len() - 1] {
+            assert_eq!(strip_png(&data[..len]), None, "{}", len);
+        }
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend_from_slice(&u32::MAX.to_be_bytes());
+        data.extend_from_slice(b"IDAT");
+        assert_eq!(strip_png(&data), None);
+        assert_eq!(strip_metadata(data.clone()), data);
+    }
+
+    fn webp_chunk(chunk_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = chunk_type.to_vec();
+        chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        data.extend_from_slice(b"WEBP");
+        data.extend(body);
+        data
+    }
+
+    #[test]
+    fn webp_drops_exif_and_xmp_and_fixes_header() {
+        let mut vp8x = [0u8; 10];
+        vp8x[0] = 0x08 | 0x04 | 0x20;
+        let data = webp(&[
+            webp_chunk(b"VP8X", &vp8x),
+            webp_chunk(b"ICCP", b"icc"),
+            webp_chunk(b"VP8 ", b"pixels"),
+            webp_chunk(b"EXIF", b"MM\0\x2A gps"),
+            webp_chunk(b"XMP ", b"<x:xmpmeta/>"),
+        ]);
+        let stripped = strip_webp(&data).unwrap();
+        vp8x[0] = 0x20;
+        let expected = webp(&[
+            webp_chunk(b"VP8X", &vp8x),
+            webp_chunk(b"ICCP", b"icc"),
+            webp_chunk(b"VP8 ", b"pixels"),
+        ]);
+        assert_eq!(stripped, expected);
+    }
+
+    #[test]
+    fn webp_leaves_simple_and_malformed_files_alone() {
+        let simple = webp(&[webp_chunk(b"VP8 ", b"pixels")]);
+        assert_eq!(strip_webp(&simple), None);
+        assert_eq!(strip_metadata(simple.clone()), simple);
+        // VP8X cortado antes de las marcas
+        let truncated = webp(&[b"VP8X\x0a\0\0\0\x0c".to_vec()]);
+        assert_eq!(strip_webp(&truncated), None);
+        // Un chunk que dice ser más largo que el fichero
+        let mut data = webp(&[webp_chunk(b"VP8X", &[0x08; 10])]);
+        data.extend_from_slice(b"EXIF\xff\xff\xff\xffgps");
+        let stripped = strip_webp(&data).unwrap();
+        assert!(!contains(&stripped, b"EXIF"));
+        assert_eq!(strip_webp(&data[..10]), None);
+    }
+}
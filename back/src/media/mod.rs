@@ -0,0 +1,220 @@
+mod audio;
+mod gc;
+mod metadata;
+mod og;
+mod responsive;
+mod resumable;
+mod sniff;
+mod validation;
+mod variants;
+
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+use sqlx::Error;
+use tracing::error;
+
+use crate::models::{AppState, Media, MediaVariant, Post};
+
+pub use audio::{probe_audio, probe_audio_file};
+pub use gc::{delete_orphans, grace_days, scan_orphans};
+pub use metadata::strip_metadata;
+pub use og::{og_card, og_card_url};
+pub use responsive::{load_images, register_image, responsive_images, unregister_image};
+pub use resumable::{
+    append_body, lock_upload, purge_uploads, read_head, read_upload, received_bytes, remove_upload,
+    staging_path, upload_checksum,
+};
+pub use sniff::FileType;
+pub use validation::{UploadError, UploadPolicy, UploadQuota};
+pub use variants::generate_variants;
+
+/// Completa la URL antes de devolver el medio.
+pub fn with_url(app_state: &AppState, mut media: Media) -> Media {
+    media.url = app_state.storage.url(&media.path);
+    for variant in media.variants.iter_mut() {
+        variant.url = app_state.storage.url(&variant.path);
+    }
+    media
+}
+
+/// Ruta de la biblioteca a partir de una URL pública, absoluta o relativa.
+pub fn path_from_url<'a>(app_state: &AppState, url: &'a str) -> Option<&'a str> {
+    let base_url = app_state.storage.base_url();
+    let path = match url.strip_prefix(base_url.as_str()) {
+        Some(path) => path,
+        None if base_url.path().len() > 1 => url.strip_prefix(base_url.path())?,
+        None => return None,
+    };
+    path.split(['?', '#']).next()
+}
+
+/// Audio de la biblioteca de cada post con `audio_url`, por id de post.
+pub async fn read_audio(app_state: &AppState, posts: &[Post]) -> HashMap<i32, Media> {
+    let paths: Vec<(i32, String)> = posts
+        .iter()
+        .filter_map(|post| {
+            let path = path_from_url(app_state, post.audio_url.as_deref()?)?;
+            Some((post.id, path.to_string()))
+        })
+        .collect();
+    if paths.is_empty() {
+        return HashMap::new();
+    }
+    let unique: Vec<String> = paths.iter().map(|(_, path)| path.clone()).collect();
+    let by_path: HashMap<String, Media> = match Media::read_by_paths(&app_state.pool, &unique).await {
+        Ok(items) => items
+            .into_iter()
+            .map(|item| (item.path.clone(), with_url(app_state, item)))
+            .collect(),
+        Err(e) => {
+            error!("Error reading audio media: {:?}", e);
+            return HashMap::new();
+        }
+    };
+    paths
+        .into_iter()
+        .filter_map(|(post_id, path)| Some((post_id, by_path.get(&path)?.clone())))
+        .collect()
+}
+
+/// Portada de la biblioteca de cada post que la tiene, con sus variantes,
+/// por id de post.
+pub async fn read_covers(app_state: &AppState, posts: &[Post]) -> HashMap<i32, Media> {
+    let ids: Vec<i32> = posts.iter().filter_map(|post| post.cover_media_id).collect();
+    if ids.is_empty() {
+        return HashMap::new();
+    }
+    let (items, variants) = match (
+        Media::read_by_ids(&app_state.pool, &ids).await,
+        MediaVariant::read_for_medias(&app_state.pool, &ids).await,
+    ) {
+        (Ok(items), Ok(variants)) => (items, variants),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("Error reading cover media: {:?}", e);
+            return HashMap::new();
+        }
+    };
+    let mut by_id: HashMap<i32, Media> = items.into_iter().map(|item| (item.id, item)).collect();
+    for variant in variants {
+        if let Some(item) = by_id.get_mut(&variant.media_id) {
+            item.variants.push(variant);
+        }
+    }
+    posts
+        .iter()
+        .filter_map(|post| {
+            let cover = by_id.get(&post.cover_media_id?)?;
+            Some((post.id, with_url(app_state, cover.clone())))
+        })
+        .collect()
+}
+
+/// Borra el registro (con sus variantes) y los ficheros del almacenamiento.
+pub async fn delete_media(app_state: &AppState, id: i32) -> Result<Media, Error> {
+    let variants = MediaVariant::read_for_media(&app_state.pool, id).await?;
+    let deleted = Media::delete(&app_state.pool, id).await?;
+    unregister_image(&deleted.path);
+    let paths = std::iter::once(&deleted.path).chain(variants.iter().map(|variant| &variant.path));
+    for path in paths {
+        if let Err(e) = app_state.storage.delete(path).await {
+            error!("Error removing file '{}': {:?}", path, e);
+        }
+    }
+    Ok(deleted)
+}
+
+/// SHA-256 del contenido en hexadecimal.
+pub fn checksum(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Ancho y alto de una imagen PNG, GIF, JPEG o WebP leyendo solo su
+/// cabecera. En los JPEG girados por EXIF son las medidas ya giradas, las
+/// que se ven en pantalla.
+pub fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be_u32(data, 16)?, be_u32(data, 20)?));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some((le_u16(data, 6)? as u32, le_u16(data, 8)? as u32));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return jpeg_dimensions(data);
+    }
+    if data.len() > 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return webp_dimensions(data);
+    }
+    None
+}
+
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut orientation = 1;
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            i += 1;
+            continue;
+        }
+        let marker = data[i + 1];
+        // Marcadores sin longitud
+        if marker == 0xFF || marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+            i += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        let length = be_u16(data, i + 2)? as usize;
+        if marker == 0xE1
+            && let Some(exif) = data.get(i + 4..i + 2 + length)
+        {
+            orientation = metadata::exif_orientation(exif).unwrap_or(orientation);
+        }
+        // SOF0-SOF15 salvo DHT (C4), JPG (C8) y DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = be_u16(data, i + 5)? as u32;
+            let width = be_u16(data, i + 7)? as u32;
+            // Orientaciones 5 a 8: girada 90 grados
+            if orientation >= 5 {
+                return Some((height, width));
+            }
+            return Some((width, height));
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+fn webp_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    match data.get(12..16)? {
+        b"VP8 " => Some((
+            (le_u16(data, 26)? & 0x3FFF) as u32,
+            (le_u16(data, 28)? & 0x3FFF) as u32,
+        )),
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(data.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8X" => Some((le_u24(data, 24)? + 1, le_u24(data, 27)? + 1)),
+        _ => None,
+    }
+}
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u24(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 3)?;
+    Some(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16)
+}
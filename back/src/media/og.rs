@@ -0,0 +1,109 @@
+use std::{
+    env::{self, var},
+    io::ErrorKind,
+};
+
+use minijinja::context;
+use tokio::{fs, process::Command};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use super::checksum;
+use crate::constants::{
+    DEFAULT_IMAGE_CONVERTER, DEFAULT_SITE_TITLE, OG_CARD_HEIGHT, OG_CARD_LINE_CHARS, OG_CARD_MAX_LINES,
+    OG_CARD_TEMPLATE, OG_CARD_WIDTH,
+};
+use crate::models::{AppState, Post, Setting, Tag};
+use crate::storage::StorageError;
+
+/// URL estable de la tarjeta de un post. No cambia aunque cambie el
+/// título o el slug; cambia la imagen que devuelve.
+pub fn og_card_url(base_url: &str, post_id: i32) -> String {
+    format!("{}/og/{}.png", base_url.trim_end_matches('/'), post_id)
+}
+
+/// Tarjeta Open Graph de un post: título, nombre del sitio y primera
+/// etiqueta sobre la plantilla `og_card.svg`, convertida a PNG con
+/// `IMAGE_CONVERTER`. Se guarda en el almacenamiento con un hash de lo que
+/// lleva, así solo se vuelve a generar si cambia el texto o la plantilla.
+/// Devuelve el hash (para el `ETag`) y el PNG.
+pub async fn og_card(app_state: &AppState, post: &Post) -> Result<(String, Vec<u8>), StorageError> {
+    let site_name = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+    let tag = Tag::read_first_for_post(&app_state.pool, post.id)
+        .await?
+        .map(|tag| tag.tag);
+    let template = app_state.templates.get_template(OG_CARD_TEMPLATE)?;
+    let hash = checksum(
+        format!(
+            "{}\n{}\n{}\n{}",
+            post.title,
+            site_name,
+            tag.as_deref().unwrap_or_default(),
+            template.source()
+        )
+        .as_bytes(),
+    );
+    let path = format!("og/{}-{}.png", post.id, &hash[..16]);
+    if app_state.storage.exists(&path).await? {
+        return Ok((hash, app_state.storage.get(&path).await?));
+    }
+
+    debug!("Rendering Open Graph card: {}", path);
+    let svg = template.render(context! {
+        width => OG_CARD_WIDTH,
+        height => OG_CARD_HEIGHT,
+        title_lines => wrap_title(&post.title),
+        site_name => site_name,
+        tag => tag,
+    })?;
+    let png = render_png(&svg).await?;
+    app_state.storage.put(&path, &png, "image/png").await?;
+    Ok((hash, png))
+}
+
+/// Reparte el título en líneas de `OG_CARD_LINE_CHARS` caracteres como
+/// mucho, sin cortar palabras. Lo que no cabe se corta con puntos
+/// suspensivos.
+fn wrap_title(title: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in title.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= OG_CARD_LINE_CHARS => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    if lines.len() > OG_CARD_MAX_LINES {
+        lines.truncate(OG_CARD_MAX_LINES);
+        if let Some(last) = lines.last_mut() {
+            last.push('…');
+        }
+    }
+    lines
+}
+
+async fn render_png(svg: &str) -> Result<Vec<u8>, StorageError> {
+    let scratch_dir = env::temp_dir().join(format!("og-{}", Uuid::new_v4()));
+    fs::create_dir_all(&scratch_dir).await?;
+    let source = scratch_dir.join("card.svg");
+    let target = scratch_dir.join("card.png");
+    fs::write(&source, svg).await?;
+    let converter = var("IMAGE_CONVERTER").unwrap_or(DEFAULT_IMAGE_CONVERTER.to_string());
+    let output = Command::new(&converter).arg(&source).arg(&target).output().await;
+    let result = match output {
+        Ok(output) if output.status.success() => fs::read(&target).await.map_err(StorageError::from),
+        Ok(output) => Err(format!(
+            "Error converting Open Graph card: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(format!("Image converter '{}' not found", converter).into()),
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = fs::remove_dir_all(&scratch_dir).await {
+        error!("Error removing directory '{:?}': {:?}", scratch_dir, e);
+    }
+    result
+}
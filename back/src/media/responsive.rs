@@ -0,0 +1,184 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use sqlx::{Error, postgres::PgPool};
+use url::Url;
+
+use crate::constants::IMAGE_SIZES;
+use crate::models::{Media, MediaVariant};
+
+static IMG_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<img src="([^"]+)"([^>]*?)\s*/?>"#).unwrap());
+
+/// Imágenes de la biblioteca en memoria para que `markdown_to_html`, que
+/// no tiene acceso a la base de datos, pueda añadirles dimensiones y
+/// variantes.
+static IMAGES: Lazy<RwLock<Images>> = Lazy::new(Default::default);
+
+#[derive(Debug, Default)]
+struct Images {
+    /// URL pública del almacenamiento
+    base: String,
+    /// Solo la ruta de `base` (`/uploads/`), para las URLs relativas o con
+    /// otro dominio
+    prefix: String,
+    by_path: HashMap<String, Image>,
+}
+
+impl Images {
+    /// Imagen de una URL y posición donde empieza su ruta.
+    fn lookup(&self, src: &str) -> Option<(usize, &Image)> {
+        let start = if src.starts_with(&self.base) {
+            self.base.len()
+        } else if self.prefix.len() > 1 {
+            src.find(&self.prefix)? + self.prefix.len()
+        } else {
+            return None;
+        };
+        let path = src[start..].split(['?', '#']).next()?;
+        Some((start, self.by_path.get(path)?))
+    }
+}
+
+#[derive(Debug)]
+struct Image {
+    width: i32,
+    height: i32,
+    mime_type: String,
+    variants: Vec<Variant>,
+}
+
+#[derive(Debug)]
+struct Variant {
+    path: String,
+    mime_type: String,
+    width: i32,
+}
+
+impl Image {
+    fn new(media: &Media, variants: &[MediaVariant]) -> Option<Self> {
+        let mut variants: Vec<Variant> = variants
+            .iter()
+            .map(|variant| Variant {
+                path: variant.path.clone(),
+                mime_type: variant.mime_type.clone(),
+                width: variant.width,
+            })
+            .collect();
+        variants.sort_by(|a, b| a.mime_type.cmp(&b.mime_type).then(a.width.cmp(&b.width)));
+        Some(Image {
+            width: media.width?,
+            height: media.height?,
+            mime_type: media.mime_type.clone(),
+            variants,
+        })
+    }
+
+    /// `srcset` de un formato; el del original incluye la propia imagen.
+    fn srcset(&self, base: &str, src: &str, mime_type: &str) -> Option<String> {
+        let mut candidates: Vec<String> = self
+            .variants
+            .iter()
+            .filter(|variant| variant.mime_type == mime_type)
+            .map(|variant| format!("{}{} {}w", base, variant.path, variant.width))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        if mime_type == self.mime_type {
+            candidates.push(format!("{} {}w", src, self.width));
+        }
+        Some(candidates.join(", "))
+    }
+
+    fn render(&self, base: &str, src: &str, attributes: &str) -> String {
+        let mut img = format!(
+            r#"<img src="{}"{} width="{}" height="{}""#,
+            src, attributes, self.width, self.height
+        );
+        if let Some(srcset) = self.srcset(base, src, &self.mime_type) {
+            img.push_str(&format!(r#" srcset="{}" sizes="{}""#, srcset, IMAGE_SIZES));
+        }
+        img.push_str(" />");
+        let mut mime_types: Vec<&str> = self
+            .variants
+            .iter()
+            .map(|variant| variant.mime_type.as_str())
+            .filter(|mime_type| *mime_type != self.mime_type)
+            .collect();
+        mime_types.dedup();
+        if mime_types.is_empty() {
+            return img;
+        }
+        // Los formatos alternativos van antes, para que el navegador escoja
+        // el primero que entienda
+        let mut picture = "<picture>".to_string();
+        for mime_type in mime_types {
+            if let Some(srcset) = self.srcset(base, src, mime_type) {
+                picture.push_str(&format!(
+                    r#"<source type="{}" srcset="{}" sizes="{}" />"#,
+                    mime_type, srcset, IMAGE_SIZES
+                ));
+            }
+        }
+        picture.push_str(&img);
+        picture.push_str("</picture>");
+        picture
+    }
+}
+
+/// Carga todas las imágenes con sus variantes al arrancar.
+pub async fn load_images(pool: &PgPool, base_url: &Url) -> Result<usize, Error> {
+    let images = Media::read_images(pool).await?;
+    let mut variants: HashMap<i32, Vec<MediaVariant>> = HashMap::new();
+    for variant in MediaVariant::read_all(pool).await? {
+        variants.entry(variant.media_id).or_default().push(variant);
+    }
+    let by_path: HashMap<String, Image> = images
+        .iter()
+        .filter_map(|media| {
+            let image = Image::new(media, variants.get(&media.id).map(Vec::as_slice).unwrap_or_default())?;
+            Some((media.path.clone(), image))
+        })
+        .collect();
+    let total = by_path.len();
+    let mut registry = IMAGES.write().unwrap();
+    registry.base = base_url.to_string();
+    registry.prefix = base_url.path().to_string();
+    registry.by_path = by_path;
+    Ok(total)
+}
+
+/// Añade o actualiza una imagen tras subirla o generar sus variantes.
+pub fn register_image(media: &Media, variants: &[MediaVariant]) {
+    if let Some(image) = Image::new(media, variants) {
+        IMAGES.write().unwrap().by_path.insert(media.path.clone(), image);
+    }
+}
+
+pub fn unregister_image(path: &str) {
+    IMAGES.write().unwrap().by_path.remove(path);
+}
+
+/// Añade `width`/`height` a las imágenes de la biblioteca y, si tienen
+/// variantes, `srcset`/`sizes` y un `<picture>` con los formatos
+/// alternativos.
+pub fn responsive_images(html: &str) -> String {
+    let registry = IMAGES.read().unwrap();
+    if registry.by_path.is_empty() || !html.contains("<img ") {
+        return html.to_string();
+    }
+    IMG_TAG_REGEX
+        .replace_all(html, |caps: &Captures| {
+            let src = &caps[1];
+            // HTML escrito a mano que ya trae sus propias medidas
+            if caps[2].contains("width=") || caps[2].contains("srcset=") {
+                return caps[0].to_string();
+            }
+            match registry.lookup(src) {
+                Some((start, image)) => image.render(&src[..start], src, &caps[2]),
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
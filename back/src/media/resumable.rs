@@ -0,0 +1,162 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env::{self, var},
+    io::{self, ErrorKind},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use axum::body::Body;
+use futures_util::StreamExt;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgPool;
+use tokio::{
+    fs::{self, OpenOptions},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+use tracing::{debug, error};
+
+use crate::constants::UPLOAD_SESSION_EXPIRATION_HOURS;
+use crate::models::UploadSession;
+
+/// Subidas que están recibiendo un trozo ahora mismo. Dos `PATCH` a la vez
+/// sobre la misma subida escribirían en el mismo fichero.
+static ACTIVE: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Reserva de una subida; se libera al soltarla.
+pub struct UploadLock(String);
+
+impl Drop for UploadLock {
+    fn drop(&mut self) {
+        ACTIVE.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// `None` si otra petición está escribiendo en la misma subida.
+pub fn lock_upload(id: &str) -> Option<UploadLock> {
+    ACTIVE
+        .lock()
+        .unwrap()
+        .insert(id.to_string())
+        .then(|| UploadLock(id.to_string()))
+}
+
+/// Hash de lo recibido de cada subida y hasta qué byte llega, para no
+/// tener que leer el fichero entero al terminar. Tras un reinicio se
+/// recalcula a partir de lo que hay en disco.
+static HASHERS: Lazy<Mutex<HashMap<String, (u64, Sha256)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Directorio donde se van juntando los trozos. Para poder reanudar tras un
+/// reinicio `UPLOAD_STAGING_DIR` tiene que estar en un disco persistente.
+pub fn staging_path(id: &str) -> PathBuf {
+    var("UPLOAD_STAGING_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir().join("resumable-uploads"))
+        .join(id)
+}
+
+/// Bytes recibidos hasta ahora.
+pub async fn received_bytes(id: &str) -> io::Result<u64> {
+    match fs::metadata(staging_path(id)).await {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Escribe el cuerpo de la petición a continuación de lo recibido, trozo a
+/// trozo, sin cargarlo en memoria. Si la conexión se corta se conserva lo
+/// que llegó y el cliente sigue desde ahí. Pasarse de `length` anula el
+/// trozo entero.
+pub async fn append_body(id: &str, body: Body, offset: u64, length: u64) -> io::Result<u64> {
+    let path = staging_path(id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path).await?;
+    let mut hasher = take_hasher(id, offset).await?;
+    let mut stream = body.into_data_stream();
+    let mut written = offset;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                debug!("Upload {} interrupted at {} bytes: {:?}", id, written, e);
+                break;
+            }
+        };
+        if written + chunk.len() as u64 > length {
+            file.set_len(offset).await?;
+            return Err(io::Error::new(ErrorKind::InvalidInput, "Chunk goes past Upload-Length"));
+        }
+        file.write_all(&chunk).await?;
+        hasher.update(&chunk);
+        written += chunk.len() as u64;
+    }
+    file.flush().await?;
+    HASHERS.lock().unwrap().insert(id.to_string(), (written, hasher));
+    Ok(written)
+}
+
+/// Hash de los `offset` bytes recibidos: el guardado si llega justo hasta
+/// ahí o, si no, leyendo el fichero por trozos.
+async fn take_hasher(id: &str, offset: u64) -> io::Result<Sha256> {
+    let saved = HASHERS.lock().unwrap().remove(id);
+    if let Some((hashed, hasher)) = saved
+        && hashed == offset
+    {
+        return Ok(hasher);
+    }
+    let mut hasher = Sha256::new();
+    if offset == 0 {
+        return Ok(hasher);
+    }
+    let mut file = fs::File::open(staging_path(id)).await?.take(offset);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher)
+}
+
+/// SHA-256 del fichero completo (ver [`crate::media::checksum`]).
+pub async fn upload_checksum(id: &str, length: u64) -> io::Result<String> {
+    let hasher = take_hasher(id, length).await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Primeros bytes de lo recibido, para comprobar el tipo.
+pub async fn read_head(id: &str, size: u64) -> io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    fs::File::open(staging_path(id)).await?.take(size).read_to_end(&mut head).await?;
+    Ok(head)
+}
+
+/// Fichero completo, una vez recibidos todos los trozos. Solo para los que
+/// hay que procesar en memoria, como las imágenes.
+pub async fn read_upload(id: &str) -> io::Result<Vec<u8>> {
+    fs::read(staging_path(id)).await
+}
+
+pub async fn remove_upload(id: &str) {
+    HASHERS.lock().unwrap().remove(id);
+    match fs::remove_file(staging_path(id)).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => error!("Error removing upload {}: {:?}", id, e),
+    }
+}
+
+/// Borra las subidas sin actividad, con lo que hubieran recibido.
+pub async fn purge_uploads(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let expired = UploadSession::delete_expired(pool, UPLOAD_SESSION_EXPIRATION_HOURS).await?;
+    for session in expired.iter() {
+        remove_upload(&session.id).await;
+    }
+    Ok(expired.len())
+}
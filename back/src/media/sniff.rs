@@ -0,0 +1,196 @@
+/// Tipo real de un fichero deducido de sus primeros bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileType {
+    pub mime_type: &'static str,
+    pub extension: &'static str,
+}
+
+impl FileType {
+    const fn new(mime_type: &'static str, extension: &'static str) -> Self {
+        FileType { mime_type, extension }
+    }
+
+    /// Formatos de texto que el navegador puede interpretar y que pueden
+    /// llevar scripts.
+    pub fn is_markup(&self) -> bool {
+        matches!(self.mime_type, "image/svg+xml" | "text/html" | "application/xml")
+    }
+
+    /// Las imágenes se procesan en memoria, así que no pasan del tamaño
+    /// máximo normal aunque lleguen por una subida reanudable.
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+
+    /// Formatos de imagen a los que se pueden convertir las variantes.
+    pub fn image_from_extension(extension: &str) -> Option<FileType> {
+        match extension {
+            "png" => Some(PNG),
+            "jpg" | "jpeg" => Some(JPEG),
+            "webp" => Some(WEBP),
+            "avif" => Some(AVIF),
+            _ => None,
+        }
+    }
+}
+
+const PNG: FileType = FileType::new("image/png", "png");
+const JPEG: FileType = FileType::new("image/jpeg", "jpg");
+const GIF: FileType = FileType::new("image/gif", "gif");
+const WEBP: FileType = FileType::new("image/webp", "webp");
+const AVIF: FileType = FileType::new("image/avif", "avif");
+const HEIC: FileType = FileType::new("image/heic", "heic");
+const MP3: FileType = FileType::new("audio/mpeg", "mp3");
+const M4A: FileType = FileType::new("audio/mp4", "m4a");
+const MP4: FileType = FileType::new("video/mp4", "mp4");
+const OGG: FileType = FileType::new("audio/ogg", "ogg");
+const WAV: FileType = FileType::new("audio/wav", "wav");
+const FLAC: FileType = FileType::new("audio/flac", "flac");
+const PDF: FileType = FileType::new("application/pdf", "pdf");
+const SVG: FileType = FileType::new("image/svg+xml", "svg");
+const HTML: FileType = FileType::new("text/html", "html");
+const XML: FileType = FileType::new("application/xml", "xml");
+
+/// Detecta el tipo por los bytes mágicos, sin fiarse del nombre ni del
+/// `Content-Type` que envía el cliente.
+pub fn sniff(data: &[u8]) -> Option<FileType> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(PNG);
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(JPEG);
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some(GIF);
+    }
+    if data.len() >= 12 && data.starts_with(b"RIFF") {
+        match &data[8..12] {
+            b"WEBP" => return Some(WEBP),
+            b"WAVE" => return Some(WAV),
+            _ => {}
+        }
+    }
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return Some(match &data[8..12] {
+            b"avif" | b"avis" => AVIF,
+            b"heic" | b"heix" | b"mif1" => HEIC,
+            b"M4A " | b"M4B " => M4A,
+            _ => MP4,
+        });
+    }
+    if data.starts_with(b"ID3") || is_mpeg_frame(data) {
+        return Some(MP3);
+    }
+    if data.starts_with(b"OggS") {
+        return Some(OGG);
+    }
+    if data.starts_with(b"fLaC") {
+        return Some(FLAC);
+    }
+    if data.starts_with(b"%PDF-") {
+        return Some(PDF);
+    }
+    sniff_markup(data)
+}
+
+/// Cabecera de trama MPEG de audio (capa III): sincronismo de 11 bits.
+fn is_mpeg_frame(data: &[u8]) -> bool {
+    data.len() >= 4 && data[0] == 0xFF && (data[1] & 0xE0) == 0xE0 && (data[1] & 0x06) == 0x02
+}
+
+fn sniff_markup(data: &[u8]) -> Option<FileType> {
+    let head = &data[..data.len().min(1024)];
+    let text = String::from_utf8_lossy(head).to_lowercase();
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if !text.starts_with('<') {
+        return None;
+    }
+    if text.contains("<svg") {
+        Some(SVG)
+    } else if text.contains("<html") || text.contains("<!doctype html") || text.contains("<script") {
+        Some(HTML)
+    } else if text.starts_with("<?xml") {
+        Some(XML)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mime(data: &[u8]) -> Option<&'static str> {
+        sniff(data).map(|file_type| file_type.mime_type)
+    }
+
+    #[test]
+    fn detects_each_signature() {
+        let cases: [(&[u8], &str, &str); 17] = [
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "image/png", "png"),
+            (b"\xFF\xD8\xFF\xE0\0\x10JFIF", "image/jpeg", "jpg"),
+            (b"GIF87a\x01\0", "image/gif", "gif"),
+            (b"GIF89a\x01\0", "image/gif", "gif"),
+            (b"RIFF\0\0\0\0WEBPVP8 ", "image/webp", "webp"),
+            (b"RIFF\0\0\0\0WAVEfmt ", "audio/wav", "wav"),
+            (b"\0\0\0\x1cftypavif", "image/avif", "avif"),
+            (b"\0\0\0\x1cftypheic", "image/heic", "heic"),
+            (b"\0\0\0\x1cftypM4A ", "audio/mp4", "m4a"),
+            (b"\0\0\0\x1cftypisom", "video/mp4", "mp4"),
+            (b"ID3\x04\0\0\0\0\0\0", "audio/mpeg", "mp3"),
+            (b"\xFF\xFB\x90\x00", "audio/mpeg", "mp3"),
+            (b"OggS\0\x02", "audio/ogg", "ogg"),
+            (b"fLaC\0\0\0\x22", "audio/flac", "flac"),
+            (b"%PDF-1.7\n", "application/pdf", "pdf"),
+            (b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>", "image/svg+xml", "svg"),
+            (b"<?xml version=\"1.0\"?><feed/>", "application/xml", "xml"),
+        ];
+        for (data, mime_type, extension) in cases {
+            let file_type = sniff(data).unwrap_or_else(|| panic!("{} not detected", mime_type));
+            assert_eq!((file_type.mime_type, file_type.extension), (mime_type, extension));
+        }
+        assert_eq!(mime(b"<!DOCTYPE html><title>x</title>"), Some("text/html"));
+    }
+
+    #[test]
+    fn truncated_or_unknown_headers_are_not_detected() {
+        for data in [
+            &b""[..],
+            b"\x89PNG\r\n",
+            b"\xFF\xD8",
+            b"GIF8",
+            b"RIFF\0\0\0\0WEB",
+            b"RIFF\0\0\0\0AVI LIST",
+            b"\0\0\0\x1cftyp",
+            b"\xFF\xF1\x50\x80",
+            b"%PDF",
+            b"Hola, mundo",
+            b"<p>sin html</p>",
+        ] {
+            assert_eq!(mime(data), None, "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn finds_markup_after_whitespace_or_bom() {
+        assert_eq!(mime(b"\n\t  <svg onload=\"alert(1)\">"), Some("image/svg+xml"));
+        assert_eq!(mime(b"\xEF\xBB\xBF<svg>"), Some("image/svg+xml"));
+        assert_eq!(mime(b"\xEF\xBB\xBF  \r\n<HTML><body>"), Some("text/html"));
+        assert_eq!(mime(b"  <?xml version=\"1.0\"?>\n<svg>"), Some("image/svg+xml"));
+        assert_eq!(mime(b"\n<div><script>alert(1)</script>"), Some("text/html"));
+        assert!(sniff(b"\xEF\xBB\xBF<svg>").unwrap().is_markup());
+        assert!(sniff(b"<html>").unwrap().is_markup());
+        assert!(!sniff(b"%PDF-1.4").unwrap().is_markup());
+    }
+
+    #[test]
+    fn content_wins_over_the_file_name() {
+        // Lo que cuenta son los bytes: un «.png» que es un JPEG se guarda
+        // como JPEG y un «.jpg» con HTML dentro se detecta como HTML
+        let jpeg = sniff(b"\xFF\xD8\xFF\xDBphoto.png").unwrap();
+        assert_eq!((jpeg.mime_type, jpeg.extension), ("image/jpeg", "jpg"));
+        assert_eq!(mime(b"<html><script>alert(1)</script>.jpg"), Some("text/html"));
+        // La firma del principio manda aunque luego haya marcado
+        assert_eq!(mime(b"GIF89a<svg onload=alert(1)>"), Some("image/gif"));
+    }
+}
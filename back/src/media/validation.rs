@@ -0,0 +1,343 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::postgres::PgPool;
+
+use super::sniff::{FileType, sniff};
+use crate::constants::{
+    DEFAULT_UPLOAD_ALLOWED_TYPES, DEFAULT_UPLOAD_MAX_SIZE_MB, DEFAULT_UPLOAD_QUOTAS_MB,
+    DEFAULT_UPLOAD_RESUMABLE_MAX_SIZE_MB,
+};
+use crate::models::{ApiResponse, Media, Setting, TokenClaims};
+
+/// Motivos por los que se rechaza una subida. Se devuelven con un `code`
+/// estable para que el cliente pueda mostrar su propio mensaje.
+#[derive(Debug)]
+pub enum UploadError {
+    MissingFile,
+    Empty,
+    TooLarge { max_size: u64 },
+    UnknownType,
+    UnsafeType { detected: &'static str },
+    NotAllowed { detected: &'static str, allowed: Vec<String> },
+    QuotaExceeded { quota: u64, used: u64 },
+    Storage(String),
+}
+
+impl UploadError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UploadError::MissingFile | UploadError::Empty => StatusCode::BAD_REQUEST,
+            UploadError::TooLarge { .. } | UploadError::QuotaExceeded { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::UnknownType | UploadError::UnsafeType { .. } | UploadError::NotAllowed { .. } => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            UploadError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            UploadError::MissingFile => "missing_file",
+            UploadError::Empty => "empty_file",
+            UploadError::TooLarge { .. } => "file_too_large",
+            UploadError::UnknownType => "unknown_type",
+            UploadError::UnsafeType { .. } => "unsafe_type",
+            UploadError::NotAllowed { .. } => "type_not_allowed",
+            UploadError::QuotaExceeded { .. } => "quota_exceeded",
+            UploadError::Storage(_) => "storage_error",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            UploadError::MissingFile => "Did not find 'file' field in the form".to_string(),
+            UploadError::Empty => "File is empty".to_string(),
+            UploadError::TooLarge { max_size } => format!("File is larger than {} bytes", max_size),
+            UploadError::UnknownType => "Can't determine the file type from its content".to_string(),
+            UploadError::UnsafeType { detected } => format!("Files of type {} are not accepted", detected),
+            UploadError::NotAllowed { detected, .. } => format!("Type {} is not allowed", detected),
+            UploadError::QuotaExceeded { quota, used } => {
+                format!("Upload quota of {} bytes exceeded, {} bytes already used", quota, used)
+            }
+            UploadError::Storage(msg) => msg.clone(),
+        }
+    }
+}
+
+impl From<UploadError> for ApiResponse {
+    fn from(error: UploadError) -> Self {
+        let mut data = json!({ "code": error.code() });
+        match &error {
+            UploadError::TooLarge { max_size } => data["max_size"] = json!(max_size),
+            UploadError::UnsafeType { detected } => data["detected"] = json!(detected),
+            UploadError::NotAllowed { detected, allowed } => {
+                data["detected"] = json!(detected);
+                data["allowed"] = json!(allowed);
+            }
+            UploadError::QuotaExceeded { quota, used } => {
+                data["quota"] = json!(quota);
+                data["used"] = json!(used);
+            }
+            _ => {}
+        }
+        ApiResponse::new(error.status(), &error.message(), Some(data))
+    }
+}
+
+/// Límites de subida configurables en `settings`: `uploads_max_size_mb`,
+/// `uploads_resumable_max_size_mb` (subidas reanudables que no son
+/// imágenes) y `uploads_allowed_types` (tipos MIME separados por comas;
+/// admite comodines como `image/*`).
+#[derive(Debug, Clone)]
+pub struct UploadPolicy {
+    pub max_size: u64,
+    pub max_resumable_size: u64,
+    pub allowed_types: Vec<String>,
+}
+
+impl UploadPolicy {
+    pub async fn load(pool: &PgPool) -> Self {
+        let max_size_mb = Setting::get_value(pool, "uploads_max_size_mb", &DEFAULT_UPLOAD_MAX_SIZE_MB.to_string())
+            .await
+            .trim()
+            .parse()
+            .unwrap_or(DEFAULT_UPLOAD_MAX_SIZE_MB);
+        let max_resumable_size_mb = Setting::get_value(
+            pool,
+            "uploads_resumable_max_size_mb",
+            &DEFAULT_UPLOAD_RESUMABLE_MAX_SIZE_MB.to_string(),
+        )
+        .await
+        .trim()
+        .parse()
+        .unwrap_or(DEFAULT_UPLOAD_RESUMABLE_MAX_SIZE_MB);
+        let allowed_types = Setting::get_value(pool, "uploads_allowed_types", DEFAULT_UPLOAD_ALLOWED_TYPES)
+            .await
+            .split(',')
+            .map(|mime| mime.trim().to_lowercase())
+            .filter(|mime| !mime.is_empty())
+            .collect();
+        UploadPolicy {
+            max_size: mb_to_bytes(max_size_mb),
+            max_resumable_size: mb_to_bytes(max_resumable_size_mb.max(max_size_mb)),
+            allowed_types,
+        }
+    }
+
+    fn allows(&self, mime_type: &str) -> bool {
+        self.allowed_types.iter().any(|allowed| match allowed.strip_suffix("/*") {
+            Some(prefix) => mime_type.split('/').next() == Some(prefix),
+            None => allowed == mime_type,
+        })
+    }
+
+    /// Comprueba el contenido ya leído y devuelve su tipo real. SVG y HTML
+    /// se rechazan siempre, estén o no en la lista.
+    pub fn check(&self, data: &[u8]) -> Result<FileType, UploadError> {
+        self.check_size(data.len() as u64)?;
+        self.check_type(data)
+    }
+
+    /// Tamaño anunciado o recibido, antes de tener el contenido.
+    pub fn check_size(&self, size: u64) -> Result<(), UploadError> {
+        if size == 0 {
+            return Err(UploadError::Empty);
+        }
+        if size > self.max_size {
+            return Err(UploadError::TooLarge { max_size: self.max_size });
+        }
+        Ok(())
+    }
+
+    /// Tamaño anunciado de una subida reanudable, cuando aún no se sabe el
+    /// tipo.
+    pub fn check_resumable_size(&self, size: u64) -> Result<(), UploadError> {
+        if size == 0 {
+            return Err(UploadError::Empty);
+        }
+        if size > self.max_resumable_size {
+            return Err(UploadError::TooLarge {
+                max_size: self.max_resumable_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Tipo real a partir de los primeros bytes; basta con el primer KB.
+    pub fn check_type(&self, head: &[u8]) -> Result<FileType, UploadError> {
+        let file_type = sniff(head).ok_or(UploadError::UnknownType)?;
+        if file_type.is_markup() {
+            return Err(UploadError::UnsafeType {
+                detected: file_type.mime_type,
+            });
+        }
+        if !self.allows(file_type.mime_type) {
+            return Err(UploadError::NotAllowed {
+                detected: file_type.mime_type,
+                allowed: self.allowed_types.clone(),
+            });
+        }
+        Ok(file_type)
+    }
+}
+
+/// Espacio que puede ocupar un usuario con lo que sube, según su rol:
+/// `uploads_quota_mb` en `settings` con pares `rol:MB` separados por comas,
+/// `*` para los roles sin entrada propia y 0 para no poner límite.
+#[derive(Debug, Clone)]
+pub struct UploadQuota {
+    pub quota: Option<u64>,
+    pub used: u64,
+}
+
+impl UploadQuota {
+    pub async fn load(pool: &PgPool, claims: &TokenClaims) -> Result<Self, UploadError> {
+        let quotas = Setting::get_value(pool, "uploads_quota_mb", DEFAULT_UPLOAD_QUOTAS_MB).await;
+        let quota = role_quota(&quotas, &claims.role);
+        let used = match quota {
+            Some(_) => Media::used_space(pool, claims.user_id)
+                .await
+                .map_err(|e| UploadError::Storage(format!("Error reading used space: {:?}", e)))?,
+            None => 0,
+        };
+        Ok(UploadQuota {
+            quota,
+            used: used as u64,
+        })
+    }
+
+    /// Comprueba si caben `size` bytes más.
+    pub fn check(&self, size: u64) -> Result<(), UploadError> {
+        match self.quota {
+            Some(quota) if self.used.saturating_add(size) > quota => Err(UploadError::QuotaExceeded { quota, used: self.used }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Bytes de la cuota del rol; `None` si no tiene límite.
+fn role_quota(quotas: &str, role: &str) -> Option<u64> {
+    role_quota_mb(quotas, role).filter(|mb| *mb > 0).map(mb_to_bytes)
+}
+
+/// Los MB vienen de los ajustes: un valor enorme se queda en el máximo en
+/// vez de desbordar y acabar siendo un límite pequeño.
+fn mb_to_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(1024 * 1024)
+}
+
+/// MB del rol, o los de `*` si no tiene entrada. `None` si ninguna vale.
+fn role_quota_mb(quotas: &str, role: &str) -> Option<u64> {
+    let pairs: Vec<(&str, u64)> = quotas
+        .split(',')
+        .filter_map(|pair| pair.split_once(':'))
+        .filter_map(|(name, mb)| Some((name.trim(), mb.trim().parse().ok()?)))
+        .collect();
+    [role, "*"]
+        .iter()
+        .find_map(|key| pairs.iter().find(|(name, _)| name == key).map(|(_, mb)| *mb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const MP3: &[u8] = b"ID3\x04\0\0\0\0\0\0";
+    const OGG: &[u8] = b"OggS\0\x02";
+
+    fn policy(allowed_types: &[&str]) -> UploadPolicy {
+        UploadPolicy {
+            max_size: 100,
+            max_resumable_size: 1000,
+            allowed_types: allowed_types.iter().map(|mime| mime.to_string()).collect(),
+        }
+    }
+
+    fn detected(result: Result<FileType, UploadError>) -> Result<&'static str, &'static str> {
+        result.map(|file_type| file_type.mime_type).map_err(|e| e.code())
+    }
+
+    #[test]
+    fn wildcards_match_the_whole_family() {
+        let images_and_mp3 = policy(&["image/*", "audio/mpeg"]);
+        assert_eq!(detected(images_and_mp3.check(PNG)), Ok("image/png"));
+        assert_eq!(detected(images_and_mp3.check(MP3)), Ok("audio/mpeg"));
+        // `audio/mpeg` es exacto: no vale para otros audios
+        assert_eq!(detected(images_and_mp3.check(OGG)), Err("type_not_allowed"));
+        assert_eq!(detected(images_and_mp3.check(b"%PDF-1.4")), Err("type_not_allowed"));
+        // El comodín compara la familia entera, no un prefijo cualquiera
+        assert_eq!(detected(policy(&["audi/*"]).check(MP3)), Err("type_not_allowed"));
+        assert_eq!(detected(policy(&["audio/mp"]).check(MP3)), Err("type_not_allowed"));
+        assert_eq!(detected(policy(&[]).check(PNG)), Err("type_not_allowed"));
+    }
+
+    #[test]
+    fn markup_is_rejected_even_when_allowed() {
+        let policy = policy(&["image/*", "image/svg+xml", "text/html", "application/xml"]);
+        for data in [&b"<svg onload=\"alert(1)\">"[..], b"\xEF\xBB\xBF <svg>", b"<!doctype html>", b"<?xml version=\"1.0\"?><a/>"] {
+            assert_eq!(detected(policy.check(data)), Err("unsafe_type"), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn checks_size_before_content() {
+        let policy = policy(&["image/*"]);
+        assert_eq!(detected(policy.check(b"")), Err("empty_file"));
+        let mut large = PNG.to_vec();
+        large.resize(101, 0);
+        assert_eq!(detected(policy.check(&large)), Err("file_too_large"));
+        large.truncate(100);
+        assert_eq!(detected(policy.check(&large)), Ok("image/png"));
+        assert_eq!(detected(policy.check(b"Hola, mundo")), Err("unknown_type"));
+        // Las subidas reanudables tienen su propio máximo
+        assert!(policy.check_resumable_size(1000).is_ok());
+        assert_eq!(policy.check_resumable_size(1001).map_err(|e| e.code()), Err("file_too_large"));
+        assert_eq!(policy.check_resumable_size(0).map_err(|e| e.code()), Err("empty_file"));
+    }
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn parses_role_quotas() {
+        let quotas = " admin:0, author : 200 ,*:500,broken,editor:x";
+        assert_eq!(role_quota_mb(quotas, "author"), Some(200));
+        assert_eq!(role_quota_mb(quotas, "admin"), Some(0));
+        // Sin entrada propia, o con una que no se entiende, vale la de `*`
+        assert_eq!(role_quota_mb(quotas, "user"), Some(500));
+        assert_eq!(role_quota_mb(quotas, "editor"), Some(500));
+        assert_eq!(role_quota_mb("author:200", "user"), None);
+        assert_eq!(role_quota_mb("", "user"), None);
+    }
+
+    #[test]
+    fn zero_means_unlimited() {
+        let quotas = "admin:0,*:500";
+        assert_eq!(role_quota(quotas, "admin"), None);
+        assert_eq!(role_quota(quotas, "user"), Some(500 * MB));
+        assert_eq!(role_quota("author:1", "user"), None);
+    }
+
+    #[test]
+    fn huge_values_saturate_instead_of_wrapping() {
+        // 2^44 MB son justo 2^64 bytes: multiplicando sin más quedaría en 0
+        let huge = 1u64 << 44;
+        assert_eq!(mb_to_bytes(huge), u64::MAX);
+        assert_eq!(role_quota(&format!("*:{}", huge), "user"), Some(u64::MAX));
+        assert_eq!(role_quota(&format!("*:{}", u64::MAX), "user"), Some(u64::MAX));
+        assert_eq!(mb_to_bytes(20), 20 * MB);
+    }
+
+    #[test]
+    fn quota_counts_what_is_already_used() {
+        let quota = UploadQuota { quota: Some(100), used: 60 };
+        assert!(quota.check(40).is_ok());
+        match quota.check(41) {
+            Err(UploadError::QuotaExceeded { quota, used }) => assert_eq!((quota, used), (100, 60)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(UploadQuota { quota: None, used: u64::MAX }.check(u64::MAX).is_ok());
+        // Sumando sin más daría la vuelta y parecería que cabe
+        assert!(UploadQuota { quota: Some(u64::MAX - 5), used: u64::MAX - 10 }.check(u64::MAX).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    env::{self, var},
+    io::ErrorKind,
+    path::Path,
+    sync::Arc,
+};
+
+use sqlx::postgres::PgPool;
+use tokio::{fs, process::Command};
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use super::{register_image, sniff::FileType};
+use crate::constants::{
+    DEFAULT_IMAGE_CONVERTER, DEFAULT_IMAGE_VARIANT_FORMATS, DEFAULT_IMAGE_VARIANT_WIDTHS, IMAGE_VARIANT_QUALITY,
+};
+use crate::models::{AppState, Media, MediaVariant, NewMediaVariant, Setting};
+
+/// Formatos que se redimensionan. Los GIF pueden ser animados y AVIF/HEIC
+/// ya vienen comprimidos.
+const RESIZABLE_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+
+/// Anchos y formatos configurables en `settings`: `images_variant_widths`
+/// (píxeles separados por comas) e `images_variant_formats` (`webp`,
+/// `avif`...).
+struct VariantSettings {
+    widths: Vec<i32>,
+    formats: Vec<FileType>,
+}
+
+impl VariantSettings {
+    async fn load(pool: &PgPool) -> Self {
+        let widths = Setting::get_value(pool, "images_variant_widths", DEFAULT_IMAGE_VARIANT_WIDTHS)
+            .await
+            .split(',')
+            .filter_map(|width| width.trim().parse().ok())
+            .filter(|width| *width > 0)
+            .collect();
+        let formats = Setting::get_value(pool, "images_variant_formats", DEFAULT_IMAGE_VARIANT_FORMATS)
+            .await
+            .split(',')
+            .filter_map(|format| FileType::image_from_extension(&format.trim().to_lowercase()))
+            .collect();
+        VariantSettings { widths, formats }
+    }
+}
+
+/// Genera las versiones reducidas de una imagen recién subida, en su
+/// formato y en los de `images_variant_formats`, y las registra para el
+/// HTML de los posts. Se usa el conversor de `IMAGE_CONVERTER`
+/// (ImageMagick por defecto) sobre copias temporales en disco; si no está
+/// instalado la imagen se queda solo con el original.
+pub async fn generate_variants(app_state: Arc<AppState>, media: Media) {
+    if media.width.is_none() || !RESIZABLE_TYPES.contains(&media.mime_type.as_str()) {
+        return;
+    }
+    let scratch_dir = env::temp_dir().join(format!("variants-{}", Uuid::new_v4()));
+    if let Err(e) = fs::create_dir_all(&scratch_dir).await {
+        error!("Error creating directory '{:?}': {:?}", scratch_dir, e);
+        return;
+    }
+    let variants = convert(&app_state, &media, &scratch_dir).await;
+    if let Err(e) = fs::remove_dir_all(&scratch_dir).await {
+        error!("Error removing directory '{:?}': {:?}", scratch_dir, e);
+    }
+    debug!("Generated {} variants for '{}'", variants.len(), media.path);
+    register_image(&media, &variants);
+}
+
+async fn convert(app_state: &AppState, media: &Media, scratch_dir: &Path) -> Vec<MediaVariant> {
+    let mut variants = Vec::new();
+    let (Some(width), Some(height)) = (media.width, media.height) else {
+        return variants;
+    };
+    let Some((stem, extension)) = media.path.rsplit_once('.') else {
+        return variants;
+    };
+    let Some(source_type) = FileType::image_from_extension(extension) else {
+        return variants;
+    };
+    let converter = var("IMAGE_CONVERTER").unwrap_or(DEFAULT_IMAGE_CONVERTER.to_string());
+    let settings = VariantSettings::load(&app_state.pool).await;
+    let mut widths: Vec<i32> = settings.widths.into_iter().filter(|w| *w < width).collect();
+    widths.push(width);
+    widths.sort_unstable();
+    widths.dedup();
+    let mut formats = vec![source_type];
+    formats.extend(settings.formats.into_iter().filter(|format| *format != source_type));
+
+    let source = scratch_dir.join(format!("source.{}", extension));
+    let data = match app_state.storage.get(&media.path).await {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Error reading '{}': {:?}", media.path, e);
+            return variants;
+        }
+    };
+    if let Err(e) = fs::write(&source, data).await {
+        error!("Error writing '{:?}': {:?}", source, e);
+        return variants;
+    }
+    for &target_width in &widths {
+        for format in &formats {
+            // El original ya es esa variante
+            if target_width == width && *format == source_type {
+                continue;
+            }
+            let path = format!("{}-{}.{}", stem, target_width, format.extension);
+            let target = scratch_dir.join(format!("{}.{}", target_width, format.extension));
+            let output = Command::new(&converter)
+                .arg(&source)
+                .args(["-auto-orient", "-strip", "-resize"])
+                .arg(format!("{}x", target_width))
+                .arg("-quality")
+                .arg(IMAGE_VARIANT_QUALITY.to_string())
+                .arg(&target)
+                .output()
+                .await;
+            match output {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => {
+                    error!(
+                        "Error converting '{}' to '{}': {}",
+                        media.path,
+                        path,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                    continue;
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    warn!("Image converter '{}' not found, skipping variants", converter);
+                    return variants;
+                }
+                Err(e) => {
+                    error!("Error running image converter '{}': {:?}", converter, e);
+                    return variants;
+                }
+            }
+            let converted = match fs::read(&target).await {
+                Ok(converted) => converted,
+                Err(e) => {
+                    error!("Error reading variant '{:?}': {:?}", target, e);
+                    continue;
+                }
+            };
+            if let Err(e) = app_state.storage.put(&path, &converted, format.mime_type).await {
+                error!("Error saving variant '{}': {:?}", path, e);
+                continue;
+            }
+            let new_variant = NewMediaVariant {
+                media_id: media.id,
+                path,
+                mime_type: format.mime_type.to_string(),
+                width: target_width,
+                height: ((height as i64 * target_width as i64 + width as i64 / 2) / width as i64) as i32,
+                size: converted.len() as i64,
+            };
+            match MediaVariant::create(&app_state.pool, &new_variant).await {
+                Ok(variant) => variants.push(variant),
+                Err(e) => error!("Error saving variant '{}': {:?}", new_variant.path, e),
+            }
+        }
+    }
+    variants
+}
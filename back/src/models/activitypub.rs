@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Error, FromRow,
+    postgres::PgPool,
+    query, query_as, query_scalar,
+};
+
+/// Par de claves RSA del actor del sitio, usado para firmar las peticiones.
+#[derive(Debug, Clone, FromRow)]
+pub struct ActorKey {
+    pub public_key_pem: String,
+    pub private_key_pem: String,
+}
+
+/// Actor remoto que sigue al blog.
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Follower {
+    pub id: i32,
+    pub actor: String,
+    pub inbox: String,
+    pub shared_inbox: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ActorKey {
+    pub async fn read(pool: &PgPool) -> Result<Option<ActorKey>, Error> {
+        let sql = "SELECT * FROM activitypub_keys ORDER BY id LIMIT 1";
+        query_as::<_, ActorKey>(sql).fetch_optional(pool).await
+    }
+
+    pub async fn create(pool: &PgPool, public_key_pem: &str, private_key_pem: &str) -> Result<ActorKey, Error> {
+        let sql = "INSERT INTO activitypub_keys (public_key_pem, private_key_pem)
+            VALUES ($1, $2) RETURNING *";
+        query_as::<_, ActorKey>(sql)
+            .bind(public_key_pem)
+            .bind(private_key_pem)
+            .fetch_one(pool)
+            .await
+    }
+}
+
+impl Follower {
+    pub async fn upsert(
+        pool: &PgPool,
+        actor: &str,
+        inbox: &str,
+        shared_inbox: Option<&str>,
+    ) -> Result<Follower, Error> {
+        let sql = "INSERT INTO activitypub_followers (actor, inbox, shared_inbox)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (actor) DO UPDATE SET
+                inbox = EXCLUDED.inbox,
+                shared_inbox = EXCLUDED.shared_inbox
+            RETURNING *";
+        query_as::<_, Follower>(sql)
+            .bind(actor)
+            .bind(inbox)
+            .bind(shared_inbox)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn delete(pool: &PgPool, actor: &str) -> Result<u64, Error> {
+        let sql = "DELETE FROM activitypub_followers WHERE actor = $1";
+        let result = query(sql).bind(actor).execute(pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn count(pool: &PgPool) -> Result<i64, Error> {
+        let sql = "SELECT COUNT(*) FROM activitypub_followers";
+        query_scalar(sql).fetch_one(pool).await
+    }
+
+    /// Buzones a los que entregar, usando el compartido de cada instancia
+    /// cuando existe para no repetir envíos.
+    pub async fn inboxes(pool: &PgPool) -> Result<Vec<String>, Error> {
+        let sql = "SELECT DISTINCT COALESCE(shared_inbox, inbox) FROM activitypub_followers";
+        query_scalar(sql).fetch_all(pool).await
+    }
+}
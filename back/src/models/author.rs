@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{
+    Error, FromRow, Row,
+    postgres::{PgPool, PgRow},
+    query, query_as,
+};
+
+use super::Post;
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+
+/// Perfil público de un usuario que firma posts.
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Author {
+    pub id: i32,
+    pub username: String,
+    pub slug: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub social_links: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostAuthors {
+    pub post_id: i32,
+    pub user_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadAuthorParams {
+    pub slug: Option<String>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+const AUTHOR_COLUMNS: &str = "u.id, u.username, u.slug, u.bio, u.avatar_url, u.social_links";
+
+impl Author {
+    pub async fn read_all(pool: &PgPool) -> Result<Vec<Author>, Error> {
+        let sql = format!(
+            "SELECT {} FROM users u WHERE u.active = TRUE ORDER BY u.username",
+            AUTHOR_COLUMNS
+        );
+        query_as::<_, Author>(&sql).fetch_all(pool).await
+    }
+
+    pub async fn read_by_slug(pool: &PgPool, slug: &str) -> Result<Author, Error> {
+        let sql = format!(
+            "SELECT {} FROM users u WHERE u.slug = $1 AND u.active = TRUE",
+            AUTHOR_COLUMNS
+        );
+        query_as::<_, Author>(&sql).bind(slug).fetch_one(pool).await
+    }
+
+    pub async fn read_for_post(pool: &PgPool, post_id: i32) -> Result<Vec<Author>, Error> {
+        let sql = format!(
+            "SELECT {} FROM users u
+            INNER JOIN posts_authors pa ON u.id = pa.user_id
+            WHERE pa.post_id = $1
+            ORDER BY pa.position ASC",
+            AUTHOR_COLUMNS
+        );
+        query_as::<_, Author>(&sql).bind(post_id).fetch_all(pool).await
+    }
+
+    /// Autores de varios posts a la vez, agrupados por post.
+    pub async fn read_for_posts(pool: &PgPool, post_ids: &[i32]) -> Result<HashMap<i32, Vec<Author>>, Error> {
+        let sql = format!(
+            "SELECT pa.post_id, {} FROM users u
+            INNER JOIN posts_authors pa ON u.id = pa.user_id
+            WHERE pa.post_id = ANY($1)
+            ORDER BY pa.post_id, pa.position ASC",
+            AUTHOR_COLUMNS
+        );
+        let rows = query(&sql)
+            .bind(post_ids)
+            .map(|row: PgRow| {
+                let post_id: i32 = row.get("post_id");
+                Author::from_row(&row).map(|author| (post_id, author))
+            })
+            .fetch_all(pool)
+            .await?;
+        let mut authors: HashMap<i32, Vec<Author>> = HashMap::new();
+        for row in rows {
+            let (post_id, author) = row?;
+            authors.entry(post_id).or_default().push(author);
+        }
+        Ok(authors)
+    }
+
+    /// Resuelve slugs o nombres de usuario a ids, conservando el orden.
+    pub async fn ids_from_slugs(pool: &PgPool, slugs: &[String]) -> Result<Vec<i32>, Error> {
+        let sql = "SELECT id, slug, username FROM users WHERE slug = ANY($1) OR username = ANY($1)";
+        let rows: Vec<(i32, Option<String>, String)> = query(sql)
+            .bind(slugs)
+            .map(|row: PgRow| (row.get("id"), row.get("slug"), row.get("username")))
+            .fetch_all(pool)
+            .await?;
+        let mut ids = Vec::new();
+        for slug in slugs {
+            match rows
+                .iter()
+                .find(|(_, s, username)| s.as_ref() == Some(slug) || username == slug)
+            {
+                Some((id, _, _)) if !ids.contains(id) => ids.push(*id),
+                Some(_) => {}
+                None => return Err(Error::Decode(format!("Author '{}' not found", slug).into())),
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Sustituye los autores del post por `user_ids`, en ese orden. Si algo
+    /// falla se quedan los que tenía.
+    pub async fn assign_to_post(pool: &PgPool, post_id: i32, user_ids: &[i32]) -> Result<Vec<Author>, Error> {
+        let mut tx = pool.begin().await?;
+        query("DELETE FROM posts_authors WHERE post_id = $1")
+            .bind(post_id)
+            .execute(&mut *tx)
+            .await?;
+        if !user_ids.is_empty() {
+            let sql = "INSERT INTO posts_authors (post_id, user_id, position)
+                SELECT $1, user_id, position FROM UNNEST($2::INTEGER[]) WITH ORDINALITY AS a(user_id, position)
+                ON CONFLICT (post_id, user_id) DO NOTHING";
+            query(sql)
+                .bind(post_id)
+                .bind(user_ids)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Author::read_for_post(pool, post_id).await
+    }
+
+    pub async fn count_posts(pool: &PgPool, author_id: i32) -> Result<i64, Error> {
+        let sql = "SELECT COUNT(*) total FROM posts p
+            INNER JOIN posts_authors pa ON p.id = pa.post_id
+            WHERE pa.user_id = $1 AND COALESCE(p.private, FALSE) = FALSE AND p.published_at <= NOW()";
+        query(sql)
+            .bind(author_id)
+            .map(|row: PgRow| {
+                let count: i64 = row.get("total");
+                count
+            })
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Archivo público del autor, del más reciente al más antiguo.
+    pub async fn read_posts(pool: &PgPool, author_id: i32, params: &ReadAuthorParams) -> Result<Vec<Post>, Error> {
+        let sql = "SELECT p.* FROM posts p
+            INNER JOIN posts_authors pa ON p.id = pa.post_id
+            WHERE pa.user_id = $1 AND COALESCE(p.private, FALSE) = FALSE AND p.published_at <= NOW()
+            ORDER BY p.published_at DESC NULLS LAST, p.id DESC
+            LIMIT $2 OFFSET $3";
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT) as i32;
+        let offset = ((params.page.unwrap_or(DEFAULT_PAGE) - 1) as i32) * limit;
+        query_as::<_, Post>(sql)
+            .bind(author_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool)
+            .await
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{
@@ -8,7 +10,43 @@ use sqlx::{
 };
 use tracing::debug;
 
-use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+use super::{Post, Setting};
+use crate::constants::{COMMENTS_MAX_DEPTH, DEFAULT_LIMIT, DEFAULT_PAGE};
+use crate::utils::comment_markdown_to_html;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum CommentStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum CommentKind {
+    Comment,
+    Webmention,
+    Activitypub,
+}
+
+/// Comentario recibido desde otro sitio, siempre pendiente de moderación.
+#[derive(Debug, Clone)]
+pub struct RemoteComment {
+    pub kind: CommentKind,
+    pub post_id: i32,
+    pub source_url: String,
+    pub nikename: String,
+    pub website: Option<String>,
+    pub avatar_url: Option<String>,
+    pub content: String,
+    /// Actor de ActivityPub que publicó la nota; solo él puede cambiarla o
+    /// borrarla
+    pub author_url: Option<String>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NewComment {
@@ -16,7 +54,27 @@ pub struct NewComment {
     pub parent_id: Option<i32>,
     pub nikename: String,
     pub content: String,
-    pub approved: Option<bool>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    /// Avisar por email cuando alguien responda
+    #[serde(default)]
+    pub notify_replies: bool,
+    /// Campo trampa: el formulario lo oculta y debe llegar vacío
+    #[serde(default, skip_serializing)]
+    pub honeypot: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub form_token: Option<String>,
+    #[serde(skip)]
+    pub author_ip: Option<String>,
+    #[serde(skip)]
+    pub user_agent: Option<String>,
+    #[serde(skip)]
+    pub avatar_url: Option<String>,
+    /// Usuario del blog que comenta con la sesión iniciada
+    #[serde(skip)]
+    pub user_id: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
@@ -26,7 +84,38 @@ pub struct Comment {
     pub parent_id: Option<i32>,
     pub nikename: String,
     pub content: String,
-    pub approved: Option<bool>,
+    #[serde(default)]
+    pub html_content: Option<String>,
+    /// Nunca se publica
+    #[serde(skip)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    /// Si no es nulo, el comentario es de un autor del blog
+    #[serde(default)]
+    pub user_id: Option<i32>,
+    pub status: CommentStatus,
+    pub kind: CommentKind,
+    /// Página que enlaza al post en las webmentions
+    #[serde(default)]
+    pub source_url: Option<String>,
+    /// Solo la ven los moderadores, en la cola de moderación
+    #[serde(default, skip_serializing)]
+    pub spam_score: Option<f32>,
+    #[serde(skip)]
+    pub author_ip: Option<String>,
+    #[serde(skip)]
+    pub user_agent: Option<String>,
+    #[serde(skip)]
+    pub trained_as: Option<String>,
+    #[serde(skip)]
+    pub notify_replies: bool,
+    #[serde(skip)]
+    pub reply_notified: bool,
+    #[serde(skip)]
+    pub unsubscribe_token: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -37,55 +126,363 @@ pub struct ReadCommentParams {
     pub post_id: Option<String>,
     pub parent_id: Option<String>,
     pub nikename: Option<String>,
+    pub status: Option<CommentStatus>,
     pub page: Option<u32>,
     pub limit: Option<u32>,
     pub sort_by: Option<String>,
     pub asc: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeParams {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadCommentTreeParams {
+    pub post_id: i32,
+    pub max_depth: Option<u32>,
+    pub asc: Option<bool>,
+    pub replies_asc: Option<bool>,
+}
+
+/// Comentario con sus respuestas anidadas.
+#[derive(Debug, Serialize)]
+pub struct CommentNode {
+    #[serde(flatten)]
+    pub comment: Comment,
+    pub depth: u32,
+    pub replies: Vec<CommentNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    Approve,
+    Reject,
+    Spam,
+    Delete,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Moderation {
+    pub ids: Vec<i32>,
+    pub action: ModerationAction,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct CommentStats {
+    pub status: CommentStatus,
+    pub total: i64,
+}
+
 impl Comment {
-    pub async fn create(pool: &PgPool, comment: &mut NewComment) -> Result<Comment, Error> {
+    /// El estado inicial lo decide quien llama: pendiente, spam según el
+    /// filtro o aprobado para los autores del blog.
+    pub async fn create(
+        pool: &PgPool,
+        comment: &NewComment,
+        status: CommentStatus,
+        spam_score: f32,
+    ) -> Result<Comment, Error> {
+        Comment::check_identity(comment)?;
+        Comment::check_post(pool, comment.post_id).await?;
+        Comment::check_parent(pool, comment.post_id, comment.parent_id, None).await?;
         let sql = "INSERT INTO comments (
                 post_id,
                 parent_id,
                 nikename,
                 content,
-                approved
+                html_content,
+                status,
+                spam_score,
+                author_ip,
+                user_agent,
+                email,
+                website,
+                avatar_url,
+                user_id,
+                notify_replies,
+                unsubscribe_token
             )
             VALUES (
-                $1, $2, $3, $4, $5
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15
             ) RETURNING *";
+        let notify_replies = comment.notify_replies && comment.email.is_some();
+        let unsubscribe_token = notify_replies.then(|| uuid::Uuid::new_v4().simple().to_string());
         query_as::<_, Comment>(sql)
             .bind(comment.post_id)
             .bind(comment.parent_id)
             .bind(&comment.nikename)
             .bind(&comment.content)
-            .bind(comment.approved)
+            .bind(comment_markdown_to_html(&comment.content))
+            .bind(status)
+            .bind(spam_score)
+            .bind(&comment.author_ip)
+            .bind(&comment.user_agent)
+            .bind(comment.email.as_ref().map(|email| email.trim().to_lowercase()))
+            .bind(&comment.website)
+            .bind(&comment.avatar_url)
+            .bind(comment.user_id)
+            .bind(notify_replies)
+            .bind(unsubscribe_token)
             .fetch_one(pool)
             .await
     }
 
-    pub async fn update(pool: &PgPool, comment: &mut Comment) -> Result<Comment, Error> {
-        let sql = "UPDATE comments set 
+    pub async fn update(pool: &PgPool, comment: &Comment) -> Result<Comment, Error> {
+        Comment::check_parent(pool, comment.post_id, comment.parent_id, Some(comment.id)).await?;
+        let sql = "UPDATE comments set
                 post_id = $1,
                 parent_id = $2,
                 nikename = $3,
                 content = $4,
-                approved = $5
+                html_content = $5,
+                status = $6
             WHERE
-                id = $6
+                id = $7
             RETURNING *";
         query_as::<_, Comment>(sql)
             .bind(comment.post_id)
             .bind(comment.parent_id)
             .bind(&comment.nikename)
             .bind(&comment.content)
-            .bind(comment.approved)
+            .bind(comment_markdown_to_html(&comment.content))
+            .bind(comment.status)
             .bind(comment.id)
             .fetch_one(pool)
             .await
     }
 
+    fn check_identity(comment: &NewComment) -> Result<(), Error> {
+        if comment.nikename.trim().is_empty() {
+            return Err(Error::Decode("Nikename cannot be empty".into()));
+        }
+        if let Some(email) = comment.email.as_ref() {
+            let email = email.trim();
+            let valid = email.len() <= 254
+                && !email.contains(char::is_whitespace)
+                && email
+                    .split_once('@')
+                    .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+            if !valid {
+                return Err(Error::Decode(format!("Invalid email '{}'", email).into()));
+            }
+        }
+        if let Some(website) = comment.website.as_ref()
+            && !(website.starts_with("https://") || website.starts_with("http://"))
+        {
+            return Err(Error::Decode(format!("Invalid website '{}'", website).into()));
+        }
+        Ok(())
+    }
+
+    /// Solo se admiten comentarios en posts publicados, con los comentarios
+    /// activos y, si `comments_close_after_days` es mayor que cero, dentro
+    /// de ese plazo desde su publicación.
+    async fn check_post(pool: &PgPool, post_id: i32) -> Result<(), Error> {
+        let post = match Post::read(pool, post_id).await {
+            Ok(post) => post,
+            Err(Error::RowNotFound) => {
+                return Err(Error::Decode(format!("Post {} not found", post_id).into()));
+            }
+            Err(e) => return Err(e),
+        };
+        let now = Utc::now();
+        let Some(published_at) = post
+            .published_at
+            .filter(|date| *date <= now && !post.private.unwrap_or(false))
+        else {
+            return Err(Error::Decode("Post is not published".into()));
+        };
+        if !post.comment_on.unwrap_or(false) {
+            return Err(Error::Decode("Comments are disabled for this post".into()));
+        }
+        let close_after_days: i64 = Setting::get_value(pool, "comments_close_after_days", "0")
+            .await
+            .parse()
+            .unwrap_or(0);
+        if close_after_days > 0 && published_at + chrono::Duration::days(close_after_days) < now {
+            return Err(Error::Decode("Comments are closed for this post".into()));
+        }
+        Ok(())
+    }
+
+    /// Comprueba que el padre existe, pertenece al mismo post y que no se
+    /// forma un ciclo al cambiarlo.
+    async fn check_parent(
+        pool: &PgPool,
+        post_id: i32,
+        parent_id: Option<i32>,
+        comment_id: Option<i32>,
+    ) -> Result<(), Error> {
+        let Some(parent_id) = parent_id else {
+            return Ok(());
+        };
+        let sql = "WITH RECURSIVE ancestors AS (
+                SELECT id, post_id, parent_id FROM comments WHERE id = $1
+                UNION
+                SELECT c.id, c.post_id, c.parent_id FROM comments c
+                JOIN ancestors a ON c.id = a.parent_id
+            )
+            SELECT id, post_id FROM ancestors";
+        let ancestors: Vec<(i32, i32)> = query_as(sql).bind(parent_id).fetch_all(pool).await?;
+        let Some((_, parent_post_id)) = ancestors.iter().find(|(id, _)| *id == parent_id) else {
+            return Err(Error::Decode(format!("Parent comment {} not found", parent_id).into()));
+        };
+        if *parent_post_id != post_id {
+            return Err(Error::Decode("Parent comment belongs to another post".into()));
+        }
+        if let Some(comment_id) = comment_id
+            && ancestors.iter().any(|(id, _)| *id == comment_id)
+        {
+            return Err(Error::Decode("A comment cannot reply to itself or its replies".into()));
+        }
+        Ok(())
+    }
+
+    /// Árbol de comentarios aprobados de un post. Las respuestas que superan
+    /// `max_depth` se cuelgan del último nivel permitido y las de un padre
+    /// no aprobado no se muestran. Quien llama comprueba que el post sea
+    /// público.
+    pub async fn read_tree(pool: &PgPool, params: &ReadCommentTreeParams) -> Result<Vec<CommentNode>, Error> {
+        let sql = "SELECT * FROM comments WHERE post_id = $1 AND status = $2 ORDER BY created_at ASC, id ASC";
+        let comments = query_as::<_, Comment>(sql)
+            .bind(params.post_id)
+            .bind(CommentStatus::Approved)
+            .fetch_all(pool)
+            .await?;
+        Ok(build_tree(comments, params))
+    }
+
+    /// Número de comentarios aprobados por post.
+    pub async fn count_for_posts(pool: &PgPool, post_ids: &[i32]) -> Result<HashMap<i32, i64>, Error> {
+        let sql = "SELECT post_id, COUNT(*) total FROM comments
+            WHERE post_id = ANY($1) AND status = $2
+            GROUP BY post_id";
+        let rows: Vec<(i32, i64)> = query_as(sql)
+            .bind(post_ids)
+            .bind(CommentStatus::Approved)
+            .fetch_all(pool)
+            .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Renderiza los comentarios que aún no tienen `html_content`.
+    pub async fn render_missing(pool: &PgPool) -> Result<u64, Error> {
+        let sql = "SELECT id, content FROM comments WHERE html_content IS NULL";
+        let rows: Vec<(i32, Option<String>)> = query_as(sql).fetch_all(pool).await?;
+        let total = rows.len() as u64;
+        for (id, content) in rows {
+            let html = comment_markdown_to_html(content.as_deref().unwrap_or_default());
+            query("UPDATE comments SET html_content = $1 WHERE id = $2")
+                .bind(html)
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+        Ok(total)
+    }
+
+    /// Aplica una acción de moderación a varios comentarios a la vez.
+    pub async fn moderate(pool: &PgPool, moderation: &Moderation) -> Result<Vec<Comment>, Error> {
+        let status = match moderation.action {
+            ModerationAction::Approve => CommentStatus::Approved,
+            ModerationAction::Reject => CommentStatus::Rejected,
+            ModerationAction::Spam => CommentStatus::Spam,
+            ModerationAction::Delete => {
+                let sql = "DELETE FROM comments WHERE id = ANY($1) RETURNING *";
+                return query_as::<_, Comment>(sql)
+                    .bind(&moderation.ids)
+                    .fetch_all(pool)
+                    .await;
+            }
+        };
+        let sql = "UPDATE comments SET status = $1 WHERE id = ANY($2) RETURNING *";
+        query_as::<_, Comment>(sql)
+            .bind(status)
+            .bind(&moderation.ids)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Guarda con qué categoría se entrenó el clasificador con este comentario.
+    pub async fn set_trained_as(pool: &PgPool, id: i32, category: Option<&str>) -> Result<(), Error> {
+        let sql = "UPDATE comments SET trained_as = $1 WHERE id = $2";
+        query(sql).bind(category).bind(id).execute(pool).await?;
+        Ok(())
+    }
+
+    pub async fn stats(pool: &PgPool) -> Result<Vec<CommentStats>, Error> {
+        let sql = "SELECT status, COUNT(*) total FROM comments GROUP BY status ORDER BY status";
+        query_as::<_, CommentStats>(sql).fetch_all(pool).await
+    }
+
+    /// Guarda (o actualiza si ya existía) un comentario llegado de otro
+    /// sitio: una webmention o una respuesta por ActivityPub.
+    pub async fn upsert_remote(pool: &PgPool, remote: &RemoteComment) -> Result<Comment, Error> {
+        let sql = "INSERT INTO comments (
+                post_id,
+                nikename,
+                content,
+                html_content,
+                website,
+                avatar_url,
+                status,
+                kind,
+                source_url,
+                author_url
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
+            )
+            ON CONFLICT (kind, post_id, source_url) WHERE source_url IS NOT NULL DO UPDATE SET
+                nikename = EXCLUDED.nikename,
+                content = EXCLUDED.content,
+                html_content = EXCLUDED.html_content,
+                website = EXCLUDED.website,
+                avatar_url = EXCLUDED.avatar_url
+            WHERE comments.author_url IS NOT DISTINCT FROM EXCLUDED.author_url
+            RETURNING *";
+        query_as::<_, Comment>(sql)
+            .bind(remote.post_id)
+            .bind(&remote.nikename)
+            .bind(&remote.content)
+            .bind(comment_markdown_to_html(&remote.content))
+            .bind(&remote.website)
+            .bind(&remote.avatar_url)
+            .bind(CommentStatus::Pending)
+            .bind(remote.kind)
+            .bind(&remote.source_url)
+            .bind(&remote.author_url)
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Borra el comentario remoto cuando su origen desaparece o deja de
+    /// enlazar al post. Sin `post_id` se borra en cualquier post y con
+    /// `author_url` solo si es de ese autor.
+    pub async fn delete_remote(
+        pool: &PgPool,
+        kind: CommentKind,
+        post_id: Option<i32>,
+        source_url: &str,
+        author_url: Option<&str>,
+    ) -> Result<u64, Error> {
+        let sql = "DELETE FROM comments
+            WHERE kind = $1 AND source_url = $2
+            AND ($3::INTEGER IS NULL OR post_id = $3)
+            AND ($4::VARCHAR IS NULL OR author_url = $4)";
+        let result = query(sql)
+            .bind(kind)
+            .bind(source_url)
+            .bind(post_id)
+            .bind(author_url)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
     pub async fn read(pool: &PgPool, id: i32) -> Result<Comment, Error> {
         let sql = "SELECT * FROM comments WHERE id = $1";
         query_as::<_, Comment>(sql)
@@ -94,9 +491,40 @@ impl Comment {
             .await
     }
 
+    pub async fn mark_reply_notified(pool: &PgPool, id: i32) -> Result<(), Error> {
+        let sql = "UPDATE comments SET reply_notified = TRUE WHERE id = $1";
+        query(sql).bind(id).execute(pool).await?;
+        Ok(())
+    }
+
+    /// Da de baja de los avisos de respuestas todos los comentarios con el
+    /// mismo email que el del token.
+    pub async fn unsubscribe(pool: &PgPool, token: &str) -> Result<u64, Error> {
+        let sql = "SELECT email FROM comments WHERE unsubscribe_token = $1";
+        let email: Option<String> = query(sql)
+            .bind(token)
+            .map(|row: PgRow| row.get("email"))
+            .fetch_optional(pool)
+            .await?
+            .ok_or(Error::RowNotFound)?;
+        let sql = "UPDATE comments SET notify_replies = FALSE
+            WHERE notify_replies AND LOWER(email) = LOWER($1)";
+        let result = query(sql).bind(email).execute(pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn read_approved(pool: &PgPool, id: i32) -> Result<Comment, Error> {
+        let sql = "SELECT * FROM comments WHERE id = $1 AND status = $2";
+        query_as::<_, Comment>(sql)
+            .bind(id)
+            .bind(CommentStatus::Approved)
+            .fetch_one(pool)
+            .await
+    }
+
     pub async fn count_paged(pool: &PgPool, params: &ReadCommentParams) -> Result<i64, Error> {
         let filters = vec![
-            ("comment_id", &params.post_id),
+            ("post_id", &params.post_id),
             ("parent_id", &params.parent_id),
             ("nikename", &params.nikename)
         ];
@@ -107,11 +535,25 @@ impl Comment {
         let mut sql = "SELECT COUNT(*) total FROM comments WHERE 1=1".to_string();
         for (i, (col, _)) in active_filters.iter().enumerate() {
             let param_index = i + 1;
-            sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
+            if *col == "post_id" || *col == "parent_id"{
+                sql.push_str(&format!(" AND {}::TEXT = ${}", col, param_index));
+            }else{
+                sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
+            }
+        }
+        if params.status.is_some() {
+            sql.push_str(&format!(" AND status = ${}", active_filters.len() + 1));
         }
         let mut query = query(&sql);
-        for (_col, val) in active_filters {
-            query = query.bind(format!("%{}%", val));
+        for (col, val) in active_filters {
+            if col == "post_id" || col == "parent_id"{
+                query = query.bind(val);
+            }else{
+                query = query.bind(format!("%{}%", val));
+            }
+        }
+        if let Some(status) = params.status {
+            query = query.bind(status);
         }
         query
             .map(|row: PgRow| {
@@ -139,15 +581,20 @@ impl Comment {
         for (i, (col, _)) in active_filters.iter().enumerate() {
             let param_index = i + 1;
             if *col == "post_id" || *col == "parent_id"{
-                sql.push_str(&format!(" AND {} = ${}", col, param_index));
+                sql.push_str(&format!(" AND {}::TEXT = ${}", col, param_index));
             }else{
                 sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
             }
         }
-        let limit_index = active_filters.len() + 1;
+        let mut next_index = active_filters.len() + 1;
+        if params.status.is_some() {
+            sql.push_str(&format!(" AND status = ${}", next_index));
+            next_index += 1;
+        }
+        let limit_index = next_index;
         let offset_index = limit_index + 1;
         if let Some(sort_by) = params.sort_by.as_ref()
-            && ["created_at", "nickname", "post_id", "parent_id"].contains(&sort_by.as_str())
+            && ["created_at", "nikename", "post_id", "parent_id"].contains(&sort_by.as_str())
         {
             if params.asc.unwrap_or(true) {
                 sql.push_str(&format!(" ORDER BY {} ASC", sort_by));
@@ -165,6 +612,9 @@ impl Comment {
                 query = query.bind(format!("%{}%", val));
             }
         }
+        if let Some(status) = params.status {
+            query = query.bind(status);
+        }
         let limit = params.limit.unwrap_or(DEFAULT_LIMIT) as i32;
         let offset = ((params.page.unwrap_or(DEFAULT_PAGE) - 1) as i32) * limit;
         query
@@ -182,3 +632,153 @@ impl Comment {
             .await
     }
 }
+
+/// Monta el árbol con los comentarios ya ordenados por fecha.
+fn build_tree(comments: Vec<Comment>, params: &ReadCommentTreeParams) -> Vec<CommentNode> {
+    let max_depth = params.max_depth.unwrap_or(COMMENTS_MAX_DEPTH).max(1);
+    let mut children: HashMap<Option<i32>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        children.entry(comment.parent_id).or_default().push(comment);
+    }
+    let mut roots = build_level(&mut children, None, 0, max_depth, params.replies_asc.unwrap_or(true));
+    if !params.asc.unwrap_or(false) {
+        roots.reverse();
+    }
+    roots
+}
+
+fn build_level(
+    children: &mut HashMap<Option<i32>, Vec<Comment>>,
+    parent_id: Option<i32>,
+    depth: u32,
+    max_depth: u32,
+    asc: bool,
+) -> Vec<CommentNode> {
+    let mut nodes = if depth + 1 >= max_depth {
+        // Último nivel permitido: todas las respuestas descendientes, planas
+        let mut flattened = Vec::new();
+        collect_descendants(children, parent_id, &mut flattened);
+        flattened.sort_by_key(|comment| (comment.created_at, comment.id));
+        flattened
+            .into_iter()
+            .map(|comment| CommentNode { comment, depth, replies: Vec::new() })
+            .collect::<Vec<CommentNode>>()
+    } else {
+        children
+            .remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|comment| {
+                let replies = build_level(children, Some(comment.id), depth + 1, max_depth, asc);
+                CommentNode { comment, depth, replies }
+            })
+            .collect()
+    };
+    if !asc && parent_id.is_some() {
+        nodes.reverse();
+    }
+    nodes
+}
+
+fn collect_descendants(
+    children: &mut HashMap<Option<i32>, Vec<Comment>>,
+    parent_id: Option<i32>,
+    flattened: &mut Vec<Comment>,
+) {
+    for comment in children.remove(&parent_id).unwrap_or_default() {
+        let id = comment.id;
+        flattened.push(comment);
+        collect_descendants(children, Some(id), flattened);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Comentario `id` en respuesta a `parent_id`, creado en el segundo `id`.
+    fn comment(id: i32, parent_id: Option<i32>) -> Comment {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "post_id": 1,
+            "parent_id": parent_id,
+            "nikename": "Ana",
+            "content": "Hola",
+            "status": "approved",
+            "kind": "comment",
+            "created_at": format!("2025-01-01T00:00:{:02}Z", id),
+            "updated_at": "2025-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn params(max_depth: Option<u32>, asc: Option<bool>, replies_asc: Option<bool>) -> ReadCommentTreeParams {
+        ReadCommentTreeParams { post_id: 1, max_depth, asc, replies_asc }
+    }
+
+    /// (id, profundidad, respuestas) de cada nodo, en orden.
+    fn shape(nodes: &[CommentNode]) -> Vec<(i32, u32, Vec<i32>)> {
+        nodes
+            .iter()
+            .map(|node| {
+                let replies = node.replies.iter().map(|reply| reply.comment.id).collect();
+                (node.comment.id, node.depth, replies)
+            })
+            .collect()
+    }
+
+    fn ids(nodes: &[CommentNode]) -> Vec<i32> {
+        nodes.iter().map(|node| node.comment.id).collect()
+    }
+
+    /// 1 ← 2 ← 3 ← 4, 1 ← 5 y 6 en la raíz.
+    fn thread() -> Vec<Comment> {
+        vec![
+            comment(1, None),
+            comment(2, Some(1)),
+            comment(3, Some(2)),
+            comment(4, Some(3)),
+            comment(5, Some(1)),
+            comment(6, None),
+        ]
+    }
+
+    #[test]
+    fn nests_replies_up_to_max_depth() {
+        let roots = build_tree(thread(), &params(Some(3), Some(true), None));
+        assert_eq!(shape(&roots), vec![(1, 0, vec![2, 5]), (6, 0, vec![])]);
+        assert_eq!(shape(&roots[0].replies), vec![(2, 1, vec![3, 4]), (5, 1, vec![])]);
+        // El tercer nivel es el último: el 4 queda al lado del 3, sin anidar
+        assert_eq!(shape(&roots[0].replies[0].replies), vec![(3, 2, vec![]), (4, 2, vec![])]);
+    }
+
+    #[test]
+    fn flattens_deeper_replies() {
+        // Con un solo nivel todo queda en la raíz
+        let roots = build_tree(thread(), &params(Some(0), Some(true), None));
+        assert_eq!(ids(&roots), vec![1, 2, 3, 4, 5, 6]);
+        assert!(roots.iter().all(|node| node.depth == 0 && node.replies.is_empty()));
+        let roots = build_tree(thread(), &params(Some(2), Some(true), None));
+        assert_eq!(ids(&roots[0].replies), vec![2, 3, 4, 5]);
+        assert!(roots[0].replies.iter().all(|node| node.depth == 1 && node.replies.is_empty()));
+    }
+
+    #[test]
+    fn orders_roots_and_replies_separately() {
+        // Por defecto, hilos más nuevos primero y respuestas en orden
+        let roots = build_tree(thread(), &params(None, None, None));
+        assert_eq!(shape(&roots), vec![(6, 0, vec![]), (1, 0, vec![2, 5])]);
+        let roots = build_tree(thread(), &params(None, Some(true), Some(false)));
+        assert_eq!(shape(&roots), vec![(1, 0, vec![5, 2]), (6, 0, vec![])]);
+        let roots = build_tree(thread(), &params(Some(2), Some(true), Some(false)));
+        assert_eq!(ids(&roots[0].replies), vec![5, 4, 3, 2]);
+    }
+
+    #[test]
+    fn drops_replies_to_missing_parents() {
+        // El 2 no está aprobado: sus respuestas no salen
+        let comments = vec![comment(1, None), comment(3, Some(2)), comment(4, Some(3))];
+        let roots = build_tree(comments, &params(None, Some(true), None));
+        assert_eq!(shape(&roots), vec![(1, 0, vec![])]);
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use sqlx::Error;
+
+// Delimitadores admitidos: `---` para YAML y `+++` para TOML
+const YAML_DELIMITER: &str = "---";
+const TOML_DELIMITER: &str = "+++";
+
+/// Metadatos declarados al principio del markdown de un post.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_date")]
+    pub date: Option<DateTime<Utc>>,
+    pub tags: Option<Vec<String>>,
+    pub authors: Option<Vec<String>>,
+    pub excerpt: Option<String>,
+    pub description: Option<String>,
+    pub audio: Option<String>,
+    /// URL de una imagen de la biblioteca; al guardar pasa a
+    /// `cover_media_id`
+    #[serde(alias = "cover_image")]
+    pub cover: Option<String>,
+    pub comments: Option<bool>,
+    /// No hay estado de borrador aparte: `draft: true` se guarda como
+    /// `private`, así que el post queda oculto aunque tenga fecha de
+    /// publicación, y `draft: false` lo hace visible
+    pub draft: Option<bool>,
+}
+
+impl FrontMatter {
+    /// Devuelve el front matter del markdown, si lo tiene.
+    pub fn parse(markdown: &str) -> Result<Option<FrontMatter>, Error> {
+        let Some((delimiter, raw, _)) = split(markdown)? else {
+            return Ok(None);
+        };
+        let front_matter: FrontMatter = if delimiter == YAML_DELIMITER {
+            if raw.trim().is_empty() {
+                FrontMatter::default()
+            } else {
+                serde_yaml::from_str(raw).map_err(|e| invalid(format!("invalid YAML: {}", e)))?
+            }
+        } else {
+            toml::from_str(raw).map_err(|e| invalid(format!("invalid TOML: {}", e)))?
+        };
+        front_matter.validate()?;
+        Ok(Some(front_matter))
+    }
+
+    /// Devuelve el markdown sin el bloque de front matter.
+    pub fn strip(markdown: &str) -> &str {
+        match split(markdown) {
+            Ok(Some((_, _, body))) => body,
+            _ => markdown,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(title) = &self.title
+            && title.trim().is_empty()
+        {
+            return Err(invalid("`title` cannot be empty".to_string()));
+        }
+        if let Some(slug) = &self.slug
+            && slug::slugify(slug).is_empty()
+        {
+            return Err(invalid(format!("`slug` '{}' is not valid", slug)));
+        }
+        if let Some(tags) = &self.tags
+            && tags.iter().any(|tag| tag.trim().is_empty())
+        {
+            return Err(invalid("`tags` cannot contain empty values".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Separa el markdown en (delimitador, front matter, cuerpo).
+fn split(markdown: &str) -> Result<Option<(&'static str, &str, &str)>, Error> {
+    let text = markdown.trim_start_matches('\u{feff}');
+    let Some(first_line) = text.lines().next() else {
+        return Ok(None);
+    };
+    let delimiter = match first_line.trim_end() {
+        YAML_DELIMITER => YAML_DELIMITER,
+        TOML_DELIMITER => TOML_DELIMITER,
+        _ => return Ok(None),
+    };
+    let start = first_line.len() + line_break_len(&text[first_line.len()..]);
+    let mut offset = start;
+    for line in text[start..].split_inclusive('\n') {
+        if line.trim_end() == delimiter {
+            let raw = &text[start..offset];
+            let body = &text[offset + line.len()..];
+            return Ok(Some((delimiter, raw, body)));
+        }
+        offset += line.len();
+    }
+    Err(invalid(format!("missing closing `{}` delimiter", delimiter)))
+}
+
+fn line_break_len(text: &str) -> usize {
+    if text.starts_with("\r\n") {
+        2
+    } else if text.starts_with('\n') {
+        1
+    } else {
+        0
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::Decode(format!("Front matter: {}", message).into())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDate {
+    Text(String),
+    Toml(toml::value::Datetime),
+}
+
+// Admite RFC 3339, `YYYY-MM-DD HH:MM[:SS]` y `YYYY-MM-DD` (interpretados en UTC)
+fn deserialize_date<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = match Option::<RawDate>::deserialize(deserializer)? {
+        Some(RawDate::Text(text)) => text,
+        Some(RawDate::Toml(datetime)) => datetime.to_string(),
+        None => return Ok(None),
+    };
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(Some(date.with_timezone(&Utc)));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(Some(date.and_utc()));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).map(|date| date.and_utc()));
+    }
+    Err(serde::de::Error::custom(format!(
+        "`date` '{}' is not a valid date (expected YYYY-MM-DD or RFC 3339)",
+        value
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn error(markdown: &str) -> String {
+        FrontMatter::parse(markdown).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_yaml_and_toml_blocks() {
+        let yaml = "---\ntitle: Hola\ntags: [rust, web]\ndraft: true\n---\n# Cuerpo\n";
+        let front_matter = FrontMatter::parse(yaml).unwrap().unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hola"));
+        assert_eq!(front_matter.tags, Some(vec!["rust".to_string(), "web".to_string()]));
+        assert_eq!(front_matter.draft, Some(true));
+        assert_eq!(FrontMatter::strip(yaml), "# Cuerpo\n");
+
+        let toml = "+++\ntitle = \"Hola\"\ncomments = false\n+++\n# Cuerpo";
+        let front_matter = FrontMatter::parse(toml).unwrap().unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hola"));
+        assert_eq!(front_matter.comments, Some(false));
+        assert_eq!(FrontMatter::strip(toml), "# Cuerpo");
+
+        // Un bloque YAML vacío vale; sin delimitador no hay front matter
+        assert!(FrontMatter::parse("---\n---\nTexto").unwrap().is_some());
+        assert!(FrontMatter::parse("# Título\n---\n").unwrap().is_none());
+        assert_eq!(FrontMatter::strip("# Título"), "# Título");
+    }
+
+    #[test]
+    fn accepts_crlf_and_bom() {
+        let markdown = "\u{feff}---\r\ntitle: Hola\r\n---\r\nCuerpo";
+        let front_matter = FrontMatter::parse(markdown).unwrap().unwrap();
+        assert_eq!(front_matter.title.as_deref(), Some("Hola"));
+        assert_eq!(FrontMatter::strip(markdown), "Cuerpo");
+    }
+
+    #[test]
+    fn rejects_missing_delimiter_and_unknown_fields() {
+        assert!(error("---\ntitle: Hola\n").contains("missing closing `---` delimiter"));
+        assert!(error("+++\ntitle = \"Hola\"\n---\n").contains("missing closing `+++` delimiter"));
+        assert!(error("---\ntitel: Hola\n---\n").contains("unknown field `titel`"));
+        assert!(error("+++\ncover_img = \"x\"\n+++\n").contains("unknown field `cover_img`"));
+        assert!(error("---\ntitle: \"  \"\n---\n").contains("`title` cannot be empty"));
+        assert!(error("---\ntags: [rust, \"\"]\n---\n").contains("`tags` cannot contain empty values"));
+    }
+
+    #[test]
+    fn parses_each_date_format() {
+        let date = |value: &str| {
+            FrontMatter::parse(&format!("---\ndate: \"{}\"\n---\n", value))
+                .unwrap()
+                .unwrap()
+                .date
+                .unwrap()
+        };
+        let utc = |h, m, s| Utc.with_ymd_and_hms(2024, 3, 5, h, m, s).unwrap();
+        assert_eq!(date("2024-03-05T12:30:15+02:00"), utc(10, 30, 15));
+        assert_eq!(date("2024-03-05 12:30:15"), utc(12, 30, 15));
+        assert_eq!(date("2024-03-05 12:30"), utc(12, 30, 0));
+        assert_eq!(date("2024-03-05T12:30:15"), utc(12, 30, 15));
+        assert_eq!(date("2024-03-05"), utc(0, 0, 0));
+        // Fechas nativas de TOML, con y sin hora
+        let toml = FrontMatter::parse("+++\ndate = 2024-03-05\n+++\n").unwrap().unwrap();
+        assert_eq!(toml.date, Some(utc(0, 0, 0)));
+        let toml = FrontMatter::parse("+++\ndate = 2024-03-05T12:30:15Z\n+++\n").unwrap().unwrap();
+        assert_eq!(toml.date, Some(utc(12, 30, 15)));
+        assert!(error("---\ndate: 05/03/2024\n---\n").contains("is not a valid date"));
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Error, FromRow, Row,
+    postgres::{PgPool, PgRow},
+    query, query_as,
+};
+use tracing::debug;
+
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+
+/// Datos de un fichero recién guardado en el directorio de subidas.
+#[derive(Debug, Clone)]
+pub struct NewMedia {
+    pub path: String,
+    pub original_filename: Option<String>,
+    pub mime_type: String,
+    pub size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration: Option<i32>,
+    pub bitrate: Option<i32>,
+    pub checksum: String,
+    pub uploader_id: Option<i32>,
+}
+
+/// Fichero de la biblioteca de medios. `path` es relativo al directorio de
+/// subidas; `url` se calcula al responder.
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Media {
+    pub id: i32,
+    pub path: String,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub url: String,
+    pub original_filename: Option<String>,
+    pub mime_type: String,
+    pub size: i64,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Audio: duración en segundos y bitrate medio en kbit/s
+    pub duration: Option<i32>,
+    pub bitrate: Option<i32>,
+    pub checksum: String,
+    pub uploader_id: Option<i32>,
+    pub alt_text: Option<String>,
+    /// Desde cuándo ningún post ni página lo enlaza
+    pub orphaned_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub variants: Vec<MediaVariant>,
+    /// Posts que enlazan el fichero
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub posts: Vec<MediaPost>,
+}
+
+/// Post que usa un fichero de la biblioteca en el contenido, el extracto,
+/// la portada o el audio.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MediaPost {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+}
+
+/// Versión redimensionada o convertida de una imagen de la biblioteca.
+#[derive(Debug, Clone)]
+pub struct NewMediaVariant {
+    pub media_id: i32,
+    pub path: String,
+    pub mime_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct MediaVariant {
+    pub id: i32,
+    pub media_id: i32,
+    pub path: String,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub url: String,
+    pub mime_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadMediaParams {
+    pub id: Option<String>,
+    /// Busca en el nombre original y en el texto alternativo
+    pub q: Option<String>,
+    /// Prefijo del tipo, p. ej. `image/`
+    pub mime_type: Option<String>,
+    /// Solo los de este usuario; fuera de los administradores, siempre el
+    /// propio
+    pub uploader_id: Option<i32>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub sort_by: Option<String>,
+    pub asc: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrphanParams {
+    /// Días que un huérfano se conserva antes de poder borrarse
+    pub grace_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMedia {
+    pub id: i32,
+    pub alt_text: Option<String>,
+}
+
+impl Media {
+    pub async fn create(pool: &PgPool, media: &NewMedia) -> Result<Media, Error> {
+        let sql = "INSERT INTO media (
+                path,
+                original_filename,
+                mime_type,
+                size,
+                width,
+                height,
+                duration,
+                bitrate,
+                checksum,
+                uploader_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *";
+        query_as::<_, Media>(sql)
+            .bind(&media.path)
+            .bind(&media.original_filename)
+            .bind(&media.mime_type)
+            .bind(media.size)
+            .bind(media.width)
+            .bind(media.height)
+            .bind(media.duration)
+            .bind(media.bitrate)
+            .bind(&media.checksum)
+            .bind(media.uploader_id)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read(pool: &PgPool, id: i32) -> Result<Media, Error> {
+        let sql = "SELECT * FROM media WHERE id = $1";
+        query_as::<_, Media>(sql).bind(id).fetch_one(pool).await
+    }
+
+    pub async fn read_by_ids(pool: &PgPool, ids: &[i32]) -> Result<Vec<Media>, Error> {
+        let sql = "SELECT * FROM media WHERE id = ANY($1)";
+        query_as::<_, Media>(sql).bind(ids).fetch_all(pool).await
+    }
+
+    pub async fn read_by_paths(pool: &PgPool, paths: &[String]) -> Result<Vec<Media>, Error> {
+        let sql = "SELECT * FROM media WHERE path = ANY($1)";
+        query_as::<_, Media>(sql).bind(paths).fetch_all(pool).await
+    }
+
+    /// Fichero con el mismo contenido que ya subió el mismo usuario, si lo
+    /// hay. Entre usuarios no se comparte: cada uno gestiona sus medios y
+    /// no puede saber qué han subido los demás.
+    pub async fn read_by_checksum(pool: &PgPool, checksum: &str, uploader_id: i32) -> Result<Option<Media>, Error> {
+        let sql = "SELECT * FROM media WHERE checksum = $1 AND uploader_id = $2 ORDER BY id LIMIT 1";
+        query_as::<_, Media>(sql)
+            .bind(checksum)
+            .bind(uploader_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    /// Posts que referencian cada una de las rutas indicadas.
+    pub async fn read_posts(pool: &PgPool, paths: &[String]) -> Result<HashMap<String, Vec<MediaPost>>, Error> {
+        let sql = "SELECT m.path, p.id, p.title, p.slug
+            FROM UNNEST($1::VARCHAR[]) AS m(path)
+            JOIN posts p ON STRPOS(
+                CONCAT_WS(' ', p.markdown, p.content, p.excerpt, p.audio_url),
+                m.path
+            ) > 0
+            OR p.cover_media_id = (SELECT id FROM media WHERE path = m.path)
+            ORDER BY p.id";
+        let rows = query(sql).bind(paths).fetch_all(pool).await?;
+        let mut posts: HashMap<String, Vec<MediaPost>> = HashMap::new();
+        for row in rows {
+            posts.entry(row.get("path")).or_default().push(MediaPost {
+                id: row.get("id"),
+                title: row.get("title"),
+                slug: row.get("slug"),
+            });
+        }
+        Ok(posts)
+    }
+
+    /// Textos de posts y páginas donde pueden aparecer URLs de la
+    /// biblioteca.
+    pub async fn read_reference_sources(pool: &PgPool) -> Result<Vec<String>, Error> {
+        let sql = "SELECT CONCAT_WS(' ', markdown, content, excerpt, meta, audio_url) source
+            FROM posts
+            UNION ALL
+            SELECT CONCAT_WS(' ', markdown, meta) source FROM pages";
+        query(sql)
+            .map(|row: PgRow| {
+                let source: String = row.get("source");
+                source
+            })
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Marca como huérfanos los ficheros que no están en `referenced` (ni
+    /// el original ni ninguna de sus variantes) ni son la portada de un
+    /// post, y desmarca los que vuelven a usarse. La fecha de un huérfano ya marcado no cambia.
+    pub async fn mark_orphans(pool: &PgPool, referenced: &[String]) -> Result<u64, Error> {
+        let sql = "WITH referenced AS (
+                SELECT id FROM media WHERE path = ANY($1)
+                UNION
+                SELECT media_id FROM media_variants WHERE path = ANY($1)
+                UNION
+                SELECT cover_media_id FROM posts WHERE cover_media_id IS NOT NULL
+            )
+            UPDATE media
+            SET orphaned_at = CASE WHEN id IN (SELECT id FROM referenced) THEN NULL ELSE NOW() END
+            WHERE (id IN (SELECT id FROM referenced)) = (orphaned_at IS NOT NULL)";
+        Ok(query(sql).bind(referenced).execute(pool).await?.rows_affected())
+    }
+
+    pub async fn read_orphans(pool: &PgPool) -> Result<Vec<Media>, Error> {
+        let sql = "SELECT * FROM media WHERE orphaned_at IS NOT NULL ORDER BY orphaned_at, id";
+        query_as::<_, Media>(sql).fetch_all(pool).await
+    }
+
+    /// Imágenes con dimensiones conocidas, para las variantes responsive.
+    pub async fn read_images(pool: &PgPool) -> Result<Vec<Media>, Error> {
+        let sql = "SELECT * FROM media WHERE width IS NOT NULL AND height IS NOT NULL";
+        query_as::<_, Media>(sql).fetch_all(pool).await
+    }
+
+    pub async fn update_alt_text(pool: &PgPool, id: i32, alt_text: Option<&str>) -> Result<Media, Error> {
+        let sql = "UPDATE media SET alt_text = $2 WHERE id = $1 RETURNING *";
+        query_as::<_, Media>(sql)
+            .bind(id)
+            .bind(alt_text.map(str::trim).filter(|alt| !alt.is_empty()))
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Bytes que ocupan los originales subidos por el usuario. Las variantes
+    /// las genera el servidor y no cuentan para la cuota.
+    pub async fn used_space(pool: &PgPool, uploader_id: i32) -> Result<i64, Error> {
+        let sql = "SELECT COALESCE(SUM(size), 0)::BIGINT used FROM media WHERE uploader_id = $1";
+        query(sql)
+            .bind(uploader_id)
+            .map(|row: PgRow| {
+                let used: i64 = row.get("used");
+                used
+            })
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn count_paged(pool: &PgPool, params: &ReadMediaParams) -> Result<i64, Error> {
+        let sql = "SELECT COUNT(*) total FROM media
+            WHERE ($1::VARCHAR IS NULL OR original_filename ILIKE $1 OR alt_text ILIKE $1)
+            AND ($2::VARCHAR IS NULL OR mime_type LIKE $2)
+            AND ($3::INT IS NULL OR uploader_id = $3)";
+        query(sql)
+            .bind(params.q.as_ref().map(|q| format!("%{}%", q)))
+            .bind(params.mime_type.as_ref().map(|mime| format!("{}%", mime)))
+            .bind(params.uploader_id)
+            .map(|row: PgRow| {
+                let count: i64 = row.get("total");
+                count
+            })
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read_paged(pool: &PgPool, params: &ReadMediaParams) -> Result<Vec<Media>, Error> {
+        let mut sql = "SELECT * FROM media
+            WHERE ($1::VARCHAR IS NULL OR original_filename ILIKE $1 OR alt_text ILIKE $1)
+            AND ($2::VARCHAR IS NULL OR mime_type LIKE $2)
+            AND ($3::INT IS NULL OR uploader_id = $3)"
+            .to_string();
+        if let Some(sort_by) = params.sort_by.as_ref()
+            && ["original_filename", "mime_type", "size", "created_at"].contains(&sort_by.as_str())
+        {
+            if params.asc.unwrap_or(true) {
+                sql.push_str(&format!(" ORDER BY {} ASC", sort_by));
+            } else {
+                sql.push_str(&format!(" ORDER BY {} DESC", sort_by));
+            }
+        } else {
+            sql.push_str(" ORDER BY created_at DESC");
+        }
+        sql.push_str(" LIMIT $4 OFFSET $5");
+        debug!("query sql: {}", sql);
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT) as i32;
+        let offset = ((params.page.unwrap_or(DEFAULT_PAGE) - 1) as i32) * limit;
+        query_as::<_, Media>(&sql)
+            .bind(params.q.as_ref().map(|q| format!("%{}%", q)))
+            .bind(params.mime_type.as_ref().map(|mime| format!("{}%", mime)))
+            .bind(params.uploader_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn delete(pool: &PgPool, id: i32) -> Result<Media, Error> {
+        let sql = "DELETE FROM media WHERE id = $1 RETURNING *";
+        query_as::<_, Media>(sql).bind(id).fetch_one(pool).await
+    }
+}
+
+impl MediaVariant {
+    pub async fn create(pool: &PgPool, variant: &NewMediaVariant) -> Result<MediaVariant, Error> {
+        let sql = "INSERT INTO media_variants (media_id, path, mime_type, width, height, size)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (path) DO UPDATE SET
+                mime_type = EXCLUDED.mime_type,
+                width = EXCLUDED.width,
+                height = EXCLUDED.height,
+                size = EXCLUDED.size
+            RETURNING *";
+        query_as::<_, MediaVariant>(sql)
+            .bind(variant.media_id)
+            .bind(&variant.path)
+            .bind(&variant.mime_type)
+            .bind(variant.width)
+            .bind(variant.height)
+            .bind(variant.size)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read_for_media(pool: &PgPool, media_id: i32) -> Result<Vec<MediaVariant>, Error> {
+        let sql = "SELECT * FROM media_variants WHERE media_id = $1 ORDER BY mime_type, width";
+        query_as::<_, MediaVariant>(sql).bind(media_id).fetch_all(pool).await
+    }
+
+    pub async fn read_for_medias(pool: &PgPool, media_ids: &[i32]) -> Result<Vec<MediaVariant>, Error> {
+        let sql = "SELECT * FROM media_variants WHERE media_id = ANY($1) ORDER BY media_id, mime_type, width";
+        query_as::<_, MediaVariant>(sql).bind(media_ids).fetch_all(pool).await
+    }
+
+    pub async fn read_all(pool: &PgPool) -> Result<Vec<MediaVariant>, Error> {
+        let sql = "SELECT * FROM media_variants ORDER BY media_id, mime_type, width";
+        query_as::<_, MediaVariant>(sql).fetch_all(pool).await
+    }
+}
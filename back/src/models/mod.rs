@@ -4,8 +4,19 @@ mod user;
 mod post;
 mod tag;
 mod comment;
+mod front_matter;
+mod slug_redirect;
+mod series;
+mod author;
+mod page;
+mod webmention;
+mod activitypub;
+mod stats;
+mod media;
+mod upload_session;
 
 use std::path::PathBuf;
+use minijinja::Environment;
 pub use response::{
     ApiResponse,
     CustomResponse,
@@ -13,19 +24,48 @@ pub use response::{
     Pagination,
     PagedResponse,
 };
-pub use user::{User, TokenClaims, UserSchema, UserRegister};
+pub use user::{User, TokenClaims, UserSchema, UserRegister, UserProfile, ProfileParams};
 pub use post::{NewPost, Post, ReadPostParams, HtmlPost};
 pub use tag::{NewTag, Tag, ReadTagParams};
-pub use comment::{NewComment, Comment, ReadCommentParams};
+pub use comment::{
+    NewComment, Comment, CommentKind, CommentStatus, RemoteComment, Moderation, ModerationAction, ReadCommentParams,
+    ReadCommentTreeParams, UnsubscribeParams,
+};
+pub use front_matter::FrontMatter;
+pub use slug_redirect::SlugRedirect;
+pub use series::{
+    NewSeries, Series, SeriesNavigation, HtmlSeries, SeriesMembership, SeriesOrder,
+    ReadSeriesParams,
+};
+pub use author::{Author, PostAuthors, ReadAuthorParams};
+pub use settings::Setting;
+pub use page::{NewPage, Page, HtmlPage, ReadPageParams};
+pub use webmention::{Webmention, WebmentionDirection, ReadWebmentionParams};
+pub use activitypub::{ActorKey, Follower};
+pub use media::{
+    Media, MediaVariant, NewMedia, NewMediaVariant, OrphanParams, ReadMediaParams, UpdateMedia,
+};
+pub use upload_session::{NewUploadSession, UploadSession};
+pub use stats::{NewReaction, NewView, PostStats, ReadReactionsParams, ReadStatsParams};
 pub type Error = Box<dyn std::error::Error>;
 
 use sqlx::postgres::PgPool;
+use std::sync::Arc;
+
+use crate::notifications::Notifier;
+use crate::spam::SpamPipeline;
+use crate::storage::Storage;
+use crate::utils::TrustedProxies;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub secret: String,
     pub static_dir: PathBuf,
-    pub upload_dir: PathBuf,
+    pub storage: Arc<dyn Storage>,
     pub base_url: String,
+    pub templates: Environment<'static>,
+    pub spam: Arc<SpamPipeline>,
+    pub notifier: Arc<Notifier>,
+    pub trusted_proxies: TrustedProxies,
 }
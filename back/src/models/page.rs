@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use md_to_text::convert;
+use serde::{Deserialize, Serialize};
+use slug::slugify;
+use sqlx::{
+    Error, FromRow, Row,
+    postgres::{PgPool, PgRow},
+    query, query_as,
+};
+use tracing::debug;
+
+use super::FrontMatter;
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE, RESERVED_PATHS};
+use crate::utils::markdown_to_html;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewPage {
+    pub parent_id: Option<i32>,
+    pub title: String,
+    pub slug: Option<String>,
+    pub markdown: String,
+    pub meta: Option<String>,
+    pub private: Option<bool>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Page {
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub title: String,
+    pub slug: String,
+    pub path: String,
+    pub markdown: String,
+    pub meta: Option<String>,
+    pub private: Option<bool>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct PageLink {
+    pub id: i32,
+    pub title: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HtmlPage {
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub title: String,
+    pub slug: String,
+    pub path: String,
+    pub markdown: String,
+    pub html_content: String,
+    pub meta: Option<String>,
+    pub clean_meta: Option<String>,
+    pub breadcrumbs: Vec<PageLink>,
+    pub children: Vec<PageLink>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadPageParams {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub path: Option<String>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub sort_by: Option<String>,
+    pub asc: Option<bool>,
+}
+
+impl HtmlPage {
+    pub fn new(page: &Page) -> Self {
+        HtmlPage {
+            id: page.id,
+            parent_id: page.parent_id,
+            title: page.title.clone(),
+            slug: page.slug.clone(),
+            path: page.path.clone(),
+            markdown: page.markdown.clone(),
+            html_content: markdown_to_html(FrontMatter::strip(&page.markdown)),
+            meta: page.meta.clone(),
+            clean_meta: page.meta.as_ref().map(|m| convert(m)),
+            breadcrumbs: Vec::new(),
+            children: Vec::new(),
+            updated_at: page.updated_at,
+        }
+    }
+}
+
+impl Page {
+    pub async fn create(pool: &PgPool, page: &NewPage) -> Result<Page, Error> {
+        let slug = slugify(page.slug.as_ref().unwrap_or(&page.title));
+        let path = Page::build_path(pool, page.parent_id, &slug, None).await?;
+        let sql = "INSERT INTO pages (
+                parent_id,
+                title,
+                slug,
+                path,
+                markdown,
+                meta,
+                private,
+                position
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8
+            ) RETURNING *";
+        query_as::<_, Page>(sql)
+            .bind(page.parent_id)
+            .bind(&page.title)
+            .bind(&slug)
+            .bind(&path)
+            .bind(&page.markdown)
+            .bind(&page.meta)
+            .bind(page.private)
+            .bind(page.position.unwrap_or_default())
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Actualiza la página y, si cambia su ruta, la de todas sus descendientes.
+    pub async fn update(pool: &PgPool, page: &Page) -> Result<Page, Error> {
+        let current = Page::read(pool, page.id).await?;
+        let slug = slugify(&page.slug);
+        let path = Page::build_path(pool, page.parent_id, &slug, Some(page.id)).await?;
+        let sql = "UPDATE pages set
+                parent_id = $1,
+                title = $2,
+                slug = $3,
+                path = $4,
+                markdown = $5,
+                meta = $6,
+                private = $7,
+                position = $8
+            WHERE
+                id = $9
+            RETURNING *";
+        let updated = query_as::<_, Page>(sql)
+            .bind(page.parent_id)
+            .bind(&page.title)
+            .bind(&slug)
+            .bind(&path)
+            .bind(&page.markdown)
+            .bind(&page.meta)
+            .bind(page.private)
+            .bind(page.position)
+            .bind(page.id)
+            .fetch_one(pool)
+            .await?;
+        if current.path != updated.path {
+            let sql = "UPDATE pages
+                SET path = $2 || SUBSTRING(path FROM LENGTH($1) + 1)
+                WHERE path LIKE $1 || '/%'";
+            query(sql)
+                .bind(&current.path)
+                .bind(&updated.path)
+                .execute(pool)
+                .await?;
+        }
+        Ok(updated)
+    }
+
+    /// Construye la ruta completa (`padre/hijo`) validando el slug y que no
+    /// se creen ciclos al cambiar de padre.
+    async fn build_path(pool: &PgPool, parent_id: Option<i32>, slug: &str, page_id: Option<i32>) -> Result<String, Error> {
+        if slug.is_empty() {
+            return Err(Error::Decode("Slug cannot be empty".into()));
+        }
+        let Some(parent_id) = parent_id else {
+            if RESERVED_PATHS.contains(&slug) {
+                return Err(Error::Decode(format!("Path '{}' is reserved", slug).into()));
+            }
+            return Ok(slug.to_string());
+        };
+        let parent = Page::read(pool, parent_id).await?;
+        if let Some(page_id) = page_id {
+            let current = Page::read(pool, page_id).await?;
+            if parent.id == page_id || parent.path.starts_with(&format!("{}/", current.path)) {
+                return Err(Error::Decode("A page cannot be moved under itself".into()));
+            }
+        }
+        Ok(format!("{}/{}", parent.path, slug))
+    }
+
+    pub async fn read(pool: &PgPool, id: i32) -> Result<Page, Error> {
+        let sql = "SELECT * FROM pages WHERE id = $1";
+        query_as::<_, Page>(sql).bind(id).fetch_one(pool).await
+    }
+
+    /// Página pública a partir de la ruta de la petición (`/acerca/equipo/`).
+    pub async fn read_public_by_path(pool: &PgPool, path: &str) -> Result<Option<Page>, Error> {
+        let path = path.trim_matches('/');
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let sql = "SELECT * FROM pages WHERE path = $1 AND COALESCE(private, FALSE) = FALSE";
+        query_as::<_, Page>(sql).bind(path).fetch_optional(pool).await
+    }
+
+    pub async fn read_children(pool: &PgPool, page_id: i32) -> Result<Vec<PageLink>, Error> {
+        let sql = "SELECT id, title, path FROM pages
+            WHERE parent_id = $1 AND COALESCE(private, FALSE) = FALSE
+            ORDER BY position ASC, title ASC";
+        query_as::<_, PageLink>(sql).bind(page_id).fetch_all(pool).await
+    }
+
+    /// Ancestros de la página, de la raíz hacia abajo.
+    pub async fn read_breadcrumbs(pool: &PgPool, page: &Page) -> Result<Vec<PageLink>, Error> {
+        let segments: Vec<&str> = page.path.split('/').collect();
+        let ancestors: Vec<String> = (1..segments.len())
+            .map(|i| segments[..i].join("/"))
+            .collect();
+        if ancestors.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = "SELECT id, title, path FROM pages WHERE path = ANY($1) ORDER BY LENGTH(path) ASC";
+        query_as::<_, PageLink>(sql).bind(&ancestors).fetch_all(pool).await
+    }
+
+    pub async fn count_paged(pool: &PgPool, params: &ReadPageParams) -> Result<i64, Error> {
+        let filters = vec![("title", &params.title), ("path", &params.path)];
+        let active_filters: Vec<(&str, String)> = filters
+            .into_iter()
+            .filter_map(|(col, val)| val.as_ref().map(|v| (col, v.to_string())))
+            .collect();
+        let mut sql = "SELECT COUNT(*) total FROM pages WHERE 1=1".to_string();
+        for (i, (col, _)) in active_filters.iter().enumerate() {
+            let param_index = i + 1;
+            sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
+        }
+        let mut query = query(&sql);
+        for (_col, val) in active_filters {
+            query = query.bind(format!("%{}%", val));
+        }
+        query
+            .map(|row: PgRow| {
+                let count: i64 = row.get("total");
+                count
+            })
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read_paged(pool: &PgPool, params: &ReadPageParams) -> Result<Vec<Page>, Error> {
+        let filters = vec![("title", &params.title), ("path", &params.path)];
+        let active_filters: Vec<(&str, String)> = filters
+            .into_iter()
+            .filter_map(|(col, val)| val.as_ref().map(|v| (col, v.to_string())))
+            .collect();
+        let mut sql = "SELECT * FROM pages WHERE 1=1".to_string();
+        for (i, (col, _)) in active_filters.iter().enumerate() {
+            let param_index = i + 1;
+            sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
+        }
+        let limit_index = active_filters.len() + 1;
+        let offset_index = limit_index + 1;
+        if let Some(sort_by) = params.sort_by.as_ref()
+            && ["title", "path", "position", "created_at"].contains(&sort_by.as_str())
+        {
+            if params.asc.unwrap_or(true) {
+                sql.push_str(&format!(" ORDER BY {} ASC", sort_by));
+            } else {
+                sql.push_str(&format!(" ORDER BY {} DESC", sort_by));
+            }
+        } else {
+            sql.push_str(" ORDER BY path ASC");
+        }
+        sql.push_str(&format!(" LIMIT ${} OFFSET ${}", limit_index, offset_index));
+        let mut query = query_as::<_, Page>(&sql);
+        debug!("query sql: {}", sql);
+        for (_col, val) in &active_filters {
+            query = query.bind(format!("%{}%", val));
+        }
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT) as i32;
+        let offset = ((params.page.unwrap_or(DEFAULT_PAGE) - 1) as i32) * limit;
+        query.bind(limit).bind(offset).fetch_all(pool).await
+    }
+
+    pub async fn delete(pool: &PgPool, page_id: i32) -> Result<Page, Error> {
+        let sql = "DELETE FROM pages WHERE id = $1 RETURNING *";
+        query_as::<_, Page>(sql).bind(page_id).fetch_one(pool).await
+    }
+}
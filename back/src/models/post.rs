@@ -6,11 +6,12 @@ use serde::{Deserialize, Serialize};
 use slug::slugify;
 use sqlx::{
     Error, FromRow, Row,
-    postgres::{PgPool, PgRow},
+    postgres::{PgConnection, PgPool, PgRow},
     query, query_as,
 };
 use tracing::debug;
 
+use super::{Author, FrontMatter, Media, SeriesNavigation, SlugRedirect};
 use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
 use crate::utils::markdown_to_html;
 
@@ -30,6 +31,9 @@ pub struct NewPost {
     pub comment_on: Option<bool>,
     pub private: Option<bool>,
     pub audio_url: Option<String>,
+    /// Portada elegida en la biblioteca de medios. La `cover` del front
+    /// matter se traduce a este campo al guardar
+    pub cover_media_id: Option<i32>,
     pub published_at: Option<DateTime<Utc>>,
 }
 
@@ -46,6 +50,7 @@ pub struct Post {
     pub comment_on: Option<bool>,
     pub private: Option<bool>,
     pub audio_url: Option<String>,
+    pub cover_media_id: Option<i32>,
     pub published_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -76,9 +81,19 @@ pub struct HtmlPost {
     pub comment_on: Option<bool>,
     pub private: Option<bool>,
     pub audio_url: Option<String>,
+    /// Fichero de la biblioteca al que apunta `audio_url`, con su duración
+    pub audio: Option<Media>,
+    /// Fichero de la biblioteca elegido como portada, con sus variantes
+    pub cover: Option<Media>,
+    /// Imagen para Open Graph: la portada o, si no hay, la tarjeta generada
+    pub og_image: Option<String>,
     pub published_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub series: Option<SeriesNavigation>,
+    pub authors: Vec<Author>,
+    pub comments_count: i64,
+    pub views_count: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,38 +109,82 @@ pub struct ReadPostParams {
 
 impl HtmlPost {
     pub fn new(post: &Post) -> Self {
+        let markdown = FrontMatter::strip(&post.markdown);
         HtmlPost {
             id: post.id,
             title: post.title.clone(),
             slug: post.slug.clone(),
             content: post.content.clone(),
             markdown: post.markdown.clone(),
-            html_content: markdown_to_html(&post.markdown),
+            html_content: markdown_to_html(markdown),
             excerpt: post.excerpt.clone(),
             html_excerpt: post.excerpt.as_ref().map(|e| markdown_to_html(e)),
             meta: post.meta.clone(),
             clean_meta: post.meta.as_ref().map(|m| convert(m)),
             html_meta: post.meta.as_ref().map(|m| markdown_to_html(m)),
-            image: get_first_image(&post.markdown),
+            image: get_first_image(markdown),
             outline: post.outline.clone(),
             comment_on: post.comment_on,
             private: post.private,
             audio_url: post.audio_url.clone(),
+            audio: None,
+            cover: None,
+            og_image: None,
             published_at: post.published_at,
             created_at: post.created_at,
             updated_at: post.updated_at,
+            series: None,
+            authors: Vec::new(),
+            comments_count: 0,
+            views_count: 0,
         }
     }
+
+    /// La portada de la biblioteca pasa a ser la imagen del post y la de
+    /// Open Graph.
+    pub fn set_cover(&mut self, cover: Media) {
+        self.image = Some(Image {
+            url: cover.url.clone(),
+            title: None,
+            alt: cover.alt_text.clone().or_else(|| Some(self.title.clone())),
+        });
+        self.og_image = Some(cover.url.clone());
+        self.cover = Some(cover);
+    }
 }
 
 impl Post {
+    /// Público y con fecha de publicación ya pasada.
+    pub fn is_public(&self) -> bool {
+        !self.private.unwrap_or(false) && self.published_at.is_some_and(|date| date <= Utc::now())
+    }
+
+    /// Marca el post como enviado al fediverso. Devuelve `true` solo la
+    /// primera vez.
+    pub async fn mark_federated(pool: &PgPool, post_id: i32) -> Result<bool, Error> {
+        let sql = "UPDATE posts SET federated_at = NOW() WHERE id = $1 AND federated_at IS NULL";
+        let result = query(sql).bind(post_id).execute(pool).await?;
+        Ok(result.rows_affected() == 1)
+    }
+
     pub async fn create(pool: &PgPool, post: &NewPost) -> Result<Post, Error> {
         if post.content.is_empty() {
             return Err(Error::Decode("Content cannot be empty".into()));
         }
-        let title =
-            get_title(&post.markdown).ok_or(Error::Decode("Not found title in content".into()))?;
-        let slug = slugify(&title);
+        let front_matter = FrontMatter::parse(&post.markdown)?.unwrap_or_default();
+        let title = front_matter
+            .title
+            .clone()
+            .or_else(|| get_title(FrontMatter::strip(&post.markdown)))
+            .ok_or(Error::Decode("Not found title in content".into()))?;
+        let requested_slug = front_matter
+            .slug
+            .as_ref()
+            .or(post.slug.as_ref())
+            .map(slugify)
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| slugify(&title));
+        let slug = Post::unique_slug(&mut *pool.acquire().await?, &requested_slug, None).await?;
         let sql = "INSERT INTO posts (
                 title,
                 slug,
@@ -137,23 +196,25 @@ impl Post {
                 comment_on,
                 private,
                 audio_url,
+                cover_media_id,
                 published_at
             )
             VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12
             ) RETURNING *";
         query_as::<_, Post>(sql)
             .bind(title)
             .bind(slug)
             .bind(&post.content)
             .bind(&post.markdown)
-            .bind(&post.excerpt)
-            .bind(&post.meta)
+            .bind(front_matter.excerpt.as_ref().or(post.excerpt.as_ref()))
+            .bind(front_matter.description.as_ref().or(post.meta.as_ref()))
             .bind(&post.outline)
-            .bind(post.comment_on)
-            .bind(post.private)
-            .bind(&post.audio_url)
-            .bind(post.published_at)
+            .bind(front_matter.comments.or(post.comment_on))
+            .bind(front_matter.draft.or(post.private))
+            .bind(front_matter.audio.as_ref().or(post.audio_url.as_ref()))
+            .bind(post.cover_media_id)
+            .bind(front_matter.date.or(post.published_at))
             .fetch_one(pool)
             .await
     }
@@ -162,9 +223,29 @@ impl Post {
         if post.content.is_empty() {
             return Err(Error::Decode("Content cannot be empty".into()));
         }
-        let title =
-            get_title(&post.markdown).ok_or(Error::Decode("Not found title in content".into()))?;
-        let slug = slugify(&title);
+        let front_matter = FrontMatter::parse(&post.markdown)?.unwrap_or_default();
+        let title = front_matter
+            .title
+            .clone()
+            .or_else(|| get_title(FrontMatter::strip(&post.markdown)))
+            .ok_or(Error::Decode("Not found title in content".into()))?;
+        // El slug solo cambia si se pide explícitamente, nunca al cambiar el título.
+        // La redirección y el post se guardan juntos o no se guarda nada, y dos
+        // renombrados a la vez esperan su turno para no elegir el mismo slug libre
+        let current = Post::read(pool, post.id).await?;
+        let requested_slug = slugify(front_matter.slug.as_ref().unwrap_or(&post.slug));
+        let mut tx = pool.begin().await?;
+        let slug = if requested_slug.is_empty() || requested_slug == current.slug {
+            current.slug.clone()
+        } else {
+            query("SELECT pg_advisory_xact_lock(hashtext('posts.slug'))")
+                .execute(&mut *tx)
+                .await?;
+            let slug = Post::unique_slug(&mut tx, &requested_slug, Some(post.id)).await?;
+            SlugRedirect::delete_by_slug(&mut tx, &slug).await?;
+            SlugRedirect::create(&mut tx, &current.slug, post.id).await?;
+            slug
+        };
         let sql = "UPDATE posts set 
                 title = $1,
                 slug = $2,
@@ -176,25 +257,50 @@ impl Post {
                 comment_on = $8,
                 private = $9,
                 audio_url = $10,
-                published_at = $11
+                cover_media_id = $11,
+                published_at = $12
             WHERE
-                id = $12
+                id = $13
             RETURNING *";
-        query_as::<_, Post>(sql)
+        let updated = query_as::<_, Post>(sql)
             .bind(&title)
             .bind(&slug)
             .bind(&post.content)
             .bind(&post.markdown)
-            .bind(&post.excerpt)
-            .bind(&post.meta)
+            .bind(front_matter.excerpt.as_ref().or(post.excerpt.as_ref()))
+            .bind(front_matter.description.as_ref().or(post.meta.as_ref()))
             .bind(&post.outline)
-            .bind(post.comment_on)
-            .bind(post.private)
-            .bind(&post.audio_url)
-            .bind(post.published_at)
+            .bind(front_matter.comments.or(post.comment_on))
+            .bind(front_matter.draft.or(post.private))
+            .bind(front_matter.audio.as_ref().or(post.audio_url.as_ref()))
+            .bind(post.cover_media_id)
+            .bind(front_matter.date.or(post.published_at))
             .bind(post.id)
-            .fetch_one(pool)
-            .await
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// Devuelve un slug libre a partir de `slug`, añadiendo un sufijo numérico
+    /// si ya lo usa otro post o una redirección de otro post.
+    pub async fn unique_slug(conn: &mut PgConnection, slug: &str, post_id: Option<i32>) -> Result<String, Error> {
+        let sql = "SELECT slug FROM posts WHERE (slug = $1 OR slug LIKE $1 || '-%') AND id <> $2
+            UNION
+            SELECT slug FROM slug_redirects WHERE (slug = $1 OR slug LIKE $1 || '-%') AND post_id <> $2";
+        let taken: Vec<String> = query(sql)
+            .bind(slug)
+            .bind(post_id.unwrap_or(0))
+            .map(|row: PgRow| row.get("slug"))
+            .fetch_all(conn)
+            .await?;
+        if !taken.iter().any(|t| t == slug) {
+            return Ok(slug.to_string());
+        }
+        let suffix = (2..)
+            .find(|n| !taken.contains(&format!("{}-{}", slug, n)))
+            .unwrap_or_default();
+        Ok(format!("{}-{}", slug, suffix))
     }
 
     pub async fn assign_tags(pool: &PgPool, post_id: i32, tag_ids: Vec<i32>) -> Result<(), Error> {
@@ -251,6 +357,8 @@ impl Post {
             .await
     }
 
+    /// Busca el post por su slug actual. Los antiguos los resuelve
+    /// `http/redirect.rs` con las redirecciones.
     pub async fn read_by_slug(pool: &PgPool, slug: &str) -> Result<Post, Error> {
         let sql = "SELECT * FROM posts WHERE slug = $1";
         query_as::<_, Post>(sql).bind(slug).fetch_one(pool).await
@@ -289,6 +397,24 @@ impl Post {
         query.bind(limit).bind(offset).fetch_all(pool).await
     }
 
+    /// Posts públicos ya publicados, del más reciente al más antiguo.
+    pub async fn read_published(pool: &PgPool, limit: i64, author_id: Option<i32>) -> Result<Vec<Post>, Error> {
+        let sql = "SELECT p.* FROM posts p
+            WHERE COALESCE(p.private, FALSE) = FALSE
+            AND p.published_at IS NOT NULL
+            AND p.published_at <= NOW()
+            AND ($1::INTEGER IS NULL OR EXISTS (
+                SELECT 1 FROM posts_authors pa WHERE pa.post_id = p.id AND pa.user_id = $1
+            ))
+            ORDER BY p.published_at DESC
+            LIMIT $2";
+        query_as::<_, Post>(sql)
+            .bind(author_id)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+    }
+
     pub async fn delete(pool: &PgPool, post_id: i32) -> Result<Post, Error> {
         let sql = "DELETE FROM posts WHERE id = $1 RETURNING *";
         query_as::<_, Post>(sql).bind(post_id).fetch_one(pool).await
@@ -0,0 +1,375 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use slug::slugify;
+use sqlx::{
+    Error, FromRow, Row,
+    postgres::{PgPool, PgRow},
+    query, query_as,
+};
+use tracing::debug;
+
+use super::{HtmlPost, Post};
+use crate::constants::{DEFAULT_LIMIT, DEFAULT_PAGE};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NewSeries {
+    pub title: String,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Series {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct SeriesPost {
+    pub id: i32,
+    pub series_id: i32,
+    pub post_id: i32,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct SeriesPostLink {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub position: i32,
+}
+
+/// Navegación anterior/siguiente de un post dentro de su serie.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeriesNavigation {
+    pub series: Series,
+    pub part: usize,
+    pub total: usize,
+    pub previous: Option<SeriesPostLink>,
+    pub next: Option<SeriesPostLink>,
+}
+
+/// Página índice de una serie con sus posts ordenados.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HtmlSeries {
+    pub series: Series,
+    pub posts: Vec<HtmlPost>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeriesMembership {
+    pub series_id: i32,
+    pub post_id: i32,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeriesOrder {
+    pub series_id: i32,
+    pub post_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadSeriesParams {
+    pub id: Option<String>,
+    pub post_id: Option<i32>,
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub sort_by: Option<String>,
+    pub asc: Option<bool>,
+}
+
+impl Series {
+    pub async fn create(pool: &PgPool, series: &NewSeries) -> Result<Series, Error> {
+        let slug = slugify(series.slug.as_ref().unwrap_or(&series.title));
+        let sql = "INSERT INTO series (
+                title,
+                slug,
+                description
+            )
+            VALUES (
+                $1, $2, $3
+            ) RETURNING *";
+        query_as::<_, Series>(sql)
+            .bind(&series.title)
+            .bind(&slug)
+            .bind(&series.description)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn update(pool: &PgPool, series: &Series) -> Result<Series, Error> {
+        let sql = "UPDATE series set
+                title = $1,
+                slug = $2,
+                description = $3
+            WHERE
+                id = $4
+            RETURNING *";
+        query_as::<_, Series>(sql)
+            .bind(&series.title)
+            .bind(slugify(&series.slug))
+            .bind(&series.description)
+            .bind(series.id)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read(pool: &PgPool, id: i32) -> Result<Series, Error> {
+        let sql = "SELECT * FROM series WHERE id = $1";
+        query_as::<_, Series>(sql).bind(id).fetch_one(pool).await
+    }
+
+    pub async fn read_by_slug(pool: &PgPool, slug: &str) -> Result<Series, Error> {
+        let sql = "SELECT * FROM series WHERE slug = $1";
+        query_as::<_, Series>(sql).bind(slug).fetch_one(pool).await
+    }
+
+    pub async fn count_paged(pool: &PgPool, params: &ReadSeriesParams) -> Result<i64, Error> {
+        let filters = vec![("title", &params.title), ("slug", &params.slug)];
+        let active_filters: Vec<(&str, String)> = filters
+            .into_iter()
+            .filter_map(|(col, val)| val.as_ref().map(|v| (col, v.to_string())))
+            .collect();
+        let mut sql = "SELECT COUNT(*) total FROM series WHERE 1=1".to_string();
+        for (i, (col, _)) in active_filters.iter().enumerate() {
+            let param_index = i + 1;
+            sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
+        }
+        let mut query = query(&sql);
+        for (_col, val) in active_filters {
+            query = query.bind(format!("%{}%", val));
+        }
+        query
+            .map(|row: PgRow| {
+                let count: i64 = row.get("total");
+                count
+            })
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read_paged(pool: &PgPool, params: &ReadSeriesParams) -> Result<Vec<Series>, Error> {
+        let filters = vec![("title", &params.title), ("slug", &params.slug)];
+        let active_filters: Vec<(&str, String)> = filters
+            .into_iter()
+            .filter_map(|(col, val)| val.as_ref().map(|v| (col, v.to_string())))
+            .collect();
+        let mut sql = "SELECT * FROM series WHERE 1=1".to_string();
+        for (i, (col, _)) in active_filters.iter().enumerate() {
+            let param_index = i + 1;
+            sql.push_str(&format!(" AND {} LIKE ${}", col, param_index));
+        }
+        let limit_index = active_filters.len() + 1;
+        let offset_index = limit_index + 1;
+        if let Some(sort_by) = params.sort_by.as_ref()
+            && ["title", "slug", "created_at"].contains(&sort_by.as_str())
+        {
+            if params.asc.unwrap_or(true) {
+                sql.push_str(&format!(" ORDER BY {} ASC", sort_by));
+            } else {
+                sql.push_str(&format!(" ORDER BY {} DESC", sort_by));
+            }
+        }
+        sql.push_str(&format!(" LIMIT ${} OFFSET ${}", limit_index, offset_index));
+        let mut query = query_as::<_, Series>(&sql);
+        debug!("query sql: {}", sql);
+        for (_col, val) in &active_filters {
+            query = query.bind(format!("%{}%", val));
+        }
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT) as i32;
+        let offset = ((params.page.unwrap_or(DEFAULT_PAGE) - 1) as i32) * limit;
+        query.bind(limit).bind(offset).fetch_all(pool).await
+    }
+
+    pub async fn delete(pool: &PgPool, series_id: i32) -> Result<Series, Error> {
+        let sql = "DELETE FROM series WHERE id = $1 RETURNING *";
+        query_as::<_, Series>(sql).bind(series_id).fetch_one(pool).await
+    }
+
+    /// Añade un post a la serie (o lo mueve desde otra). Sin posición, va al final.
+    pub async fn add_post(pool: &PgPool, membership: &SeriesMembership) -> Result<SeriesPost, Error> {
+        let sql = "INSERT INTO series_posts (series_id, post_id, position)
+            VALUES (
+                $1,
+                $2,
+                COALESCE($3, (SELECT COALESCE(MAX(position), 0) + 1 FROM series_posts WHERE series_id = $1))
+            )
+            ON CONFLICT (post_id) DO UPDATE SET
+                series_id = EXCLUDED.series_id,
+                position = EXCLUDED.position
+            RETURNING *";
+        query_as::<_, SeriesPost>(sql)
+            .bind(membership.series_id)
+            .bind(membership.post_id)
+            .bind(membership.position)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn remove_post(pool: &PgPool, post_id: i32) -> Result<SeriesPost, Error> {
+        let sql = "DELETE FROM series_posts WHERE post_id = $1 RETURNING *";
+        query_as::<_, SeriesPost>(sql).bind(post_id).fetch_one(pool).await
+    }
+
+    /// Reordena los posts de la serie según el orden de `post_ids`.
+    pub async fn reorder(pool: &PgPool, order: &SeriesOrder) -> Result<Vec<SeriesPost>, Error> {
+        let sql = "UPDATE series_posts sp
+            SET position = o.position
+            FROM UNNEST($2::INTEGER[]) WITH ORDINALITY AS o(post_id, position)
+            WHERE sp.series_id = $1 AND sp.post_id = o.post_id
+            RETURNING sp.*";
+        let mut posts = query_as::<_, SeriesPost>(sql)
+            .bind(order.series_id)
+            .bind(&order.post_ids as &[i32])
+            .fetch_all(pool)
+            .await?;
+        posts.sort_by_key(|p| p.position);
+        Ok(posts)
+    }
+
+    /// Posts de la serie en orden. Con `only_public` solo los publicados,
+    /// como en [`Post::is_public`]: ni privados, ni borradores, ni
+    /// programados.
+    pub async fn read_posts(pool: &PgPool, series_id: i32, only_public: bool) -> Result<Vec<Post>, Error> {
+        let sql = "SELECT p.* FROM posts p
+            INNER JOIN series_posts sp ON p.id = sp.post_id
+            WHERE sp.series_id = $1
+            AND ($2 = FALSE OR (
+                COALESCE(p.private, FALSE) = FALSE
+                AND p.published_at IS NOT NULL
+                AND p.published_at <= NOW()
+            ))
+            ORDER BY sp.position ASC, p.published_at ASC";
+        query_as::<_, Post>(sql)
+            .bind(series_id)
+            .bind(only_public)
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn read_for_post(pool: &PgPool, post_id: i32) -> Result<Option<Series>, Error> {
+        let sql = "SELECT s.* FROM series s
+            INNER JOIN series_posts sp ON s.id = sp.series_id
+            WHERE sp.post_id = $1";
+        query_as::<_, Series>(sql).bind(post_id).fetch_optional(pool).await
+    }
+
+    /// Calcula la navegación de un post dentro de su serie, si pertenece a
+    /// alguna. Solo cuentan los posts publicados y el propio post.
+    pub async fn navigation(pool: &PgPool, post_id: i32) -> Result<Option<SeriesNavigation>, Error> {
+        let Some(series) = Series::read_for_post(pool, post_id).await? else {
+            return Ok(None);
+        };
+        let sql = "SELECT p.id, p.title, p.slug, sp.position FROM posts p
+            INNER JOIN series_posts sp ON p.id = sp.post_id
+            WHERE sp.series_id = $1
+            AND (p.id = $2 OR (
+                COALESCE(p.private, FALSE) = FALSE
+                AND p.published_at IS NOT NULL
+                AND p.published_at <= NOW()
+            ))
+            ORDER BY sp.position ASC, p.published_at ASC";
+        let links = query_as::<_, SeriesPostLink>(sql)
+            .bind(series.id)
+            .bind(post_id)
+            .fetch_all(pool)
+            .await?;
+        let Some(index) = links.iter().position(|link| link.id == post_id) else {
+            return Ok(None);
+        };
+        Ok(Some(SeriesNavigation {
+            part: index + 1,
+            total: links.len(),
+            previous: index.checked_sub(1).and_then(|i| links.get(i).cloned()),
+            next: links.get(index + 1).cloned(),
+            series,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::var;
+
+    use super::*;
+
+    async fn insert_post(pool: &PgPool, slug: &str, published_at: Option<DateTime<Utc>>, position: i32, series_id: i32) -> i32 {
+        let sql = "INSERT INTO posts (title, slug, content, markdown, private, published_at)
+            VALUES ($1, $1, 'x', 'x', FALSE, $2) RETURNING id";
+        let id: i32 = query(sql)
+            .bind(slug)
+            .bind(published_at)
+            .map(|row: PgRow| row.get("id"))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        query("INSERT INTO series_posts (series_id, post_id, position) VALUES ($1, $2, $3)")
+            .bind(series_id)
+            .bind(id)
+            .bind(position)
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    /// Necesita una base de datos con las migraciones en `DATABASE_URL`;
+    /// sin ella no se ejecuta.
+    #[tokio::test]
+    async fn unpublished_posts_stay_out_of_navigation() {
+        let Ok(url) = var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping");
+            return;
+        };
+        let pool = PgPool::connect(&url).await.unwrap();
+        let prefix = format!("test-series-{}", uuid::Uuid::new_v4());
+        let series_id: i32 = query("INSERT INTO series (title, slug) VALUES ($1, $1) RETURNING id")
+            .bind(&prefix)
+            .map(|row: PgRow| row.get("id"))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let first = insert_post(&pool, &format!("{}-1", prefix), Some(now - chrono::Duration::days(2)), 1, series_id).await;
+        let scheduled = insert_post(&pool, &format!("{}-2", prefix), Some(now + chrono::Duration::days(2)), 2, series_id).await;
+        let last = insert_post(&pool, &format!("{}-3", prefix), Some(now - chrono::Duration::days(1)), 3, series_id).await;
+        let draft = insert_post(&pool, &format!("{}-4", prefix), None, 4, series_id).await;
+
+        let first_nav = Series::navigation(&pool, first).await.unwrap().unwrap();
+        let last_nav = Series::navigation(&pool, last).await.unwrap().unwrap();
+        let scheduled_nav = Series::navigation(&pool, scheduled).await.unwrap().unwrap();
+        let public: Vec<i32> = Series::read_posts(&pool, series_id, true)
+            .await
+            .unwrap()
+            .iter()
+            .map(|post| post.id)
+            .collect();
+        let all = Series::read_posts(&pool, series_id, false).await.unwrap().len();
+
+        let ids = [first, scheduled, last, draft];
+        query("DELETE FROM series_posts WHERE series_id = $1").bind(series_id).execute(&pool).await.unwrap();
+        query("DELETE FROM posts WHERE id = ANY($1)").bind(&ids[..]).execute(&pool).await.unwrap();
+        query("DELETE FROM series WHERE id = $1").bind(series_id).execute(&pool).await.unwrap();
+
+        assert_eq!((first_nav.part, first_nav.total), (1, 2));
+        assert!(first_nav.previous.is_none());
+        assert_eq!(first_nav.next.map(|link| link.id), Some(last));
+        assert_eq!((last_nav.part, last_nav.total), (2, 2));
+        assert_eq!(last_nav.previous.map(|link| link.id), Some(first));
+        assert!(last_nav.next.is_none());
+        // El propio post programado sí ve su sitio, para la vista previa
+        assert_eq!((scheduled_nav.part, scheduled_nav.total), (2, 3));
+        assert_eq!(public, vec![first, last]);
+        assert_eq!(all, 4);
+    }
+}
@@ -65,6 +65,16 @@ impl Setting {
             .await
     }
 
+    // =================================================================
+    // R: READ (Valor de una configuración o el valor por defecto)
+    // =================================================================
+    pub async fn get_value(pool: &PgPool, key: &str, default: &str) -> String {
+        match Self::find_by_key(pool, key).await {
+            Ok(Some(setting)) => setting.value,
+            _ => default.to_string(),
+        }
+    }
+
     // =================================================================
     // U: UPDATE (Actualizar una configuración existente)
     // =================================================================
@@ -96,6 +106,7 @@ impl Setting {
         RETURNING *
         "#;
         sqlx::query_as::<_, Setting>(sql)
+            .bind(key)
             .fetch_one(pool)
             .await
     }
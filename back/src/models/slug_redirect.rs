@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Error, FromRow,
+    postgres::{PgConnection, PgPool},
+    query, query_as,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct SlugRedirect {
+    pub id: i32,
+    pub slug: String,
+    pub post_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SlugRedirect {
+    /// Registra un slug antiguo apuntando al post indicado. Va en la misma
+    /// transacción que el cambio de slug del post.
+    pub async fn create(conn: &mut PgConnection, slug: &str, post_id: i32) -> Result<SlugRedirect, Error> {
+        let sql = "INSERT INTO slug_redirects (slug, post_id)
+            VALUES ($1, $2)
+            ON CONFLICT (slug) DO UPDATE SET post_id = EXCLUDED.post_id
+            RETURNING *";
+        query_as::<_, SlugRedirect>(sql)
+            .bind(slug)
+            .bind(post_id)
+            .fetch_one(conn)
+            .await
+    }
+
+    pub async fn read_by_slug(pool: &PgPool, slug: &str) -> Result<Option<SlugRedirect>, Error> {
+        let sql = "SELECT * FROM slug_redirects WHERE slug = $1";
+        query_as::<_, SlugRedirect>(sql)
+            .bind(slug)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn read_for_post(pool: &PgPool, post_id: i32) -> Result<Vec<SlugRedirect>, Error> {
+        let sql = "SELECT * FROM slug_redirects WHERE post_id = $1 ORDER BY created_at DESC";
+        query_as::<_, SlugRedirect>(sql)
+            .bind(post_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn delete_by_slug(conn: &mut PgConnection, slug: &str) -> Result<(), Error> {
+        let sql = "DELETE FROM slug_redirects WHERE slug = $1";
+        query(sql).bind(slug).execute(conn).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Error, FromRow,
+    postgres::PgPool,
+    query, query_as,
+};
+
+use crate::constants::{STATS_DEFAULT_DAYS, STATS_TOP_LIMIT};
+
+#[derive(Debug, Deserialize)]
+pub struct NewView {
+    pub post_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewReaction {
+    pub post_id: i32,
+    pub reaction: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadReactionsParams {
+    pub post_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadStatsParams {
+    pub post_id: Option<i32>,
+    pub days: Option<i32>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct ReactionCount {
+    pub reaction: String,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct DailyViews {
+    pub day: NaiveDate,
+    pub views: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct DailyReactions {
+    pub day: NaiveDate,
+    pub reaction: String,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct TopPost {
+    pub post_id: i32,
+    pub title: String,
+    pub slug: String,
+    pub views: i64,
+    pub reactions: i64,
+}
+
+/// Series diarias para las gráficas del panel.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailyStats {
+    pub views: Vec<DailyViews>,
+    pub reactions: Vec<DailyReactions>,
+}
+
+/// Visitas y reacciones por post, agregadas por día. No se guardan IPs ni
+/// cookies: solo un hash del visitante con sal diaria que se borra al día
+/// siguiente.
+pub struct PostStats;
+
+impl PostStats {
+    /// Cuenta la visita si el visitante no ha visto hoy el post. Devuelve
+    /// `true` si se ha contado.
+    pub async fn record_view(pool: &PgPool, post_id: i32, visitor_hash: &str) -> Result<bool, Error> {
+        let sql = "WITH visitor AS (
+                INSERT INTO post_visitors (post_id, action, visitor_hash)
+                VALUES ($1, 'view', $2)
+                ON CONFLICT DO NOTHING
+                RETURNING post_id, day
+            )
+            INSERT INTO post_views_daily (post_id, day, views)
+            SELECT post_id, day, 1 FROM visitor
+            ON CONFLICT (post_id, day) DO UPDATE SET
+                views = post_views_daily.views + 1";
+        let result = query(sql).bind(post_id).bind(visitor_hash).execute(pool).await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Suma la reacción salvo que el visitante ya la haya dado hoy.
+    pub async fn record_reaction(
+        pool: &PgPool,
+        post_id: i32,
+        reaction: &str,
+        visitor_hash: &str,
+    ) -> Result<bool, Error> {
+        let sql = "WITH visitor AS (
+                INSERT INTO post_visitors (post_id, action, visitor_hash)
+                VALUES ($1, 'reaction:' || $2, $3)
+                ON CONFLICT DO NOTHING
+                RETURNING post_id, day
+            )
+            INSERT INTO post_reactions_daily (post_id, day, reaction, total)
+            SELECT post_id, day, $2, 1 FROM visitor
+            ON CONFLICT (post_id, day, reaction) DO UPDATE SET
+                total = post_reactions_daily.total + 1";
+        let result = query(sql)
+            .bind(post_id)
+            .bind(reaction)
+            .bind(visitor_hash)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn reactions(pool: &PgPool, post_id: i32) -> Result<Vec<ReactionCount>, Error> {
+        let sql = "SELECT reaction, SUM(total)::BIGINT total FROM post_reactions_daily
+            WHERE post_id = $1
+            GROUP BY reaction
+            ORDER BY total DESC, reaction";
+        query_as::<_, ReactionCount>(sql).bind(post_id).fetch_all(pool).await
+    }
+
+    pub async fn views_for_posts(pool: &PgPool, post_ids: &[i32]) -> Result<HashMap<i32, i64>, Error> {
+        let sql = "SELECT post_id, SUM(views)::BIGINT total FROM post_views_daily
+            WHERE post_id = ANY($1)
+            GROUP BY post_id";
+        let rows: Vec<(i32, i64)> = query_as(sql).bind(post_ids).fetch_all(pool).await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Visitas y reacciones de los últimos días, de un post o de todo el
+    /// sitio. Los días sin actividad no aparecen.
+    pub async fn daily(pool: &PgPool, params: &ReadStatsParams) -> Result<DailyStats, Error> {
+        let days = params.days.unwrap_or(STATS_DEFAULT_DAYS);
+        let sql = "SELECT day, SUM(views)::BIGINT views FROM post_views_daily
+            WHERE day > CURRENT_DATE - $1
+            AND ($2::INTEGER IS NULL OR post_id = $2)
+            GROUP BY day
+            ORDER BY day";
+        let views = query_as::<_, DailyViews>(sql)
+            .bind(days)
+            .bind(params.post_id)
+            .fetch_all(pool)
+            .await?;
+        let sql = "SELECT day, reaction, SUM(total)::BIGINT total FROM post_reactions_daily
+            WHERE day > CURRENT_DATE - $1
+            AND ($2::INTEGER IS NULL OR post_id = $2)
+            GROUP BY day, reaction
+            ORDER BY day, reaction";
+        let reactions = query_as::<_, DailyReactions>(sql)
+            .bind(days)
+            .bind(params.post_id)
+            .fetch_all(pool)
+            .await?;
+        Ok(DailyStats { views, reactions })
+    }
+
+    /// Posts más vistos en los últimos días.
+    pub async fn top(pool: &PgPool, params: &ReadStatsParams) -> Result<Vec<TopPost>, Error> {
+        let sql = "SELECT p.id post_id, p.title, p.slug,
+                COALESCE(v.views, 0)::BIGINT views,
+                COALESCE(r.reactions, 0)::BIGINT reactions
+            FROM posts p
+            LEFT JOIN (
+                SELECT post_id, SUM(views) views FROM post_views_daily
+                WHERE day > CURRENT_DATE - $1 GROUP BY post_id
+            ) v ON v.post_id = p.id
+            LEFT JOIN (
+                SELECT post_id, SUM(total) reactions FROM post_reactions_daily
+                WHERE day > CURRENT_DATE - $1 GROUP BY post_id
+            ) r ON r.post_id = p.id
+            WHERE v.views IS NOT NULL OR r.reactions IS NOT NULL
+            ORDER BY views DESC, reactions DESC
+            LIMIT $2";
+        query_as::<_, TopPost>(sql)
+            .bind(params.days.unwrap_or(STATS_DEFAULT_DAYS))
+            .bind(params.limit.unwrap_or(STATS_TOP_LIMIT))
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Borra los hashes de visitantes de días anteriores.
+    pub async fn purge_visitors(pool: &PgPool) -> Result<u64, Error> {
+        let sql = "DELETE FROM post_visitors WHERE day < CURRENT_DATE";
+        let result = query(sql).execute(pool).await?;
+        Ok(result.rows_affected())
+    }
+}
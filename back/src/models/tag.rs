@@ -111,6 +111,16 @@ impl Tag {
         query_as::<_, Tag>(sql).bind(post_id).fetch_one(pool).await
     }
 
+    /// Primera etiqueta asignada al post, la que sale en su tarjeta.
+    pub async fn read_first_for_post(pool: &PgPool, post_id: i32) -> Result<Option<Tag>, Error> {
+        let sql = "SELECT t.* FROM tags t
+            INNER JOIN posts_tags pt ON t.id = pt.tag_id
+            WHERE pt.post_id = $1
+            ORDER BY pt.id
+            LIMIT 1";
+        query_as::<_, Tag>(sql).bind(post_id).fetch_optional(pool).await
+    }
+
     pub async fn delele_relations_for_post(pool: &PgPool, post_id: i32) -> Result<(), Error> {
         let sql = "DELETE FROM post_tags WHERE post_id = $1";
         query(sql).bind(post_id).execute(pool).await?;
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, FromRow, postgres::PgPool, query_as};
+
+/// Datos de una subida reanudable recién creada.
+#[derive(Debug, Clone)]
+pub struct NewUploadSession {
+    pub id: String,
+    pub filename: Option<String>,
+    pub length: i64,
+    pub uploader_id: Option<i32>,
+}
+
+/// Subida reanudable en curso. Los bytes recibidos están en un fichero
+/// temporal; lo que ya ha llegado es su tamaño. `mime_type` se rellena al
+/// comprobar los primeros bytes y `media_id` al terminar.
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct UploadSession {
+    pub id: String,
+    pub filename: Option<String>,
+    pub length: i64,
+    pub mime_type: Option<String>,
+    pub uploader_id: Option<i32>,
+    pub media_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    pub async fn create(pool: &PgPool, session: &NewUploadSession) -> Result<UploadSession, Error> {
+        let sql = "INSERT INTO upload_sessions (id, filename, length, uploader_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *";
+        query_as::<_, UploadSession>(sql)
+            .bind(&session.id)
+            .bind(&session.filename)
+            .bind(session.length)
+            .bind(session.uploader_id)
+            .fetch_one(pool)
+            .await
+    }
+
+    pub async fn read(pool: &PgPool, id: &str) -> Result<Option<UploadSession>, Error> {
+        let sql = "SELECT * FROM upload_sessions WHERE id = $1";
+        query_as::<_, UploadSession>(sql).bind(id).fetch_optional(pool).await
+    }
+
+    /// Marca actividad en la subida (y guarda el tipo si ya se conoce) para
+    /// que no caduque mientras sigan llegando trozos.
+    pub async fn touch(pool: &PgPool, id: &str, mime_type: Option<&str>) -> Result<UploadSession, Error> {
+        let sql = "UPDATE upload_sessions
+            SET mime_type = COALESCE($2, mime_type), updated_at = NOW()
+            WHERE id = $1
+            RETURNING *";
+        query_as::<_, UploadSession>(sql).bind(id).bind(mime_type).fetch_one(pool).await
+    }
+
+    pub async fn complete(pool: &PgPool, id: &str, media_id: i32) -> Result<UploadSession, Error> {
+        let sql = "UPDATE upload_sessions SET media_id = $2 WHERE id = $1 RETURNING *";
+        query_as::<_, UploadSession>(sql).bind(id).bind(media_id).fetch_one(pool).await
+    }
+
+    pub async fn delete(pool: &PgPool, id: &str) -> Result<UploadSession, Error> {
+        let sql = "DELETE FROM upload_sessions WHERE id = $1 RETURNING *";
+        query_as::<_, UploadSession>(sql).bind(id).fetch_one(pool).await
+    }
+
+    /// Borra las subidas sin actividad en las últimas `hours` horas,
+    /// terminadas o no.
+    pub async fn delete_expired(pool: &PgPool, hours: i64) -> Result<Vec<UploadSession>, Error> {
+        let sql = "DELETE FROM upload_sessions
+            WHERE updated_at < NOW() - make_interval(hours => $1::INT)
+            RETURNING *";
+        query_as::<_, UploadSession>(sql).bind(hours).fetch_all(pool).await
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 use chrono::{DateTime, Utc};
+use slug::slugify;
 use sqlx::{postgres::{PgPool, PgRow}, query, Row, Error};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -10,6 +12,10 @@ pub struct User{
     pub hashed_password: String,
     pub role: String,
     pub active: bool,
+    pub slug: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub social_links: Value,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
 }
@@ -23,6 +29,12 @@ pub struct TokenClaims {
     pub exp: usize,
 }
 
+impl TokenClaims {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UserSchema {
     pub email: String,
@@ -37,6 +49,20 @@ pub struct UserRegister {
     pub role: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct UserProfile {
+    pub slug: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+    pub social_links: Option<Value>,
+}
+
+/// Perfil de otro usuario; solo para administradores.
+#[derive(Debug, Deserialize)]
+pub struct ProfileParams {
+    pub user_id: Option<i32>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FilteredUser {
     pub id: i32,
@@ -57,6 +83,10 @@ impl User{
             hashed_password: row.get("hashed_password"),
             role: row.get("role"),
             active: row.get("active"),
+            slug: row.get("slug"),
+            bio: row.get("bio"),
+            avatar_url: row.get("avatar_url"),
+            social_links: row.get("social_links"),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         }
@@ -64,18 +94,90 @@ impl User{
 
     pub async fn create(pool: &PgPool, username: &str, email: &str, password: &str, role: &str) -> Result<User, Error> {
         let hashed_password = bcrypt::hash(password, bcrypt::DEFAULT_COST).unwrap();
+        let slug = Self::unique_slug(pool, &slugify(username), None).await?;
 
-        let sql = "INSERT INTO users (username, email, hashed_password, role) VALUES ($1, $2, $3, $4) RETURNING *";
+        let sql = "INSERT INTO users (username, email, hashed_password, role, slug) VALUES ($1, $2, $3, $4, $5) RETURNING *";
         query(sql)
             .bind(username)
             .bind(email)
             .bind(hashed_password)
             .bind(role)
+            .bind(slug)
+            .map(Self::from_row)
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Emails a los que avisar de la actividad de un post: sus autores o,
+    /// si no tiene, los administradores.
+    pub async fn notification_emails(pool: &PgPool, post_id: i32) -> Result<Vec<String>, Error> {
+        let sql = "SELECT u.email FROM users u
+            INNER JOIN posts_authors pa ON u.id = pa.user_id
+            WHERE pa.post_id = $1 AND u.active
+            UNION
+            SELECT email FROM users
+            WHERE role = 'admin' AND active
+            AND NOT EXISTS (SELECT 1 FROM posts_authors WHERE post_id = $1)";
+        query(sql)
+            .bind(post_id)
+            .map(|row: PgRow| row.get("email"))
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn read(pool: &PgPool, id: i32) -> Result<User, Error> {
+        let sql = "SELECT * FROM users WHERE id = $1";
+        query(sql)
+            .bind(id)
             .map(Self::from_row)
             .fetch_one(pool)
             .await
     }
 
+    /// Actualiza los datos públicos del autor (bio, avatar, redes sociales)
+    pub async fn update_profile(pool: &PgPool, user_id: i32, profile: &UserProfile) -> Result<User, Error> {
+        let current = Self::read(pool, user_id).await?;
+        let slug = match profile.slug.as_ref().map(slugify) {
+            Some(slug) if !slug.is_empty() && Some(&slug) != current.slug.as_ref() => {
+                Some(Self::unique_slug(pool, &slug, Some(current.id)).await?)
+            }
+            _ => current.slug,
+        };
+        let sql = "UPDATE users SET
+                slug = $1,
+                bio = $2,
+                avatar_url = $3,
+                social_links = $4
+            WHERE id = $5
+            RETURNING *";
+        query(sql)
+            .bind(slug)
+            .bind(profile.bio.as_ref().or(current.bio.as_ref()))
+            .bind(profile.avatar_url.as_ref().or(current.avatar_url.as_ref()))
+            .bind(profile.social_links.as_ref().unwrap_or(&current.social_links))
+            .bind(user_id)
+            .map(Self::from_row)
+            .fetch_one(pool)
+            .await
+    }
+
+    async fn unique_slug(pool: &PgPool, slug: &str, user_id: Option<i32>) -> Result<String, Error> {
+        let sql = "SELECT slug FROM users WHERE (slug = $1 OR slug LIKE $1 || '-%') AND id <> $2";
+        let taken: Vec<String> = query(sql)
+            .bind(slug)
+            .bind(user_id.unwrap_or(0))
+            .map(|row: PgRow| row.get("slug"))
+            .fetch_all(pool)
+            .await?;
+        if !taken.iter().any(|t| t == slug) {
+            return Ok(slug.to_string());
+        }
+        let suffix = (2..)
+            .find(|n| !taken.contains(&format!("{}-{}", slug, n)))
+            .unwrap_or_default();
+        Ok(format!("{}-{}", slug, suffix))
+    }
+
     pub async fn get_by_email(pool: &PgPool, email: &str) -> Result<User, Error>{
         let sql = "SELECT * FROM users WHERE email = $1";
         query(sql)
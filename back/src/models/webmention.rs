@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Error, FromRow,
+    postgres::PgPool,
+    query_as,
+};
+
+use crate::constants::{WEBMENTION_MAX_ATTEMPTS, WEBMENTION_RETRY_MINUTES};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum WebmentionDirection {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+pub enum WebmentionStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+/// Webmention recibida (pendiente de verificar) o por enviar.
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct Webmention {
+    pub id: i32,
+    pub direction: WebmentionDirection,
+    pub source: String,
+    pub target: String,
+    pub status: WebmentionStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadWebmentionParams {
+    pub direction: Option<WebmentionDirection>,
+    pub status: Option<WebmentionStatus>,
+    pub limit: Option<i64>,
+}
+
+impl Webmention {
+    /// Encola la webmention; si ya existía vuelve a dejarla pendiente.
+    pub async fn enqueue(
+        pool: &PgPool,
+        direction: WebmentionDirection,
+        source: &str,
+        target: &str,
+    ) -> Result<Webmention, Error> {
+        let sql = "INSERT INTO webmentions (direction, source, target)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (direction, source, target) DO UPDATE SET
+                status = 'pending',
+                attempts = 0,
+                next_attempt_at = CURRENT_TIMESTAMP,
+                last_error = NULL
+            RETURNING *";
+        query_as::<_, Webmention>(sql)
+            .bind(direction)
+            .bind(source)
+            .bind(target)
+            .fetch_one(pool)
+            .await
+    }
+
+    /// Pendientes cuyo siguiente intento ya ha llegado.
+    pub async fn read_due(pool: &PgPool, limit: i64) -> Result<Vec<Webmention>, Error> {
+        let sql = "SELECT * FROM webmentions
+            WHERE status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+            ORDER BY next_attempt_at ASC
+            LIMIT $1";
+        query_as::<_, Webmention>(sql).bind(limit).fetch_all(pool).await
+    }
+
+    pub async fn read(pool: &PgPool, params: &ReadWebmentionParams) -> Result<Vec<Webmention>, Error> {
+        let sql = "SELECT * FROM webmentions
+            WHERE ($1::VARCHAR IS NULL OR direction = $1)
+            AND ($2::VARCHAR IS NULL OR status = $2)
+            ORDER BY updated_at DESC
+            LIMIT $3";
+        query_as::<_, Webmention>(sql)
+            .bind(params.direction)
+            .bind(params.status)
+            .bind(params.limit.unwrap_or(100))
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn mark_done(pool: &PgPool, id: i32) -> Result<Webmention, Error> {
+        let sql = "UPDATE webmentions SET
+                status = 'done',
+                attempts = attempts + 1,
+                last_error = NULL
+            WHERE id = $1
+            RETURNING *";
+        query_as::<_, Webmention>(sql).bind(id).fetch_one(pool).await
+    }
+
+    pub async fn mark_failed(pool: &PgPool, id: i32, error: &str) -> Result<Webmention, Error> {
+        let sql = "UPDATE webmentions SET
+                status = 'failed',
+                attempts = attempts + 1,
+                last_error = $2
+            WHERE id = $1
+            RETURNING *";
+        query_as::<_, Webmention>(sql).bind(id).bind(error).fetch_one(pool).await
+    }
+
+    /// Programa un nuevo intento con espera exponencial o la da por
+    /// fallida si ha agotado los intentos.
+    pub async fn retry(pool: &PgPool, id: i32, error: &str) -> Result<Webmention, Error> {
+        let sql = "UPDATE webmentions SET
+                attempts = attempts + 1,
+                status = CASE WHEN attempts + 1 >= $3 THEN 'failed' ELSE 'pending' END,
+                next_attempt_at = CURRENT_TIMESTAMP
+                    + make_interval(mins => $4 * POWER(2, attempts)::INTEGER),
+                last_error = $2
+            WHERE id = $1
+            RETURNING *";
+        query_as::<_, Webmention>(sql)
+            .bind(id)
+            .bind(error)
+            .bind(WEBMENTION_MAX_ATTEMPTS)
+            .bind(WEBMENTION_RETRY_MINUTES)
+            .fetch_one(pool)
+            .await
+    }
+}
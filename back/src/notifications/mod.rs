@@ -0,0 +1,192 @@
+mod transport;
+
+use std::env::var;
+
+use lettre::{
+    Message,
+    message::{
+        Mailbox,
+        header::{ContentType, HeaderName, HeaderValue},
+    },
+};
+use minijinja::{Value, context};
+use tracing::{debug, error, info};
+
+use crate::constants::DEFAULT_SITE_TITLE;
+use crate::models::{AppState, Comment, CommentStatus, Post, Setting, User};
+
+pub use transport::{
+    FileTransport, MailError, MailTransport, MaildirTransport, NoopTransport, SmtpTransport,
+};
+
+/// Avisos por correo de la actividad de los comentarios.
+pub struct Notifier {
+    transport: Box<dyn MailTransport>,
+    from: Mailbox,
+}
+
+impl Notifier {
+    pub fn new(transport: impl MailTransport + 'static, from: Mailbox) -> Self {
+        Notifier {
+            transport: Box::new(transport),
+            from,
+        }
+    }
+
+    /// Configura el transporte a partir de `MAIL_TRANSPORT` (`smtp`, `file`,
+    /// `maildir` o nada) y el remitente con `MAIL_FROM`.
+    pub fn from_env() -> Self {
+        let from: Mailbox = var("MAIL_FROM")
+            .unwrap_or("Blog <blog@localhost>".to_string())
+            .parse()
+            .unwrap_or_else(|e| {
+                error!("Invalid MAIL_FROM: {:?}", e);
+                "blog@localhost".parse().unwrap()
+            });
+        let transport = var("MAIL_TRANSPORT").unwrap_or_default();
+        info!("Mail transport: {}", transport);
+        let result: Result<Notifier, MailError> = match transport.as_str() {
+            "smtp" => SmtpTransport::from_env().map(|t| Notifier::new(t, from.clone())),
+            "file" => FileTransport::new(var("MAIL_DIR").unwrap_or("mail".to_string()).into())
+                .map(|t| Notifier::new(t, from.clone())),
+            "maildir" => MaildirTransport::new(var("MAIL_DIR").unwrap_or("mail".to_string()).into())
+                .map(|t| Notifier::new(t, from.clone())),
+            _ => Ok(Notifier::new(NoopTransport, from.clone())),
+        };
+        result.unwrap_or_else(|e| {
+            error!("Error configuring mail transport: {:?}", e);
+            Notifier::new(NoopTransport, from)
+        })
+    }
+
+    /// Avisa a los autores del post de un comentario pendiente de moderación.
+    pub async fn comment_pending(&self, app_state: &AppState, comment: &Comment) {
+        if comment.status != CommentStatus::Pending {
+            return;
+        }
+        let Ok(post) = Post::read(&app_state.pool, comment.post_id).await else {
+            return;
+        };
+        let emails = match User::notification_emails(&app_state.pool, post.id).await {
+            Ok(emails) => emails,
+            Err(e) => {
+                error!("Error reading notification emails: {:?}", e);
+                return;
+            }
+        };
+        let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+        let ctx = context! {
+            comment => Value::from_serialize(comment),
+            post => Value::from_serialize(&post),
+            site_title => site_title,
+            base_url => app_state.base_url,
+        };
+        let Some(body) = render(app_state, "email/comment_pending.txt", ctx) else {
+            return;
+        };
+        let subject = format!("Comentario pendiente en «{}»", post.title);
+        for email in emails {
+            self.send(&email, &subject, &body, None).await;
+        }
+    }
+
+    /// Cuando se publica una respuesta, avisa al autor del comentario al que
+    /// responde si lo pidió. Cada respuesta se notifica una sola vez.
+    pub async fn comment_approved(&self, app_state: &AppState, comment: &Comment) {
+        if comment.status != CommentStatus::Approved || comment.reply_notified {
+            return;
+        }
+        let Some(parent_id) = comment.parent_id else {
+            return;
+        };
+        let Ok(parent) = Comment::read(&app_state.pool, parent_id).await else {
+            return;
+        };
+        let (Some(email), Some(token)) = (parent.email.as_ref(), parent.unsubscribe_token.as_ref()) else {
+            return;
+        };
+        if !parent.notify_replies
+            || parent.status != CommentStatus::Approved
+            || comment.email.as_ref().is_some_and(|e| e.eq_ignore_ascii_case(email))
+        {
+            return;
+        }
+        let Ok(post) = Post::read(&app_state.pool, comment.post_id).await else {
+            return;
+        };
+        let unsubscribe_url = format!(
+            "{}/api/v1/comments/unsubscribe?token={}",
+            app_state.base_url, token
+        );
+        let site_title = Setting::get_value(&app_state.pool, "site_title", DEFAULT_SITE_TITLE).await;
+        let ctx = context! {
+            comment => Value::from_serialize(comment),
+            parent => Value::from_serialize(&parent),
+            post => Value::from_serialize(&post),
+            unsubscribe_url => unsubscribe_url,
+            site_title => site_title,
+            base_url => app_state.base_url,
+        };
+        let Some(body) = render(app_state, "email/comment_reply.txt", ctx) else {
+            return;
+        };
+        let subject = format!("Nueva respuesta en «{}»", post.title);
+        if self.send(email, &subject, &body, Some(&unsubscribe_url)).await
+            && let Err(e) = Comment::mark_reply_notified(&app_state.pool, comment.id).await
+        {
+            error!("Error marking comment {} as notified: {:?}", comment.id, e);
+        }
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: &str, unsubscribe_url: Option<&str>) -> bool {
+        let to: Mailbox = match to.parse() {
+            Ok(to) => to,
+            Err(e) => {
+                error!("Invalid recipient '{}': {:?}", to, e);
+                return false;
+            }
+        };
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        if let Some(url) = unsubscribe_url {
+            builder = builder
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                    format!("<{}>", url),
+                ))
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                    "List-Unsubscribe=One-Click".to_string(),
+                ));
+        }
+        let message = match builder.body(body.to_string()) {
+            Ok(message) => message,
+            Err(e) => {
+                error!("Error building mail: {:?}", e);
+                return false;
+            }
+        };
+        match self.transport.send(message).await {
+            Ok(_) => {
+                debug!("Mail sent: {}", subject);
+                true
+            }
+            Err(e) => {
+                error!("Error sending mail: {:?}", e);
+                false
+            }
+        }
+    }
+}
+
+fn render(app_state: &AppState, name: &str, ctx: Value) -> Option<String> {
+    app_state
+        .templates
+        .get_template(name)
+        .and_then(|template| template.render(ctx))
+        .map_err(|e| error!("Error rendering {}: {:?}", name, e))
+        .ok()
+}
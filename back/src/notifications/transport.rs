@@ -0,0 +1,119 @@
+use std::{env::var, path::PathBuf};
+
+use async_trait::async_trait;
+use lettre::{
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    transport::smtp::authentication::Credentials,
+};
+use tracing::info;
+
+pub type MailError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Forma de entregar los correos. Se elige con `MAIL_TRANSPORT`.
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, message: Message) -> Result<(), MailError>;
+}
+
+/// Envío por SMTP (`SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`,
+/// `SMTP_PASSWORD` y `SMTP_TLS` = `starttls`, `tls` o `none`).
+pub struct SmtpTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn from_env() -> Result<Self, MailError> {
+        let host = var("SMTP_HOST").map_err(|_| "SMTP_HOST environment mandatory")?;
+        let tls = var("SMTP_TLS").unwrap_or("starttls".to_string());
+        let mut builder = match tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+        };
+        if let Ok(port) = var("SMTP_PORT") {
+            builder = builder.port(port.parse()?);
+        }
+        if let (Ok(username), Ok(password)) = (var("SMTP_USERNAME"), var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(SmtpTransport {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpTransport {
+    async fn send(&self, message: Message) -> Result<(), MailError> {
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Guarda cada correo como un fichero `.eml` en `MAIL_DIR`, útil para
+/// probar en local sin servidor de correo.
+pub struct FileTransport {
+    transport: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileTransport {
+    pub fn new(dir: PathBuf) -> Result<Self, MailError> {
+        std::fs::create_dir_all(&dir)?;
+        info!("Mail saved in {}", dir.display());
+        Ok(FileTransport {
+            transport: AsyncFileTransport::new(dir),
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileTransport {
+    async fn send(&self, message: Message) -> Result<(), MailError> {
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Entrega cada correo en un buzón Maildir (`tmp`, `new` y `cur` dentro de
+/// `MAIL_DIR`) que se puede abrir con cualquier cliente de correo.
+pub struct MaildirTransport {
+    dir: PathBuf,
+}
+
+impl MaildirTransport {
+    pub fn new(dir: PathBuf) -> Result<Self, MailError> {
+        for subdir in ["tmp", "new", "cur"] {
+            std::fs::create_dir_all(dir.join(subdir))?;
+        }
+        info!("Mail delivered to maildir {}", dir.display());
+        Ok(MaildirTransport { dir })
+    }
+}
+
+#[async_trait]
+impl MailTransport for MaildirTransport {
+    async fn send(&self, message: Message) -> Result<(), MailError> {
+        // Se escribe en `tmp` y se mueve a `new` para que un lector nunca
+        // vea un correo a medias
+        let name = format!(
+            "{}.{}.blog",
+            chrono::Utc::now().timestamp(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let tmp = self.dir.join("tmp").join(&name);
+        tokio::fs::write(&tmp, message.formatted()).await?;
+        tokio::fs::rename(&tmp, self.dir.join("new").join(&name)).await?;
+        Ok(())
+    }
+}
+
+/// No envía nada; es el transporte por defecto.
+pub struct NoopTransport;
+
+#[async_trait]
+impl MailTransport for NoopTransport {
+    async fn send(&self, message: Message) -> Result<(), MailError> {
+        info!("Mail not sent (no transport): {:?}", message.headers().get_raw("Subject"));
+        Ok(())
+    }
+}
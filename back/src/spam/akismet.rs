@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sqlx::postgres::PgPool;
+use tracing::{debug, error};
+
+use super::{SpamContext, SpamFilter, SpamScore};
+use crate::models::{Comment, Setting};
+
+const DEFAULT_AKISMET_URL: &str = "https://rest.akismet.com/1.1";
+
+/// Cliente de la API de Akismet (o de cualquier servicio compatible).
+/// Solo se activa si existe el ajuste `akismet_key`; `akismet_url` permite
+/// apuntarlo a otro servidor, por ejemplo un stub local.
+pub struct Akismet {
+    client: reqwest::Client,
+    blog: String,
+}
+
+impl Akismet {
+    pub fn new(base_url: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+        Akismet {
+            client,
+            blog: base_url.to_string(),
+        }
+    }
+
+    async fn config(pool: &PgPool) -> Option<(String, String)> {
+        let key = Setting::get_value(pool, "akismet_key", "").await;
+        if key.trim().is_empty() {
+            return None;
+        }
+        let url = Setting::get_value(pool, "akismet_url", DEFAULT_AKISMET_URL).await;
+        Some((url.trim_end_matches('/').to_string(), key))
+    }
+
+    async fn call(&self, url: &str, form: &[(&str, &str)]) -> Result<String, reqwest::Error> {
+        self.client
+            .post(url)
+            .form(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await
+    }
+}
+
+#[async_trait]
+impl SpamFilter for Akismet {
+    fn name(&self) -> &'static str {
+        "akismet"
+    }
+
+    async fn check(&self, pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore> {
+        let (url, key) = Akismet::config(pool).await?;
+        let comment = context.comment;
+        let form = [
+            ("api_key", key.as_str()),
+            ("blog", self.blog.as_str()),
+            ("user_ip", comment.author_ip.as_deref().unwrap_or_default()),
+            ("user_agent", comment.user_agent.as_deref().unwrap_or_default()),
+            ("referrer", context.referrer.as_deref().unwrap_or_default()),
+            ("comment_type", "comment"),
+            ("comment_author", comment.nikename.as_str()),
+            ("comment_author_email", comment.email.as_deref().unwrap_or_default()),
+            ("comment_author_url", comment.website.as_deref().unwrap_or_default()),
+            ("comment_content", comment.content.as_str()),
+        ];
+        match self.call(&format!("{}/comment-check", url), &form).await {
+            Ok(body) => {
+                debug!("Akismet response: {}", body);
+                if body.trim() == "true" {
+                    Some(SpamScore {
+                        filter: self.name().to_string(),
+                        score: 1.0,
+                        reason: "Akismet marked it as spam".to_string(),
+                    })
+                } else {
+                    None
+                }
+            }
+            Err(e) => {
+                error!("Error calling Akismet: {:?}", e);
+                None
+            }
+        }
+    }
+
+    async fn learn(&self, pool: &PgPool, comment: &Comment, spam: bool) {
+        let Some((url, key)) = Akismet::config(pool).await else {
+            return;
+        };
+        let form = [
+            ("api_key", key.as_str()),
+            ("blog", self.blog.as_str()),
+            ("user_ip", comment.author_ip.as_deref().unwrap_or_default()),
+            ("user_agent", comment.user_agent.as_deref().unwrap_or_default()),
+            ("comment_type", "comment"),
+            ("comment_author", comment.nikename.as_str()),
+            ("comment_author_email", comment.email.as_deref().unwrap_or_default()),
+            ("comment_author_url", comment.website.as_deref().unwrap_or_default()),
+            ("comment_content", comment.content.as_str()),
+        ];
+        let action = if spam { "submit-spam" } else { "submit-ham" };
+        if let Err(e) = self.call(&format!("{}/{}", url, action), &form).await {
+            error!("Error calling Akismet {}: {:?}", action, e);
+        }
+    }
+}
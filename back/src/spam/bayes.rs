@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use sqlx::{Error, postgres::PgPool, query, query_as};
+use tracing::error;
+
+use super::{SpamContext, SpamFilter, SpamScore};
+use crate::models::Comment;
+
+/// Comentarios de cada clase necesarios antes de empezar a puntuar.
+const MIN_DOCUMENTS: i32 = 5;
+/// Tokens más significativos que se combinan en la probabilidad final.
+const INTERESTING_TOKENS: usize = 15;
+
+/// Clasificador bayesiano ingenuo entrenado con las decisiones de los
+/// moderadores (aprobar o marcar como spam).
+pub struct Bayes;
+
+fn tokens(nikename: &str, content: &str) -> HashSet<String> {
+    let mut tokens: HashSet<String> = content
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '$')
+        .filter(|word| (3..=30).contains(&word.chars().count()))
+        .map(|word| word.to_string())
+        .collect();
+    if !nikename.trim().is_empty() {
+        tokens.insert(format!("name:{}", nikename.trim().to_lowercase()));
+    }
+    tokens
+}
+
+impl Bayes {
+    /// Probabilidad de spam o `None` si aún no hay entrenamiento suficiente.
+    async fn probability(pool: &PgPool, tokens: &HashSet<String>) -> Result<Option<f64>, Error> {
+        let sql = "SELECT category, total FROM spam_documents";
+        let documents: HashMap<String, i32> = query_as::<_, (String, i32)>(sql)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+        let spam_total = documents.get("spam").copied().unwrap_or(0);
+        let ham_total = documents.get("ham").copied().unwrap_or(0);
+        if spam_total < MIN_DOCUMENTS || ham_total < MIN_DOCUMENTS {
+            return Ok(None);
+        }
+        let tokens: Vec<String> = tokens.iter().cloned().collect();
+        let sql = "SELECT spam, ham FROM spam_tokens WHERE token = ANY($1) AND spam + ham > 0";
+        let counts = query_as::<_, (i32, i32)>(sql)
+            .bind(&tokens)
+            .fetch_all(pool)
+            .await?;
+        Ok(Bayes::combine(&counts, spam_total, ham_total))
+    }
+
+    /// Combina las veces que cada token apareció en spam y en comentarios
+    /// buenos en una sola probabilidad.
+    fn combine(counts: &[(i32, i32)], spam_total: i32, ham_total: i32) -> Option<f64> {
+        // Probabilidad por token con el suavizado de Robinson
+        let mut probabilities: Vec<f64> = counts
+            .iter()
+            .copied()
+            .map(|(spam, ham)| {
+                let spam_freq = spam as f64 / spam_total as f64;
+                let ham_freq = ham as f64 / ham_total as f64;
+                let p = spam_freq / (spam_freq + ham_freq);
+                let n = (spam + ham) as f64;
+                (0.5 + n * p) / (1.0 + n)
+            })
+            .collect();
+        if probabilities.is_empty() {
+            return None;
+        }
+        probabilities.sort_by(|a, b| (b - 0.5).abs().total_cmp(&(a - 0.5).abs()));
+        probabilities.truncate(INTERESTING_TOKENS);
+        let (log_spam, log_ham) = probabilities
+            .iter()
+            .map(|p| p.clamp(0.01, 0.99))
+            .fold((0.0, 0.0), |(s, h), p| (s + p.ln(), h + (1.0 - p).ln()));
+        Some(1.0 / (1.0 + (log_ham - log_spam).exp()))
+    }
+
+    /// Suma (`delta = 1`) o resta (`delta = -1`) un comentario del corpus.
+    async fn train(pool: &PgPool, comment: &Comment, category: &str, delta: i32) -> Result<(), Error> {
+        let tokens: Vec<String> = tokens(&comment.nikename, &comment.content).into_iter().collect();
+        let (spam, ham) = if category == "spam" { (delta, 0) } else { (0, delta) };
+        let sql = "INSERT INTO spam_tokens (token, spam, ham)
+            SELECT token, GREATEST($2, 0), GREATEST($3, 0) FROM UNNEST($1::VARCHAR[]) AS token
+            ON CONFLICT (token) DO UPDATE SET
+                spam = GREATEST(spam_tokens.spam + $2, 0),
+                ham = GREATEST(spam_tokens.ham + $3, 0)";
+        query(sql)
+            .bind(&tokens)
+            .bind(spam)
+            .bind(ham)
+            .execute(pool)
+            .await?;
+        let sql = "UPDATE spam_documents SET total = GREATEST(total + $1, 0) WHERE category = $2";
+        query(sql).bind(delta).bind(category).execute(pool).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SpamFilter for Bayes {
+    fn name(&self) -> &'static str {
+        "bayes"
+    }
+
+    async fn check(&self, pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore> {
+        let tokens = tokens(&context.comment.nikename, &context.comment.content);
+        match Bayes::probability(pool, &tokens).await {
+            Ok(Some(p)) if p > 0.5 => Some(SpamScore {
+                filter: self.name().to_string(),
+                score: ((p - 0.5) * 2.0) as f32,
+                reason: format!("Spam probability {:.2}", p),
+            }),
+            Ok(_) => None,
+            Err(e) => {
+                error!("Error classifying comment: {:?}", e);
+                None
+            }
+        }
+    }
+
+    async fn learn(&self, pool: &PgPool, comment: &Comment, spam: bool) {
+        let category = if spam { "spam" } else { "ham" };
+        // Si el moderador cambia de opinión se deshace el entrenamiento anterior
+        let result = match comment.trained_as.as_deref() {
+            Some(previous) if previous != category => {
+                match Bayes::train(pool, comment, previous, -1).await {
+                    Ok(_) => Bayes::train(pool, comment, category, 1).await,
+                    Err(e) => Err(e),
+                }
+            }
+            Some(_) => Ok(()),
+            None => Bayes::train(pool, comment, category, 1).await,
+        };
+        if let Err(e) = result {
+            error!("Error training spam classifier with comment {}: {:?}", comment.id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_lowercase_words_of_reasonable_length() {
+        let tokens = tokens(" Ana ", "Compra YA en casino-online, $100 de regalo! a");
+        assert!(tokens.contains("compra"));
+        assert!(tokens.contains("casino"));
+        assert!(tokens.contains("online"));
+        assert!(tokens.contains("$100"));
+        assert!(tokens.contains("regalo"));
+        assert!(tokens.contains("name:ana"));
+        assert!(!tokens.contains("ya"));
+        assert!(!tokens.contains("a"));
+        let long = "x".repeat(31);
+        assert!(!super::tokens("", &long).iter().any(|token| token == &long));
+        assert!(!super::tokens("  ", "hola").iter().any(|token| token.starts_with("name:")));
+    }
+
+    #[test]
+    fn combine_needs_known_tokens() {
+        assert_eq!(Bayes::combine(&[], 10, 10), None);
+    }
+
+    #[test]
+    fn combine_leans_towards_the_class_of_the_tokens() {
+        let spammy = Bayes::combine(&[(9, 0), (8, 1), (7, 0)], 10, 10).unwrap();
+        assert!(spammy > 0.9, "{}", spammy);
+        let hammy = Bayes::combine(&[(0, 9), (1, 8), (0, 7)], 10, 10).unwrap();
+        assert!(hammy < 0.1, "{}", hammy);
+        let neutral = Bayes::combine(&[(5, 5)], 10, 10).unwrap();
+        assert!((neutral - 0.5).abs() < 1e-9, "{}", neutral);
+    }
+
+    #[test]
+    fn combine_weighs_rare_tokens_less() {
+        let rare = Bayes::combine(&[(1, 0)], 10, 10).unwrap();
+        let common = Bayes::combine(&[(20, 0)], 10, 10).unwrap();
+        assert!(rare < common, "{} < {}", rare, common);
+    }
+}
@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+
+use super::{SpamContext, SpamFilter, SpamScore};
+use crate::models::{NewComment, Setting};
+
+/// Validez del token del formulario de comentarios.
+const FORM_TOKEN_HOURS: i64 = 24;
+
+static LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)https?://|www\.").unwrap());
+
+fn score(filter: &str, score: f32, reason: impl Into<String>) -> Option<SpamScore> {
+    Some(SpamScore {
+        filter: filter.to_string(),
+        score,
+        reason: reason.into(),
+    })
+}
+
+/// Campo oculto del formulario que solo rellenan los bots.
+pub struct Honeypot;
+
+#[async_trait]
+impl SpamFilter for Honeypot {
+    fn name(&self) -> &'static str {
+        "honeypot"
+    }
+
+    async fn check(&self, _pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore> {
+        Honeypot::evaluate(context.comment)
+    }
+}
+
+impl Honeypot {
+    fn evaluate(comment: &NewComment) -> Option<SpamScore> {
+        match comment.honeypot.as_deref() {
+            Some(value) if !value.trim().is_empty() => {
+                score(Honeypot.name(), 1.0, "Honeypot field is filled")
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FormClaims {
+    post_id: i32,
+    iat: i64,
+    exp: i64,
+}
+
+/// Token firmado que se entrega al abrir el formulario. Sirve para saber
+/// cuánto tiempo ha pasado hasta el envío: los bots lo envían al instante.
+pub struct FormToken {
+    secret: String,
+}
+
+impl FormToken {
+    pub fn new(secret: &str) -> Self {
+        FormToken {
+            secret: secret.to_string(),
+        }
+    }
+
+    pub fn issue(&self, post_id: i32) -> Result<String, jsonwebtoken::errors::Error> {
+        let now = chrono::Utc::now();
+        let claims = FormClaims {
+            post_id,
+            iat: now.timestamp(),
+            exp: (now + chrono::Duration::hours(FORM_TOKEN_HOURS)).timestamp(),
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.secret.as_bytes()),
+        )
+    }
+}
+
+#[async_trait]
+impl SpamFilter for FormToken {
+    fn name(&self) -> &'static str {
+        "form_token"
+    }
+
+    async fn check(&self, pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore> {
+        let min_seconds: i64 = Setting::get_value(pool, "comments_min_submit_seconds", "3")
+            .await
+            .parse()
+            .unwrap_or(3);
+        self.evaluate(context.comment, min_seconds, chrono::Utc::now().timestamp())
+    }
+}
+
+impl FormToken {
+    /// `now` en segundos desde la época.
+    fn evaluate(&self, comment: &NewComment, min_seconds: i64, now: i64) -> Option<SpamScore> {
+        let Some(token) = comment.form_token.as_ref() else {
+            return score(self.name(), 0.5, "Form token is missing");
+        };
+        let claims = match decode::<FormClaims>(
+            token,
+            &DecodingKey::from_secret(self.secret.as_bytes()),
+            &Validation::default(),
+        ) {
+            Ok(data) => data.claims,
+            Err(e) => return score(self.name(), 0.5, format!("Invalid form token: {}", e)),
+        };
+        if claims.post_id != comment.post_id {
+            return score(self.name(), 0.5, "Form token belongs to another post");
+        }
+        let elapsed = now - claims.iat;
+        if elapsed < min_seconds {
+            return score(self.name(), 0.6, format!("Submitted after {} seconds", elapsed));
+        }
+        None
+    }
+}
+
+/// Demasiados enlaces en el contenido.
+pub struct LinkCount;
+
+#[async_trait]
+impl SpamFilter for LinkCount {
+    fn name(&self) -> &'static str {
+        "links"
+    }
+
+    async fn check(&self, pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore> {
+        let max_links: usize = Setting::get_value(pool, "comments_max_links", "2")
+            .await
+            .parse()
+            .unwrap_or(2);
+        LinkCount::evaluate(&context.comment.content, max_links)
+    }
+}
+
+impl LinkCount {
+    fn evaluate(content: &str, max_links: usize) -> Option<SpamScore> {
+        let links = LINK_REGEX.find_iter(content).count();
+        if links > max_links {
+            let extra = (links - max_links) as f32;
+            return score(
+                LinkCount.name(),
+                (0.3 + 0.2 * extra).min(1.0),
+                format!("{} links", links),
+            );
+        }
+        None
+    }
+}
+
+/// Palabras prohibidas de `comments_blocklist`, una por línea o separadas
+/// por comas.
+pub struct Blocklist;
+
+#[async_trait]
+impl SpamFilter for Blocklist {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    async fn check(&self, pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore> {
+        let blocklist = Setting::get_value(pool, "comments_blocklist", "").await;
+        Blocklist::evaluate(&blocklist, context.comment)
+    }
+}
+
+impl Blocklist {
+    fn evaluate(blocklist: &str, comment: &NewComment) -> Option<SpamScore> {
+        let text = format!(
+            "{}\n{}",
+            comment.nikename.to_lowercase(),
+            comment.content.to_lowercase()
+        );
+        blocklist
+            .split([',', '\n'])
+            .map(|word| word.trim().to_lowercase())
+            .find(|word| !word.is_empty() && text.contains(word.as_str()))
+            .and_then(|word| score(Blocklist.name(), 1.0, format!("Blocked word '{}'", word)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(content: &str) -> NewComment {
+        serde_json::from_value(serde_json::json!({
+            "post_id": 7,
+            "parent_id": null,
+            "nikename": "Ana",
+            "content": content,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn honeypot_flags_filled_field() {
+        let mut new_comment = comment("Hola");
+        assert!(Honeypot::evaluate(&new_comment).is_none());
+        new_comment.honeypot = Some("   ".to_string());
+        assert!(Honeypot::evaluate(&new_comment).is_none());
+        new_comment.honeypot = Some("http://spam.example".to_string());
+        let result = Honeypot::evaluate(&new_comment).unwrap();
+        assert_eq!(result.filter, "honeypot");
+        assert_eq!(result.score, 1.0);
+    }
+
+    #[test]
+    fn form_token_accepts_slow_submission() {
+        let form_token = FormToken::new("secret");
+        let mut new_comment = comment("Hola");
+        new_comment.form_token = Some(form_token.issue(7).unwrap());
+        let now = chrono::Utc::now().timestamp();
+        assert!(form_token.evaluate(&new_comment, 3, now + 10).is_none());
+    }
+
+    #[test]
+    fn form_token_flags_fast_submission() {
+        let form_token = FormToken::new("secret");
+        let mut new_comment = comment("Hola");
+        new_comment.form_token = Some(form_token.issue(7).unwrap());
+        let now = chrono::Utc::now().timestamp();
+        let result = form_token.evaluate(&new_comment, 3, now).unwrap();
+        assert_eq!(result.score, 0.6);
+    }
+
+    #[test]
+    fn form_token_flags_missing_forged_and_foreign_tokens() {
+        let form_token = FormToken::new("secret");
+        let now = chrono::Utc::now().timestamp() + 10;
+        let mut new_comment = comment("Hola");
+        assert_eq!(form_token.evaluate(&new_comment, 3, now).unwrap().reason, "Form token is missing");
+
+        new_comment.form_token = Some(FormToken::new("other").issue(7).unwrap());
+        let result = form_token.evaluate(&new_comment, 3, now).unwrap();
+        assert!(result.reason.starts_with("Invalid form token"));
+
+        new_comment.form_token = Some(form_token.issue(8).unwrap());
+        let result = form_token.evaluate(&new_comment, 3, now).unwrap();
+        assert_eq!(result.reason, "Form token belongs to another post");
+    }
+
+    #[test]
+    fn link_count_scores_links_over_the_limit() {
+        assert!(LinkCount::evaluate("Mira https://a.example y www.b.example", 2).is_none());
+        let result = LinkCount::evaluate("https://a.example http://b.example WWW.c.example https://d.example", 2);
+        let result = result.unwrap();
+        assert_eq!(result.reason, "4 links");
+        assert!((result.score - 0.7).abs() < f32::EPSILON);
+        let many = "https://x.example ".repeat(20);
+        assert_eq!(LinkCount::evaluate(&many, 2).unwrap().score, 1.0);
+    }
+
+    #[test]
+    fn blocklist_matches_name_and_content_ignoring_case() {
+        let blocklist = "casino,\n  Viagra \n,";
+        assert!(Blocklist::evaluate(blocklist, &comment("Buen artículo")).is_none());
+        let result = Blocklist::evaluate(blocklist, &comment("Compra VIAGRA barata")).unwrap();
+        assert_eq!(result.reason, "Blocked word 'viagra'");
+        let mut new_comment = comment("Hola");
+        new_comment.nikename = "Casino Royale".to_string();
+        assert!(Blocklist::evaluate(blocklist, &new_comment).is_some());
+        assert!(Blocklist::evaluate("", &new_comment).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+mod akismet;
+mod bayes;
+mod heuristics;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use tracing::{debug, error};
+
+use crate::models::{Comment, NewComment, Setting};
+
+pub use akismet::Akismet;
+pub use bayes::Bayes;
+pub use heuristics::{Blocklist, FormToken, Honeypot, LinkCount};
+
+/// Puntuación a partir de la cual un comentario se considera spam.
+const DEFAULT_THRESHOLD: &str = "0.9";
+
+/// Datos del comentario y de la petición que reciben los filtros.
+#[derive(Debug)]
+pub struct SpamContext<'a> {
+    pub comment: &'a NewComment,
+    pub referrer: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpamScore {
+    pub filter: String,
+    pub score: f32,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpamVerdict {
+    pub score: f32,
+    pub spam: bool,
+    pub scores: Vec<SpamScore>,
+}
+
+/// Un paso del filtro de spam. Devuelve `None` si no tiene nada que decir
+/// del comentario y una puntuación (1.0 es spam seguro) en otro caso.
+#[async_trait]
+pub trait SpamFilter: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn check(&self, pool: &PgPool, context: &SpamContext<'_>) -> Option<SpamScore>;
+
+    /// Decisión del moderador, para los filtros que aprenden de ella.
+    async fn learn(&self, _pool: &PgPool, _comment: &Comment, _spam: bool) {}
+}
+
+/// Cadena de filtros que se aplica a cada comentario nuevo.
+pub struct SpamPipeline {
+    filters: Vec<Box<dyn SpamFilter>>,
+}
+
+impl SpamPipeline {
+    pub fn new() -> Self {
+        SpamPipeline { filters: Vec::new() }
+    }
+
+    pub fn with(mut self, filter: impl SpamFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Filtros incluidos por defecto.
+    pub fn default_filters(secret: &str, base_url: &str) -> Self {
+        SpamPipeline::new()
+            .with(Honeypot)
+            .with(FormToken::new(secret))
+            .with(LinkCount)
+            .with(Blocklist)
+            .with(Bayes)
+            .with(Akismet::new(base_url))
+    }
+
+    pub async fn evaluate(&self, pool: &PgPool, context: &SpamContext<'_>) -> SpamVerdict {
+        let mut scores = Vec::new();
+        for filter in &self.filters {
+            if let Some(score) = filter.check(pool, context).await {
+                debug!("Spam filter {}: {:?}", filter.name(), score);
+                scores.push(score);
+            }
+        }
+        let score = scores.iter().fold(0.0, |total, s| total + s.score);
+        let threshold: f32 = Setting::get_value(pool, "comments_spam_threshold", DEFAULT_THRESHOLD)
+            .await
+            .parse()
+            .unwrap_or(0.9);
+        SpamVerdict {
+            score,
+            spam: score >= threshold,
+            scores,
+        }
+    }
+
+    pub async fn learn(&self, pool: &PgPool, comments: &[Comment], spam: bool) {
+        let category = if spam { "spam" } else { "ham" };
+        for comment in comments {
+            if comment.trained_as.as_deref() == Some(category) {
+                continue;
+            }
+            for filter in &self.filters {
+                filter.learn(pool, comment, spam).await;
+            }
+            if let Err(e) = Comment::set_trained_as(pool, comment.id, Some(category)).await {
+                error!("Error saving training of comment {}: {:?}", comment.id, e);
+            }
+        }
+    }
+}
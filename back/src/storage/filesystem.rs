@@ -0,0 +1,79 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use tokio::fs;
+use url::Url;
+
+use super::{Storage, StorageError};
+
+/// Ficheros en un directorio local.
+pub struct FileSystemStorage {
+    root: PathBuf,
+    base_url: Url,
+}
+
+impl FileSystemStorage {
+    pub fn new(root: PathBuf, base_url: Url) -> Self {
+        FileSystemStorage { root, base_url }
+    }
+
+    /// Ruta en disco; no se admite salir del directorio raíz.
+    fn file_path(&self, path: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(path);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(format!("Invalid storage path '{}'", path).into());
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl Storage for FileSystemStorage {
+    async fn put(&self, path: &str, data: &[u8], _content_type: &str) -> Result<(), StorageError> {
+        let file_path = self.file_path(path)?;
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&file_path, data).await?;
+        Ok(())
+    }
+
+    async fn put_file(&self, path: &str, source: &Path, _content_type: &str) -> Result<(), StorageError> {
+        let file_path = self.file_path(path)?;
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // Entre discos distintos no se puede renombrar
+        if fs::rename(source, &file_path).await.is_err() {
+            fs::copy(source, &file_path).await?;
+            fs::remove_file(source).await?;
+        }
+        Ok(())
+    }
+
+    async fn get(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        Ok(fs::read(self.file_path(path)?).await?)
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        Ok(fs::try_exists(self.file_path(path)?).await?)
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.file_path(path)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
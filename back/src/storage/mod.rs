@@ -0,0 +1,76 @@
+mod filesystem;
+mod s3;
+
+use std::{env::var, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use tracing::info;
+use url::Url;
+
+pub use filesystem::FileSystemStorage;
+pub use s3::S3Storage;
+
+pub type StorageError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Dónde se guardan los ficheros subidos. Se elige con `STORAGE_BACKEND`:
+/// `filesystem` (por defecto) o `s3`. Las rutas son relativas, p. ej.
+/// `2025/12/24/<uuid>.png`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn put(&self, path: &str, data: &[u8], content_type: &str) -> Result<(), StorageError>;
+
+    /// Guarda un fichero local sin cargarlo entero en memoria. El origen
+    /// se puede mover, así que después no hay que contar con él.
+    async fn put_file(&self, path: &str, source: &Path, content_type: &str) -> Result<(), StorageError>;
+
+    async fn get(&self, path: &str) -> Result<Vec<u8>, StorageError>;
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError>;
+
+    /// Borrar algo que no existe no es un error.
+    async fn delete(&self, path: &str) -> Result<(), StorageError>;
+
+    /// URL pública bajo la que se sirven los ficheros, acabada en `/`.
+    fn base_url(&self) -> &Url;
+
+    /// Directorio local, si los ficheros están en disco y hay que servirlos.
+    fn local_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    fn url(&self, path: &str) -> String {
+        match self.base_url().join(path) {
+            Ok(url) => url.to_string(),
+            Err(_) => format!("{}{}", self.base_url(), path),
+        }
+    }
+}
+
+/// URL acabada en `/` para que `join` añada la ruta en lugar de
+/// sustituir el último segmento.
+fn directory_url(url: &str) -> Result<Url, StorageError> {
+    let mut url = Url::parse(url)?;
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    Ok(url)
+}
+
+/// Configura el almacenamiento a partir del entorno. En disco los ficheros
+/// van a `STORAGE_DIR` (por defecto `uploads` dentro de los estáticos) y se
+/// sirven desde `STORAGE_PUBLIC_URL` (por defecto `<base_url>/uploads/`).
+pub fn from_env(base_url: &str, static_dir: &Path) -> Result<Arc<dyn Storage>, StorageError> {
+    let backend = var("STORAGE_BACKEND").unwrap_or("filesystem".to_string());
+    info!("Storage backend: {}", backend);
+    match backend.as_str() {
+        "s3" => Ok(Arc::new(S3Storage::from_env()?)),
+        "filesystem" => {
+            let root = var("STORAGE_DIR")
+                .map(Into::into)
+                .unwrap_or(static_dir.join("uploads"));
+            let public_url = var("STORAGE_PUBLIC_URL").unwrap_or(format!("{}/uploads/", base_url));
+            Ok(Arc::new(FileSystemStorage::new(root, directory_url(&public_url)?)))
+        }
+        other => Err(format!("Unknown STORAGE_BACKEND '{}'", other).into()),
+    }
+}
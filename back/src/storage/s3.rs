@@ -0,0 +1,405 @@
+use std::{env::var, path::Path};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{
+    Client, Method, Response, StatusCode,
+    header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, ETAG},
+};
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncReadExt};
+use tracing::error;
+use url::Url;
+
+use super::{Storage, StorageError, directory_url};
+use crate::utils::xml_escape;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Los nombres llevan un UUID, así que el contenido nunca cambia.
+const OBJECT_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Tamaño de cada parte de una subida multiparte (S3 pide al menos 5 MB).
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Objetos en un bucket S3 o compatible (MinIO, Garage, R2...) con
+/// direcciones de estilo ruta, `<endpoint>/<bucket>/<ruta>`. Se configura
+/// con `S3_ENDPOINT`, `S3_BUCKET`, `S3_REGION`, `S3_ACCESS_KEY_ID`,
+/// `S3_SECRET_ACCESS_KEY` y, si los ficheros se sirven desde otro sitio
+/// (un CDN), `S3_PUBLIC_URL`.
+pub struct S3Storage {
+    client: Client,
+    bucket_url: Url,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    base_url: Url,
+}
+
+impl S3Storage {
+    pub fn from_env() -> Result<Self, StorageError> {
+        let mandatory = |name: &str| var(name).map_err(|_| format!("{} environment mandatory", name));
+        let endpoint = mandatory("S3_ENDPOINT")?;
+        let bucket = mandatory("S3_BUCKET")?;
+        let bucket_url = directory_url(&format!("{}/{}", endpoint.trim_end_matches('/'), bucket))?;
+        let base_url = match var("S3_PUBLIC_URL") {
+            Ok(public_url) => directory_url(&public_url)?,
+            Err(_) => bucket_url.clone(),
+        };
+        Ok(S3Storage {
+            client: Client::new(),
+            bucket_url,
+            region: var("S3_REGION").unwrap_or("us-east-1".to_string()),
+            access_key_id: mandatory("S3_ACCESS_KEY_ID")?,
+            secret_access_key: mandatory("S3_SECRET_ACCESS_KEY")?,
+            base_url,
+        })
+    }
+
+    /// Petición firmada con AWS Signature Version 4.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<Response, StorageError> {
+        let mut url = self.bucket_url.join(&encode_path(path))?;
+        let query = canonical_query(query);
+        if !query.is_empty() {
+            url.set_query(Some(&query));
+        }
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let headers = [
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        let signed_headers = signed_headers(&headers);
+        let canonical_request = canonical_request(method.as_str(), url.path(), &query, &headers, &payload_hash);
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = string_to_sign(&amz_date, &scope, &canonical_request);
+        let key = signing_key(&self.secret_access_key, &date, &self.region, "s3");
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        );
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(AUTHORIZATION, authorization);
+        if let Some(content_type) = content_type {
+            request = request
+                .header(CONTENT_TYPE, content_type)
+                .header(CACHE_CONTROL, OBJECT_CACHE_CONTROL);
+        }
+        Ok(request.body(body).send().await?)
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, path: &str, data: &[u8], content_type: &str) -> Result<(), StorageError> {
+        let response = self
+            .request(Method::PUT, path, &[], data.to_vec(), Some(content_type))
+            .await?;
+        check(response, "PUT", path).await?;
+        Ok(())
+    }
+
+    /// Los ficheros grandes se suben por partes, leyendo una cada vez.
+    async fn put_file(&self, path: &str, source: &Path, content_type: &str) -> Result<(), StorageError> {
+        let mut file = File::open(source).await?;
+        if file.metadata().await?.len() <= MULTIPART_PART_SIZE as u64 {
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).await?;
+            return self.put(path, &data, content_type).await;
+        }
+        let response = self
+            .request(Method::POST, path, &[("uploads", "")], Vec::new(), Some(content_type))
+            .await?;
+        let body = check(response, "POST", path).await?.text().await?;
+        let upload_id = xml_value(&body, "UploadId").ok_or("S3 multipart upload without UploadId")?;
+        let result = self.upload_parts(path, &upload_id, &mut file).await;
+        if result.is_err() {
+            let query = [("uploadId", upload_id.as_str())];
+            if let Err(e) = self.request(Method::DELETE, path, &query, Vec::new(), None).await {
+                error!("Error aborting S3 upload '{}': {:?}", path, e);
+            }
+        }
+        result
+    }
+
+    async fn get(&self, path: &str) -> Result<Vec<u8>, StorageError> {
+        let response = self.request(Method::GET, path, &[], Vec::new(), None).await?;
+        Ok(check(response, "GET", path).await?.bytes().await?.to_vec())
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, StorageError> {
+        let response = self.request(Method::HEAD, path, &[], Vec::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        check(response, "HEAD", path).await?;
+        Ok(true)
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), StorageError> {
+        let response = self.request(Method::DELETE, path, &[], Vec::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(response, "DELETE", path).await?;
+        Ok(())
+    }
+
+    fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+}
+
+impl S3Storage {
+    async fn upload_parts(&self, path: &str, upload_id: &str, file: &mut File) -> Result<(), StorageError> {
+        let mut parts = String::new();
+        for part_number in 1.. {
+            let mut part = Vec::with_capacity(MULTIPART_PART_SIZE);
+            (&mut *file).take(MULTIPART_PART_SIZE as u64).read_to_end(&mut part).await?;
+            if part.is_empty() {
+                break;
+            }
+            let part_number = part_number.to_string();
+            let query = [("partNumber", part_number.as_str()), ("uploadId", upload_id)];
+            let response = self.request(Method::PUT, path, &query, part, None).await?;
+            let response = check(response, "PUT", path).await?;
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|value| value.to_str().ok())
+                .ok_or("S3 part without ETag")?;
+            parts.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                part_number,
+                xml_escape(etag)
+            ));
+        }
+        let body = format!("<CompleteMultipartUpload>{}</CompleteMultipartUpload>", parts);
+        let query = [("uploadId", upload_id)];
+        let response = self.request(Method::POST, path, &query, body.into_bytes(), None).await?;
+        // S3 puede contestar 200 y avisar del error en el cuerpo
+        let body = check(response, "POST", path).await?.text().await?;
+        if body.contains("<Error>") {
+            return Err(format!("S3 POST '{}' failed: {}", path, body.trim()).into());
+        }
+        Ok(())
+    }
+}
+
+async fn check(response: Response, method: &str, path: &str) -> Result<Response, StorageError> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("S3 {} '{}' failed with {}: {}", method, path, status, body.trim()).into())
+}
+
+/// Petición canónica de SigV4. La query ya va en su forma canónica (ver
+/// [`canonical_query`]) y las cabeceras en minúsculas y ordenadas por
+/// nombre.
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        path,
+        query,
+        canonical_headers,
+        signed_headers(headers),
+        payload_hash
+    )
+}
+
+/// Parámetros codificados y ordenados por nombre; los que no tienen valor
+/// quedan como `nombre=`.
+fn canonical_query(params: &[(&str, &str)]) -> String {
+    let mut params: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode_component(name), encode_component(value)))
+        .collect();
+    params.sort();
+    params.join("&")
+}
+
+fn signed_headers(headers: &[(&str, &str)]) -> String {
+    headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";")
+}
+
+fn string_to_sign(amz_date: &str, scope: &str, canonical_request: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    )
+}
+
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let mut key = hmac_sha256(format!("AWS4{}", secret_access_key).as_bytes(), date.as_bytes());
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    key
+}
+
+/// Texto de la primera etiqueta `<name>` de una respuesta XML.
+fn xml_value(xml: &str, name: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(xml[start..end].to_string())
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Codifica la ruta como pide la firma: todo salvo los caracteres no
+/// reservados y `/`.
+fn encode_path(path: &str) -> String {
+    encode(path, true)
+}
+
+/// Como [`encode_path`], pero codificando también `/`.
+fn encode_component(value: &str) -> String {
+    encode(value, false)
+}
+
+fn encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') || (keep_slash && byte == b'/') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    /// `get-vanilla` de la batería de pruebas de SigV4 de AWS.
+    #[test]
+    fn signs_aws_get_vanilla() {
+        let headers = [("host", "example.amazonaws.com"), ("x-amz-date", "20150830T123600Z")];
+        let canonical = canonical_request("GET", "/", "", &headers, EMPTY_PAYLOAD_HASH);
+        assert_eq!(
+            canonical,
+            "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n\
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let string_to_sign = string_to_sign("20150830T123600Z", "20150830/us-east-1/service/aws4_request", &canonical);
+        assert_eq!(
+            string_to_sign,
+            "AWS4-HMAC-SHA256\n20150830T123600Z\n20150830/us-east-1/service/aws4_request\n\
+             bb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63"
+        );
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20150830", "us-east-1", "service");
+        assert_eq!(
+            hex::encode(hmac_sha256(&key, string_to_sign.as_bytes())),
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    /// `post-vanilla` de la misma batería.
+    #[test]
+    fn signs_aws_post_vanilla() {
+        let headers = [("host", "example.amazonaws.com"), ("x-amz-date", "20150830T123600Z")];
+        let canonical = canonical_request("POST", "/", "", &headers, EMPTY_PAYLOAD_HASH);
+        let string_to_sign = string_to_sign("20150830T123600Z", "20150830/us-east-1/service/aws4_request", &canonical);
+        assert!(string_to_sign.ends_with("553f88c9e4d10fc9e109e2aeb65f030801b70c2f6468faca261d401ae622fc87"));
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20150830", "us-east-1", "service");
+        assert_eq!(
+            hex::encode(hmac_sha256(&key, string_to_sign.as_bytes())),
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    /// Ejemplo "GET Object" de la documentación de S3, con las mismas
+    /// cabeceras firmadas que usa `request` más `Range`.
+    #[test]
+    fn signs_aws_s3_get_object_example() {
+        let headers = [
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("range", "bytes=0-9"),
+            ("x-amz-content-sha256", EMPTY_PAYLOAD_HASH),
+            ("x-amz-date", "20130524T000000Z"),
+        ];
+        let canonical = canonical_request("GET", "/test.txt", "", &headers, EMPTY_PAYLOAD_HASH);
+        assert_eq!(signed_headers(&headers), "host;range;x-amz-content-sha256;x-amz-date");
+        let string_to_sign = string_to_sign("20130524T000000Z", "20130524/us-east-1/s3/aws4_request", &canonical);
+        assert!(string_to_sign.ends_with("7344ae5b7ee6c3e7e6b0fe0640412a37625d1fbfff95c48bbb2dc43964946972"));
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY", "20130524", "us-east-1", "s3");
+        assert_eq!(
+            hex::encode(hmac_sha256(&key, string_to_sign.as_bytes())),
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    /// `get-vanilla-query-order-key-case` de la batería de AWS.
+    #[test]
+    fn signs_aws_query_in_canonical_order() {
+        let headers = [("host", "example.amazonaws.com"), ("x-amz-date", "20150830T123600Z")];
+        let query = canonical_query(&[("Param2", "value2"), ("Param1", "value1")]);
+        assert_eq!(query, "Param1=value1&Param2=value2");
+        let canonical = canonical_request("GET", "/", &query, &headers, EMPTY_PAYLOAD_HASH);
+        let string_to_sign = string_to_sign("20150830T123600Z", "20150830/us-east-1/service/aws4_request", &canonical);
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20150830", "us-east-1", "service");
+        assert_eq!(
+            hex::encode(hmac_sha256(&key, string_to_sign.as_bytes())),
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+        assert_eq!(canonical_query(&[("uploads", "")]), "uploads=");
+        assert_eq!(canonical_query(&[("uploadId", "a/b+c")]), "uploadId=a%2Fb%2Bc");
+    }
+
+    #[test]
+    fn reads_xml_values() {
+        let xml = "<InitiateMultipartUploadResult><Bucket>b</Bucket><UploadId>abc.123</UploadId></InitiateMultipartUploadResult>";
+        assert_eq!(xml_value(xml, "UploadId").as_deref(), Some("abc.123"));
+        assert_eq!(xml_value(xml, "Key"), None);
+    }
+
+    #[test]
+    fn encodes_path_like_sigv4() {
+        assert_eq!(encode_path("2025/01/02/a b+ñ.png"), "2025/01/02/a%20b%2B%C3%B1.png");
+        assert_eq!(encode_path("-_.~/"), "-_.~/");
+    }
+}
@@ -1,5 +1,26 @@
-use comrak::Options;
+use std::{
+    env::var,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{HeaderMap, StatusCode, header, request::Parts},
+};
+use chrono::Utc;
+use comrak::{Arena, Options, format_html, nodes::NodeValue, parse_document};
 use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use sha2::{Digest, Sha256};
+
+use crate::media::responsive_images;
+use crate::models::{ApiResponse, AppState};
 
 // Define las opciones una sola vez al inicio.
 static MARKDOWN_OPTIONS: Lazy<Options> = Lazy::new(|| {
@@ -33,6 +54,412 @@ static MARKDOWN_OPTIONS: Lazy<Options> = Lazy::new(|| {
     options
 });
 
+// Perfil restringido para los comentarios: sin HTML crudo ni imágenes.
+static COMMENT_MARKDOWN_OPTIONS: Lazy<Options> = Lazy::new(|| {
+    let mut options = Options::default();
+    options.extension.strikethrough = true; // ~~texto tachado~~
+    options.extension.autolink = true; // Enlazado automático (www.ejemplo.com)
+    options.render.escape = true; // El HTML crudo se muestra escapado
+    options
+});
+
+static BREAK_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<br\s*/?>|</p>").unwrap());
+static HTML_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+static LINK_TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<a href=""#).unwrap());
+
+/// Renderiza el markdown de posts y páginas. Las imágenes de la biblioteca
+/// de medios salen con sus medidas y variantes.
 pub fn markdown_to_html(markdown: &str) -> String {
-    comrak::markdown_to_html(markdown, &MARKDOWN_OPTIONS)
+    responsive_images(&comrak::markdown_to_html(markdown, &MARKDOWN_OPTIONS))
+}
+
+/// Renderiza el markdown de un comentario: las imágenes se convierten en
+/// enlaces y todos los enlaces llevan `rel="nofollow ugc"`.
+pub fn comment_markdown_to_html(markdown: &str) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, &COMMENT_MARKDOWN_OPTIONS);
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        if let NodeValue::Image(link) = &data.value {
+            data.value = NodeValue::Link(link.clone());
+        }
+    }
+    let mut html = String::new();
+    if format_html(root, &COMMENT_MARKDOWN_OPTIONS, &mut html).is_err() {
+        return xml_escape(markdown);
+    }
+    LINK_TAG_REGEX
+        .replace_all(&html, r#"<a rel="nofollow ugc" href=""#)
+        .to_string()
+}
+
+/// Escapa los caracteres especiales para incluir texto en XML/HTML.
+pub fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Avatar de Gravatar a partir del email (hash SHA-256).
+pub fn gravatar_url(email: &str) -> String {
+    let hash = Sha256::digest(email.trim().to_lowercase().as_bytes());
+    format!("https://www.gravatar.com/avatar/{}?d=identicon", hex::encode(hash))
+}
+
+/// Solo se aceptan URLs http(s) públicas. `ALLOW_PRIVATE_URLS=true`
+/// permite direcciones locales para pruebas.
+pub fn parse_public_url(url: &str) -> Option<reqwest::Url> {
+    let url = reqwest::Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?.trim_start_matches('[').trim_end_matches(']');
+    if allow_private_urls() {
+        return Some(url);
+    }
+    if host.eq_ignore_ascii_case("localhost") || host.ends_with(".localhost") {
+        return None;
+    }
+    match host.parse::<IpAddr>() {
+        Ok(ip) => is_public_ip(ip).then_some(url),
+        Err(_) => Some(url),
+    }
+}
+
+fn allow_private_urls() -> bool {
+    var("ALLOW_PRIVATE_URLS").is_ok_and(|v| v == "true")
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resuelve los nombres descartando las direcciones privadas, así un
+/// dominio que apunta a la red local no sirve para llegar a ella.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| allow_private_urls() || is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} does not resolve to a public address", host).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Cliente para pedir URLs que llegan de fuera: solo conecta con
+/// direcciones públicas y revisa cada redirección (hasta `max_redirects`).
+pub fn public_client(user_agent: &str, max_redirects: usize) -> reqwest::Client {
+    let policy = if max_redirects == 0 {
+        redirect::Policy::none()
+    } else {
+        redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                attempt.error("Too many redirects")
+            } else if parse_public_url(attempt.url().as_str()).is_none() {
+                attempt.error("Redirect to a non public url")
+            } else {
+                attempt.follow()
+            }
+        })
+    };
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent(user_agent)
+        .redirect(policy)
+        .dns_resolver(PublicResolver)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Lee el cuerpo de la respuesta hasta `max_size` bytes; no basta con
+/// mirar `Content-Length` porque puede faltar o mentir.
+pub async fn read_limited(mut response: reqwest::Response, max_size: usize) -> Result<Vec<u8>, String> {
+    if response.content_length().is_some_and(|length| length > max_size as u64) {
+        return Err("Response too large".to_string());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("Error reading response: {:?}", e))? {
+        if body.len() + chunk.len() > max_size {
+            return Err("Response too large".to_string());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Texto plano aproximado de un fragmento HTML (p. ej. el `content` de una
+/// nota de ActivityPub): conserva los saltos de párrafo y quita etiquetas.
+pub fn html_to_text(html: &str) -> String {
+    let text = BREAK_TAG_REGEX.replace_all(html, "\n");
+    let text = HTML_TAG_REGEX.replace_all(&text, "");
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+/// Proxies de los que se fían las cabeceras `X-Forwarded-For` y
+/// `X-Real-IP`: IPs o redes (`10.0.0.0/8`) separadas por comas en
+/// `TRUSTED_PROXIES`. Sin proxies se usa la IP de la conexión.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl TrustedProxies {
+    pub fn from_env() -> Self {
+        TrustedProxies::parse(&var("TRUSTED_PROXIES").unwrap_or_default())
+    }
+
+    pub fn parse(value: &str) -> Self {
+        let networks = value
+            .split(',')
+            .map(str::trim)
+            .filter(|network| !network.is_empty())
+            .filter_map(|network| {
+                let (ip, prefix) = network.split_once('/').unwrap_or((network, ""));
+                let ip: IpAddr = ip.parse().ok()?;
+                let max = if ip.is_ipv4() { 32 } else { 128 };
+                let prefix = if prefix.is_empty() { max } else { prefix.parse().ok()? };
+                (prefix <= max).then_some((ip, prefix))
+            })
+            .collect();
+        TrustedProxies(networks)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        self.0.iter().any(|(network, prefix)| match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        })
+    }
+}
+
+/// IP del cliente. Las cabeceras del proxy solo cuentan si la conexión
+/// viene de uno de confianza; en `X-Forwarded-For` se recorre de derecha a
+/// izquierda hasta la primera IP que no es de un proxy.
+pub fn client_ip(headers: &HeaderMap, peer: IpAddr, proxies: &TrustedProxies) -> IpAddr {
+    if !proxies.contains(peer) {
+        return peer;
+    }
+    let header_value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let forwarded: Vec<IpAddr> = header_value("x-forwarded-for")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+    if let Some(ip) = forwarded.iter().rev().find(|ip| !proxies.contains(**ip)) {
+        return *ip;
+    }
+    if let Some(ip) = forwarded.first() {
+        return *ip;
+    }
+    header_value("x-real-ip")
+        .and_then(|ip| ip.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// Extractor con la IP del cliente (ver [`client_ip`]). Necesita que el
+/// servidor guarde la dirección de la conexión (`ConnectInfo`).
+pub struct ClientIp(pub IpAddr);
+
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = ApiResponse;
+
+    async fn from_request_parts(parts: &mut Parts, app_state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .ok_or_else(|| ApiResponse::new(StatusCode::INTERNAL_SERVER_ERROR, "Missing connection info", None))?;
+        Ok(ClientIp(client_ip(&parts.headers, peer.ip(), &app_state.trusted_proxies)))
+    }
+}
+
+/// Identificador anónimo del visitante: hash de IP y user agent con una
+/// sal que cambia cada día, así no se puede seguir a nadie entre días.
+pub fn visitor_hash(secret: &str, headers: &HeaderMap, ip: IpAddr) -> String {
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let day = Utc::now().date_naive();
+    let hash = Sha256::digest(format!("{}|{}|{}|{}", secret, day, ip, user_agent).as_bytes());
+    hex::encode(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_ips_exclude_local_networks() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn trusted_proxies_match_addresses_and_networks() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8, 192.168.1.5,fd00::/8, nonsense, 1.2.3.4/40");
+        assert!(proxies.contains("10.20.30.40".parse().unwrap()));
+        assert!(proxies.contains("192.168.1.5".parse().unwrap()));
+        assert!(proxies.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("fd12::1".parse().unwrap()));
+        assert!(!proxies.contains("192.168.1.6".parse().unwrap()));
+        assert!(!proxies.contains("1.2.3.4".parse().unwrap()));
+        assert!(!TrustedProxies::default().contains("10.0.0.1".parse().unwrap()));
+        assert!(TrustedProxies::parse("0.0.0.0/0").contains("8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn client_ip_ignores_forwarded_headers_from_untrusted_peers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.1.1.1".parse().unwrap());
+        headers.insert("x-real-ip", "2.2.2.2".parse().unwrap());
+        let peer: IpAddr = "9.9.9.9".parse().unwrap();
+        assert_eq!(client_ip(&headers, peer, &TrustedProxies::default()), peer);
+        assert_eq!(client_ip(&headers, peer, &TrustedProxies::parse("10.0.0.1")), peer);
+    }
+
+    #[test]
+    fn client_ip_skips_trusted_proxies_in_forwarded_for() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8");
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        // El primer valor lo puede poner el cliente; cuenta el que añadió el proxy
+        headers.insert("x-forwarded-for", "6.6.6.6, 5.5.5.5, 10.0.0.2".parse().unwrap());
+        assert_eq!(client_ip(&headers, peer, &proxies), "5.5.5.5".parse::<IpAddr>().unwrap());
+        headers.insert("x-forwarded-for", "10.0.0.3".parse().unwrap());
+        assert_eq!(client_ip(&headers, peer, &proxies), "10.0.0.3".parse::<IpAddr>().unwrap());
+        headers.remove("x-forwarded-for");
+        headers.insert("x-real-ip", "7.7.7.7".parse().unwrap());
+        assert_eq!(client_ip(&headers, peer, &proxies), "7.7.7.7".parse::<IpAddr>().unwrap());
+        headers.remove("x-real-ip");
+        assert_eq!(client_ip(&headers, peer, &proxies), peer);
+    }
+
+    #[tokio::test]
+    async fn public_client_refuses_names_resolving_to_local_addresses() {
+        let error = public_client("test", 0).get("http://localhost:9/").send().await.unwrap_err();
+        assert!(format!("{:?}", error).contains("does not resolve to a public address"), "{:?}", error);
+    }
+
+    #[test]
+    fn comment_markdown_escapes_raw_html() {
+        let html =
+            comment_markdown_to_html("<script>alert(1)</script>\n\nHola <b onclick=\"x()\">tú</b>");
+        assert!(!html.contains("<script"), "{}", html);
+        assert!(!html.contains("<b "), "{}", html);
+        assert!(html.contains("&lt;script&gt;"), "{}", html);
+    }
+
+    #[test]
+    fn comment_markdown_neutralizes_dangerous_links() {
+        for markdown in [
+            "[clic](javascript:alert(1))",
+            "[clic](JavaScript:alert(1))",
+            "[clic](data:text/html;base64,PHNjcmlwdD4=)",
+            "<javascript:alert(1)>",
+        ] {
+            let html = comment_markdown_to_html(markdown);
+            assert!(html.contains(r#"href="""#), "{}", html);
+            assert!(
+                !html.to_lowercase().contains(r#"href="javascript"#),
+                "{}",
+                html
+            );
+            assert!(!html.contains(r#"href="data"#), "{}", html);
+        }
+    }
+
+    #[test]
+    fn comment_markdown_turns_images_into_links() {
+        let html = comment_markdown_to_html("![gato](https://example.com/gato.png)");
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(
+            html.contains(r#"<a rel="nofollow ugc" href="https://example.com/gato.png">gato</a>"#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn comment_markdown_marks_every_link_as_ugc() {
+        let html = comment_markdown_to_html(
+            "[uno](https://uno.example) y https://dos.example y www.tres.example",
+        );
+        assert_eq!(html.matches("<a ").count(), 3, "{}", html);
+        assert_eq!(
+            html.matches(r#"<a rel="nofollow ugc" href=""#).count(),
+            3,
+            "{}",
+            html
+        );
+    }
 }
@@ -0,0 +1,266 @@
+use std::{sync::Arc, time::Duration};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{StatusCode, Url, header};
+use tracing::{debug, error, info};
+
+use crate::constants::WEBMENTION_INTERVAL_SECONDS;
+use crate::models::{
+    AppState, Comment, CommentKind, Post, RemoteComment, SlugRedirect, Webmention, WebmentionDirection,
+};
+use crate::utils::{markdown_to_html, parse_public_url, public_client, read_limited};
+
+// Tamaño máximo de la página que se descarga para verificar o descubrir
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+const WORKER_BATCH: i64 = 20;
+
+static CLIENT: Lazy<reqwest::Client> =
+    Lazy::new(|| public_client(concat!("blog-webmention/", env!("CARGO_PKG_VERSION")), 5));
+static HREF_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"href="([^"]+)""#).unwrap());
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<(?:link|a)\b[^>]*>").unwrap());
+static REL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\brel\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+static TAG_HREF_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+static TITLE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+/// Resultado de procesar una webmention de la cola.
+#[derive(Debug)]
+enum Outcome {
+    Done,
+    Retry(String),
+    Fail(String),
+}
+
+/// Post al que apunta `target`, siempre que sea público y esté publicado.
+pub async fn target_post(app_state: &AppState, target: &str) -> Option<Post> {
+    let path = target.strip_prefix(&app_state.base_url)?.strip_prefix('/')?;
+    let slug = path.split(['?', '#']).next()?.trim_end_matches('/');
+    if slug.is_empty() || slug.contains('/') {
+        return None;
+    }
+    // Los enlaces a un slug antiguo siguen valiendo para su post
+    let post = match Post::read_by_slug(&app_state.pool, slug).await {
+        Ok(post) => post,
+        Err(_) => {
+            let redirect = SlugRedirect::read_by_slug(&app_state.pool, slug).await.ok()??;
+            Post::read(&app_state.pool, redirect.post_id).await.ok()?
+        }
+    };
+    post.is_public().then_some(post)
+}
+
+/// Encola un aviso para cada enlace externo del post. Solo para posts
+/// públicos ya publicados; el envío lo hace el `worker`.
+pub async fn enqueue_for_post(app_state: &AppState, post: &Post) {
+    if !post.is_public() {
+        return;
+    }
+    let source = format!("{}/{}", app_state.base_url, post.slug);
+    for target in external_links(&app_state.base_url, &post.content) {
+        match Webmention::enqueue(&app_state.pool, WebmentionDirection::Outgoing, &source, &target).await {
+            Ok(webmention) => debug!("Webmention queued: {:?}", webmention),
+            Err(e) => error!("Error queuing webmention to {}: {:?}", target, e),
+        }
+    }
+}
+
+fn external_links(base_url: &str, markdown: &str) -> Vec<String> {
+    let html = markdown_to_html(markdown);
+    let mut links: Vec<String> = HREF_REGEX
+        .captures_iter(&html)
+        .filter_map(|cap| cap.get(1))
+        .map(|href| href.as_str().replace("&amp;", "&"))
+        .filter(|href| !href.starts_with(base_url) && parse_public_url(href).is_some())
+        .collect();
+    links.sort();
+    links.dedup();
+    links
+}
+
+/// Revisa la cola periódicamente y procesa las webmentions pendientes.
+pub async fn worker(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(WEBMENTION_INTERVAL_SECONDS));
+    loop {
+        interval.tick().await;
+        let due = match Webmention::read_due(&app_state.pool, WORKER_BATCH).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("Error reading webmention queue: {:?}", e);
+                continue;
+            }
+        };
+        for webmention in due {
+            process(&app_state, &webmention).await;
+        }
+    }
+}
+
+async fn process(app_state: &AppState, webmention: &Webmention) {
+    let outcome = match webmention.direction {
+        WebmentionDirection::Incoming => receive(app_state, &webmention.source, &webmention.target).await,
+        WebmentionDirection::Outgoing => send(&webmention.source, &webmention.target).await,
+    };
+    info!(
+        "Webmention {} ({} -> {}): {:?}",
+        webmention.id, webmention.source, webmention.target, outcome
+    );
+    let result = match outcome {
+        Outcome::Done => Webmention::mark_done(&app_state.pool, webmention.id).await,
+        Outcome::Retry(msg) => Webmention::retry(&app_state.pool, webmention.id, &msg).await,
+        Outcome::Fail(msg) => Webmention::mark_failed(&app_state.pool, webmention.id, &msg).await,
+    };
+    if let Err(e) = result {
+        error!("Error updating webmention {}: {:?}", webmention.id, e);
+    }
+}
+
+/// Verifica que `source` enlaza al post y la guarda como comentario
+/// pendiente de moderación. Si la fuente ya no existe o no enlaza, se borra.
+async fn receive(app_state: &AppState, source: &str, target: &str) -> Outcome {
+    let Some(post) = target_post(app_state, target).await else {
+        return Outcome::Fail("Target is not a published post".to_string());
+    };
+    let Some(source_url) = parse_public_url(source) else {
+        return Outcome::Fail("Invalid source".to_string());
+    };
+    let body = match fetch(source_url.clone()).await {
+        Ok((status, body)) if status.is_success() => body,
+        Ok((status, _)) if status.is_server_error() => {
+            return Outcome::Retry(format!("Source returned {}", status));
+        }
+        Ok((status, _)) => {
+            remove(app_state, post.id, source).await;
+            return Outcome::Fail(format!("Source returned {}", status));
+        }
+        Err(msg) => return Outcome::Retry(msg),
+    };
+    if !body.contains(target) {
+        remove(app_state, post.id, source).await;
+        return Outcome::Fail("Source does not link to target".to_string());
+    }
+    let host = source_url.host_str().unwrap_or_default().to_string();
+    let title = TITLE_REGEX
+        .captures(&body)
+        .and_then(|cap| cap.get(1))
+        .map(|title| title.as_str().split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|title| !title.is_empty())
+        .unwrap_or(host.clone());
+    let content = format!("Mencionado en [{}]({})", title.replace(['[', ']'], ""), source);
+    let remote = RemoteComment {
+        kind: CommentKind::Webmention,
+        post_id: post.id,
+        source_url: source.to_string(),
+        nikename: host,
+        website: Some(source.to_string()),
+        avatar_url: None,
+        content,
+        author_url: None,
+    };
+    match Comment::upsert_remote(&app_state.pool, &remote).await {
+        Ok(comment) => {
+            app_state.notifier.comment_pending(app_state, &comment).await;
+            Outcome::Done
+        }
+        Err(e) => Outcome::Retry(format!("Error saving webmention: {:?}", e)),
+    }
+}
+
+async fn remove(app_state: &AppState, post_id: i32, source: &str) {
+    if let Err(e) = Comment::delete_remote(&app_state.pool, CommentKind::Webmention, Some(post_id), source, None).await {
+        error!("Error deleting webmention from {}: {:?}", source, e);
+    }
+}
+
+/// Descubre el endpoint de `target` y le notifica el enlace desde `source`.
+async fn send(source: &str, target: &str) -> Outcome {
+    let Some(target_url) = parse_public_url(target) else {
+        return Outcome::Fail("Invalid target".to_string());
+    };
+    let endpoint = match discover_endpoint(target_url).await {
+        Ok(Some(endpoint)) => endpoint,
+        Ok(None) => return Outcome::Fail("No webmention endpoint".to_string()),
+        Err(msg) => return Outcome::Retry(msg),
+    };
+    debug!("Webmention endpoint for {}: {}", target, endpoint);
+    let response = CLIENT
+        .post(endpoint)
+        .form(&[("source", source), ("target", target)])
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => Outcome::Done,
+        Ok(response) if response.status().is_server_error() || response.status() == StatusCode::TOO_MANY_REQUESTS => {
+            Outcome::Retry(format!("Endpoint returned {}", response.status()))
+        }
+        Ok(response) => Outcome::Fail(format!("Endpoint returned {}", response.status())),
+        Err(e) => Outcome::Retry(format!("Error sending webmention: {:?}", e)),
+    }
+}
+
+/// Busca el endpoint en la cabecera `Link` y, si no está, en los `<link>`
+/// y `<a>` con `rel="webmention"` del HTML.
+async fn discover_endpoint(target: Url) -> Result<Option<Url>, String> {
+    let response = CLIENT
+        .get(target)
+        .send()
+        .await
+        .map_err(|e| format!("Error fetching target: {:?}", e))?;
+    if response.status().is_server_error() {
+        return Err(format!("Target returned {}", response.status()));
+    }
+    let base = response.url().clone();
+    let from_header = response
+        .headers()
+        .get_all(header::LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let (url, params) = link.trim().split_once(';')?;
+            let is_webmention = params.split(';').any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("rel=")
+                    .is_some_and(|rel| has_webmention(rel.trim_matches('"')))
+            });
+            is_webmention.then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+        });
+    let href = match from_header {
+        Some(href) => Some(href),
+        None => {
+            let body = read_body(response).await?;
+            TAG_REGEX
+                .find_iter(&body)
+                .map(|tag| tag.as_str())
+                .filter(|tag| REL_REGEX.captures(tag).and_then(first_group).is_some_and(has_webmention))
+                .find_map(|tag| TAG_HREF_REGEX.captures(tag).and_then(first_group).map(|href| href.replace("&amp;", "&")))
+        }
+    };
+    Ok(href.and_then(|href| base.join(&href).ok()).filter(|url| parse_public_url(url.as_str()).is_some()))
+}
+
+fn first_group(cap: regex::Captures<'_>) -> Option<&str> {
+    cap.iter().skip(1).flatten().next().map(|group| group.as_str())
+}
+
+fn has_webmention(rel: &str) -> bool {
+    rel.split_whitespace().any(|value| value.eq_ignore_ascii_case("webmention"))
+}
+
+async fn fetch(url: Url) -> Result<(StatusCode, String), String> {
+    let response = CLIENT
+        .get(url)
+        .header(header::ACCEPT, "text/html")
+        .send()
+        .await
+        .map_err(|e| format!("Error fetching source: {:?}", e))?;
+    let status = response.status();
+    Ok((status, read_body(response).await?))
+}
+
+async fn read_body(response: reqwest::Response) -> Result<String, String> {
+    let body = read_limited(response, MAX_BODY_SIZE).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
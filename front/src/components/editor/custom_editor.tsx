@@ -12,6 +12,9 @@ async function uploadFile(file: File) {
 
     const ret = await fetch("http://localhost:3000/api/v1/uploads", {
         method: "POST",
+        headers: {
+            "Authorization": `Bearer ${localStorage.getItem("token")}`,
+        },
         body: body,
     });
     if (ret.status === 200) {